@@ -16,7 +16,14 @@ from .hooks import (
 
 # Importar las clases principales del framework
 try:
-    from ._core import VNode, Patch, EventHandler, LazyComponent, SuspenseComponent
+    from ._core import (
+        VNode,
+        Patch,
+        EventHandler,
+        LazyComponent,
+        SuspenseComponent,
+        PyxSyntaxError,
+    )
 except ImportError:
     import _core
 
@@ -25,7 +32,9 @@ except ImportError:
     EventHandler = _core.EventHandler
     LazyComponent = _core.LazyComponent
     SuspenseComponent = _core.SuspenseComponent
+    PyxSyntaxError = _core.PyxSyntaxError
 
+from .element import create_element, Fragment, lazy, Suspense
 from .server import ConnectionManager
 from .runtime import set_root
 
@@ -43,6 +52,11 @@ __all__ = [
     "EventHandler",
     "LazyComponent",
     "SuspenseComponent",
+    "create_element",
+    "Fragment",
+    "lazy",
+    "Suspense",
+    "PyxSyntaxError",
     # Server
     "ConnectionManager",
     "set_root",
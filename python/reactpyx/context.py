@@ -18,3 +18,22 @@ def set_current_session_id(session_id: str):
 
 def reset_current_session_id(token):
     _session_context.reset(token)
+
+
+# Instance path of the component being rendered, e.g. "App/0:Counter"
+# Hooks called without explicit ids keep their state under this path
+_component_path: ContextVar[Optional[str]] = ContextVar(
+    "component_path", default=None
+)
+
+
+def get_current_component_path() -> Optional[str]:
+    return _component_path.get()
+
+
+def set_current_component_path(path: Optional[str]):
+    return _component_path.set(path)
+
+
+def reset_current_component_path(token):
+    _component_path.reset(token)
@@ -0,0 +1,127 @@
+"""
+Element factory used by code compiled from .pyx files.
+
+The compiler turns ``<div className="x">Hello {name}</div>`` into
+``create_element("div", {"class": "x"}, ["Hello ", (name)])``, a fragment
+``<>...</>`` into ``create_element("#fragment", {}, [...])`` and a component
+``<ui.Button label="x" />`` into ``create_element(ui.Button, {"label": "x"}, [])``.
+"""
+
+import importlib
+import inspect
+from typing import Any, Callable, Dict, Iterable, List, Optional, Union
+
+from ._core import VNode
+from .context import reset_current_component_path, set_current_component_path
+
+# Tag of fragment nodes, whose children render in place of the fragment
+Fragment = "#fragment"
+
+# Package the compiled .pyx modules are built into
+COMPILED_PACKAGE = "components"
+
+
+def _flatten_children(children: Iterable[Any], out: List[VNode]) -> None:
+    for child in children:
+        # None and booleans render nothing, so `{cond and <p/>}` works
+        if child is None or isinstance(child, bool):
+            continue
+        if isinstance(child, VNode):
+            out.append(child)
+        elif isinstance(child, (list, tuple)) or hasattr(child, "__next__"):
+            _flatten_children(child, out)
+        else:
+            out.append(VNode.text(str(child)))
+
+
+def create_element(
+    tag: Union[str, Callable[..., Any]],
+    props: Optional[Dict[str, Any]] = None,
+    children: Optional[Iterable[Any]] = None,
+) -> VNode:
+    """Creates a VNode from a tag, its props and a list of children.
+
+    When ``tag`` is a component callable, the node is a component node: the
+    component is called with the props (plus ``children``) when rendered.
+    """
+    props = dict(props or {})
+    # Spread props may still use the JSX name
+    if "className" in props:
+        props["class"] = props.pop("className")
+    key = props.pop("key", None)
+    flat_children: List[VNode] = []
+    _flatten_children(children or [], flat_children)
+    if callable(tag):
+        if flat_children:
+            props["children"] = flat_children
+        return VNode.component(tag, props, None if key is None else str(key))
+    return VNode(
+        tag,
+        props,
+        flat_children,
+        False,
+        0,
+        None if key is None else str(key),
+    )
+
+
+def _takes_props(component: Callable[..., Any]) -> bool:
+    try:
+        parameters = inspect.signature(component).parameters.values()
+    except (TypeError, ValueError):
+        return True
+    return any(
+        p.kind
+        in (p.POSITIONAL_ONLY, p.POSITIONAL_OR_KEYWORD, p.VAR_POSITIONAL)
+        for p in parameters
+    )
+
+
+def render_component(
+    component: Callable[..., Any],
+    props: Dict[str, Any],
+    path: Optional[str] = None,
+) -> VNode:
+    """Calls a component, passing ``props`` unless it takes no arguments, and
+    returns its output as a VNode.
+
+    ``path`` is the component's instance path, under which the hooks it
+    calls keep their state.
+    """
+    token = set_current_component_path(path)
+    try:
+        result = component(props) if _takes_props(component) else component()
+    finally:
+        reset_current_component_path(token)
+    if isinstance(result, VNode):
+        return result
+    return create_element(Fragment, None, [result])
+
+
+def lazy(module: str, name: Optional[str] = None) -> Callable[[Dict[str, Any]], Any]:
+    """A component that imports the component ``name`` of the .pyx module
+    ``module`` (``"pages.Admin"`` for ``src/pages/Admin.pyx``) when it first
+    renders. ``name`` defaults to the last part of the module name.
+
+    The node target loads the module in its own chunk of the bundle.
+    """
+    name = name or module.rsplit(".", 1)[-1]
+    loaded: List[Callable[..., Any]] = []
+
+    def Lazy(props: Dict[str, Any]) -> Any:
+        if not loaded:
+            imported = importlib.import_module(f"{COMPILED_PACKAGE}.{module}")
+            loaded.append(getattr(imported, name))
+        component = loaded[0]
+        return component(props) if _takes_props(component) else component()
+
+    Lazy.__name__ = name
+    return Lazy
+
+
+def Suspense(props: Dict[str, Any]) -> VNode:
+    """Renders its children. Python components render synchronously, so the
+    ``fallback`` prop only shows on the node target, while a ``lazy``
+    component's chunk loads.
+    """
+    return create_element(Fragment, None, props.get("children"))
@@ -24,9 +24,14 @@ __all__ = [
 """
 ReactPyx Hooks Usage Guide
 
+In compiled .pyx components, use_state, use_reducer and use_effect_with_deps
+take no ids: the compiler passes each call site a hook_id, and the state is
+kept per component instance.
+
 1. use_state:
-   - Usage: value, set_value = use_state(component_id, key, initial_value)
-   - Example: count, setCount = use_state("counter", "count", 0)
+   - Usage: value, set_value = use_state(initial_value)
+   - Or: value, set_value = use_state(component_id, key, initial_value)
+   - Example: count, setCount = use_state(0)
 
 2. use_effect:
    - Usage: use_effect(effect_function)
@@ -34,8 +39,9 @@ ReactPyx Hooks Usage Guide
    - Runs on every render
 
 3. use_effect_with_deps:
-   - Usage: use_effect_with_deps(effect_id, effect_function, dependencies)
-   - Example: use_effect_with_deps("counter-effect", lambda deps: print(f"Count: {count}"), [count])
+   - Usage: use_effect_with_deps(effect_function, dependencies)
+   - Or: use_effect_with_deps(effect_id, effect_function, dependencies)
+   - Example: use_effect_with_deps(lambda deps: print(f"Count: {count}"), [count])
    - Only runs when dependencies change
 
 4. use_context:
@@ -43,9 +49,10 @@ ReactPyx Hooks Usage Guide
    - Example: theme = use_context("ThemeProvider", "theme")
 
 5. use_reducer:
-   - Usage: state, dispatch = use_reducer(component_id, key, reducer, initial_state)
+   - Usage: state, dispatch = use_reducer(reducer, initial_state)
+   - Or: state, dispatch = use_reducer(component_id, key, reducer, initial_state)
    - Example: 
-     state, dispatch = use_reducer("todo-app", "todos", lambda state, action: [...], [])
+     state, dispatch = use_reducer(lambda state, action: [...], [])
      dispatch({"type": "ADD_TODO", "payload": "New task"})
 
 6. use_lazy_state:
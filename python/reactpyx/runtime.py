@@ -1,6 +1,8 @@
+import sys
 from typing import Callable, Dict, Any, Optional, List
 from .registry import get_handler
-from ._core import VNode, diff_nodes, Patch
+from ._core import VNode, diff_nodes, Patch, format_pyx_traceback
+from .element import create_element
 
 _GLOBAL_ROOT: Optional[Callable] = None
 
@@ -10,6 +12,13 @@ def set_root(component: Callable):
     _GLOBAL_ROOT = component
 
 
+def report_exception(context: str, error: BaseException) -> None:
+    """Prints an exception with its frames mapped to the .pyx sources. The
+    traceback stays on the server: it holds file paths and source code."""
+    formatted = format_pyx_traceback(error)
+    print(f"{context}:\n{formatted}", file=sys.stderr)
+
+
 class RuntimeManager:
     def __init__(self, root_component: Optional[Callable] = None):
         self.root_component = root_component or _GLOBAL_ROOT
@@ -18,15 +27,22 @@ class RuntimeManager:
     def set_root(self, component_func: Callable):
         self.root_component = component_func
 
+    def _render_root(self) -> VNode:
+        # Rendering the root as a component node gives it and its child
+        # components instance paths, which scope the state of their hooks
+        return create_element(self.root_component).render_component()
+
     def render(self) -> str:
         if not self.root_component:
             return ""
 
-        # Execute component function to get VNode
-        # Note: This assumes the component function returns a VNode directly
-        # In a real React-like system, we need to handle the component tree recursively.
-        # For this prototype, we assume a simple tree.
-        vnode = self.root_component()
+        # Execute the root component to get its VNode; child components
+        # render lazily when the tree is rendered or diffed
+        try:
+            vnode = self._render_root()
+        except Exception as e:
+            report_exception("Error rendering component", e)
+            raise
         self.current_vdom = vnode
 
         # Render to HTML string
@@ -48,11 +64,15 @@ class RuntimeManager:
         try:
             handler(event_data)
         except Exception as e:
-            print(f"Error executing handler: {e}")
+            report_exception("Error executing handler", e)
             return {"error": str(e)}
 
         # Re-render después de las actualizaciones de estado
-        new_vdom = self.root_component()
+        try:
+            new_vdom = self._render_root()
+        except Exception as e:
+            report_exception("Error rendering component", e)
+            return {"error": str(e)}
 
         # Si no hay VDOM previo, enviamos reemplazo completo
         if self.current_vdom is None:
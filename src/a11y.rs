@@ -0,0 +1,330 @@
+//! Accessibility checks run by the compiler on every JSX element.
+//!
+//! The checks only look at HTML elements whose attributes are all known, so
+//! components and elements with `{...spread}` attributes are skipped. They
+//! report warnings, which a strict build turns into errors.
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::jsx_parser::{
+    JsxAttr, JsxAttrValue, JsxAttribute, JsxChild, JsxElement, LineIndex, PyCode, PyPart, Span,
+};
+use crate::jsx_transformer::collapse_jsx_text;
+use std::collections::HashSet;
+
+/// The WAI-ARIA 1.2 states and properties
+const ARIA_ATTRIBUTES: &[&str] = &[
+    "aria-activedescendant",
+    "aria-atomic",
+    "aria-autocomplete",
+    "aria-braillelabel",
+    "aria-brailleroledescription",
+    "aria-busy",
+    "aria-checked",
+    "aria-colcount",
+    "aria-colindex",
+    "aria-colindextext",
+    "aria-colspan",
+    "aria-controls",
+    "aria-current",
+    "aria-describedby",
+    "aria-description",
+    "aria-details",
+    "aria-disabled",
+    "aria-dropeffect",
+    "aria-errormessage",
+    "aria-expanded",
+    "aria-flowto",
+    "aria-grabbed",
+    "aria-haspopup",
+    "aria-hidden",
+    "aria-invalid",
+    "aria-keyshortcuts",
+    "aria-label",
+    "aria-labelledby",
+    "aria-level",
+    "aria-live",
+    "aria-modal",
+    "aria-multiline",
+    "aria-multiselectable",
+    "aria-orientation",
+    "aria-owns",
+    "aria-placeholder",
+    "aria-posinset",
+    "aria-pressed",
+    "aria-readonly",
+    "aria-relevant",
+    "aria-required",
+    "aria-roledescription",
+    "aria-rowcount",
+    "aria-rowindex",
+    "aria-rowindextext",
+    "aria-rowspan",
+    "aria-selected",
+    "aria-setsize",
+    "aria-sort",
+    "aria-valuemax",
+    "aria-valuemin",
+    "aria-valuenow",
+    "aria-valuetext",
+];
+
+/// Elements that handle clicks and keyboard input on their own
+const INTERACTIVE_ELEMENTS: &[&str] = &[
+    "a", "button", "details", "input", "label", "option", "select", "summary", "textarea",
+];
+
+/// `<input>` types that are not labelled by a `<label>`
+const UNLABELLED_INPUT_TYPES: &[&str] = &["hidden", "submit", "reset", "button", "image"];
+
+/// Checks the JSX of a parsed `.pyx` source, returning warnings in source
+/// order
+pub fn check_accessibility(source: &str, code: &PyCode) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        lines: LineIndex::new(source),
+        labelled_ids: HashSet::new(),
+        dynamic_labels: false,
+        diagnostics: Vec::new(),
+    };
+    checker.collect_labels(code);
+    checker.check_code(code, false);
+    checker.diagnostics.sort_by_key(|d| d.span.start);
+    checker.diagnostics
+}
+
+struct Checker {
+    lines: LineIndex,
+    /// `for` values of the `<label>`s in the file
+    labelled_ids: HashSet<String>,
+    /// Whether a `<label>` has a computed `for`, which may label any input
+    dynamic_labels: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn warn(&mut self, code: &'static str, message: String, span: Span, help: &str) {
+        self.diagnostics.push(
+            Diagnostic::new(Severity::Warning, code, message, span, &self.lines).with_help(help),
+        );
+    }
+
+    fn collect_labels(&mut self, code: &PyCode) {
+        code.for_each_element(&mut |element| {
+            if element.name != "label" {
+                return;
+            }
+            match label_target(element).map(|attribute| &attribute.value) {
+                Some(JsxAttrValue::Str { value, .. }) => {
+                    self.labelled_ids.insert(value.clone());
+                }
+                Some(_) => self.dynamic_labels = true,
+                None => {}
+            }
+        });
+    }
+
+    fn check_code(&mut self, code: &PyCode, in_label: bool) {
+        for part in &code.parts {
+            if let PyPart::Element(element) = part {
+                self.check_element(element, in_label);
+            }
+        }
+    }
+
+    fn check_element(&mut self, element: &JsxElement, in_label: bool) {
+        let in_label = in_label || element.name == "label";
+        for attr in &element.attributes {
+            match attr {
+                JsxAttr::Spread { code, .. } => self.check_code(code, in_label),
+                JsxAttr::Named(attribute) => {
+                    if let JsxAttrValue::Expr(code) = &attribute.value {
+                        self.check_code(code, in_label);
+                    }
+                }
+            }
+        }
+        for child in &element.children {
+            match child {
+                JsxChild::Expr { code, .. } => self.check_code(code, in_label),
+                JsxChild::Element(child) => self.check_element(child, in_label),
+                JsxChild::Text { .. } | JsxChild::Comment { .. } => {}
+            }
+        }
+
+        let has_spread = element
+            .attributes
+            .iter()
+            .any(|attr| matches!(attr, JsxAttr::Spread { .. }));
+        if element.is_component() || element.is_fragment() || has_spread {
+            return;
+        }
+        self.check_aria_attributes(element);
+        self.check_img_alt(element);
+        self.check_button_name(element);
+        self.check_form_label(element, in_label);
+        self.check_click_handler(element);
+    }
+
+    fn check_img_alt(&mut self, element: &JsxElement) {
+        let needs_alt = match element.name.as_str() {
+            "img" | "area" => true,
+            "input" => str_attribute(element, "type") == Some("image"),
+            _ => false,
+        };
+        if needs_alt && element.attribute("alt").is_none() && !is_labelled(element) {
+            self.warn(
+                "A11Y001",
+                format!("<{}> has no `alt` text", element.name),
+                element.name_span,
+                "describe the image in `alt`, or use `alt=\"\"` if it is decorative",
+            );
+        }
+    }
+
+    fn check_button_name(&mut self, element: &JsxElement) {
+        if element.name == "button" && !is_labelled(element) && !has_accessible_text(element) {
+            self.warn(
+                "A11Y002",
+                "<button> has no accessible text".to_string(),
+                element.name_span,
+                "add text content, or an `aria-label` for icon-only buttons",
+            );
+        }
+    }
+
+    fn check_form_label(&mut self, element: &JsxElement, in_label: bool) {
+        let needs_label = match element.name.as_str() {
+            "select" | "textarea" => true,
+            "input" => !str_attribute(element, "type")
+                .is_some_and(|kind| UNLABELLED_INPUT_TYPES.contains(&kind)),
+            _ => false,
+        };
+        if !needs_label || in_label || is_labelled(element) {
+            return;
+        }
+        let labelled = match element.attribute("id").map(|attribute| &attribute.value) {
+            Some(JsxAttrValue::Str { value, .. }) => {
+                self.labelled_ids.contains(value) || self.dynamic_labels
+            }
+            Some(_) => self.dynamic_labels || !self.labelled_ids.is_empty(),
+            None => false,
+        };
+        if !labelled {
+            self.warn(
+                "A11Y003",
+                format!("<{}> has no label", element.name),
+                element.name_span,
+                "wrap it in a <label>, point a <label htmlFor=...> at its `id`, or add an `aria-label`",
+            );
+        }
+    }
+
+    fn check_aria_attributes(&mut self, element: &JsxElement) {
+        for attr in &element.attributes {
+            let JsxAttr::Named(attribute) = attr else {
+                continue;
+            };
+            let name = attribute.name.as_str();
+            if !name.starts_with("aria-") || ARIA_ATTRIBUTES.contains(&name) {
+                continue;
+            }
+            let help = match closest_aria_attribute(name) {
+                Some(suggestion) => format!("did you mean `{}`?", suggestion),
+                None => "see the WAI-ARIA states and properties for the valid names".to_string(),
+            };
+            let span = Span::new(attribute.span.start, attribute.span.start + name.len());
+            self.warn(
+                "A11Y004",
+                format!("Unknown ARIA attribute `{}`", name),
+                span,
+                &help,
+            );
+        }
+    }
+
+    fn check_click_handler(&mut self, element: &JsxElement) {
+        if INTERACTIVE_ELEMENTS.contains(&element.name.as_str())
+            || element.attribute("role").is_some()
+        {
+            return;
+        }
+        let Some(handler) = element.attribute("onClick") else {
+            return;
+        };
+        let span = Span::new(handler.span.start, handler.span.start + "onClick".len());
+        self.warn(
+            "A11Y005",
+            format!("<{}> has `onClick` but is not interactive", element.name),
+            span,
+            "use a <button>, or add a `role`, `tabIndex={0}` and a keyboard handler",
+        );
+    }
+}
+
+/// The `htmlFor` (or `for`) attribute of a `<label>`
+fn label_target(label: &JsxElement) -> Option<&JsxAttribute> {
+    label
+        .attribute("htmlFor")
+        .or_else(|| label.attribute("for"))
+}
+
+/// The value of a quoted attribute
+fn str_attribute<'e>(element: &'e JsxElement, name: &str) -> Option<&'e str> {
+    match &element.attribute(name)?.value {
+        JsxAttrValue::Str { value, .. } => Some(value),
+        _ => None,
+    }
+}
+
+/// Whether the element is named by an ARIA attribute or a `title`
+fn is_labelled(element: &JsxElement) -> bool {
+    ["aria-label", "aria-labelledby", "title"]
+        .iter()
+        .any(|name| element.attribute(name).is_some())
+}
+
+/// Whether the content of an element gives it a name: text, an expression
+/// (which may render text), or an image with `alt` text
+fn has_accessible_text(element: &JsxElement) -> bool {
+    element.children.iter().any(|child| match child {
+        JsxChild::Text { value, .. } => collapse_jsx_text(value).is_some(),
+        JsxChild::Expr { .. } => true,
+        JsxChild::Comment { .. } => false,
+        JsxChild::Element(child) => {
+            if str_attribute(child, "aria-hidden") == Some("true") {
+                return false;
+            }
+            if child.is_component() || is_labelled(child) {
+                return true;
+            }
+            match child.attribute("alt").map(|attribute| &attribute.value) {
+                Some(JsxAttrValue::Str { value, .. }) => !value.is_empty(),
+                Some(_) => true,
+                None => has_accessible_text(child),
+            }
+        }
+    })
+}
+
+/// The known ARIA attribute closest to a misspelt one, if any is close
+fn closest_aria_attribute(name: &str) -> Option<&'static str> {
+    ARIA_ATTRIBUTES
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
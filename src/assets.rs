@@ -0,0 +1,239 @@
+//! Content-hashed names for the assets of a build.
+//!
+//! `reactpyx build` copies the `styles.css` and `bundle.js` of the output
+//! directory to names holding a hash of their content, such as
+//! `styles.3fa2c1d4.css`, so every deploy changes the URL of the assets that
+//! changed and browsers and CDNs can cache them for good.
+//! `asset-manifest.json` maps each logical name to its hashed file, size and
+//! Subresource Integrity hash, and [`asset_url`] and [`asset_tag`] resolve
+//! the names with it when pages are rendered. The fixed names are still
+//! written, for `reactpyx run`.
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use data_encoding::BASE64;
+use once_cell::sync::Lazy;
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha384};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// File of the manifest, in the output directory, apart from the bundler's
+/// `manifest.json`
+pub const ASSET_MANIFEST_FILE: &str = "asset-manifest.json";
+
+/// The assets given hashed names, by their logical name
+pub const HASHED_ASSETS: &[&str] = &["styles.css", "bundle.js"];
+
+/// Loaded manifests by path, reloaded when the file changes
+static MANIFEST_CACHE: Lazy<DashMap<PathBuf, Arc<LoadedManifest>>> = Lazy::new(DashMap::new);
+
+struct LoadedManifest {
+    modified: Option<SystemTime>,
+    manifest: AssetManifest,
+}
+
+/// The hashed file of an asset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Asset {
+    /// Path of the file in the output directory
+    pub file: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Subresource Integrity hash of the file (`sha384-...`)
+    pub integrity: String,
+    /// Its source map, when there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map: Option<String>,
+}
+
+/// The contents of `asset-manifest.json`: the hashed file of each asset, by
+/// its logical name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AssetManifest {
+    pub assets: BTreeMap<String, Asset>,
+}
+
+impl AssetManifest {
+    /// The manifest of `out_dir`, if it has one
+    pub fn load(out_dir: &Path) -> Result<Option<Self>> {
+        let path = out_dir.join(ASSET_MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content =
+            fs::read_to_string(&path).with_context(|| format!("Error reading {:?}", path))?;
+        let manifest =
+            serde_json::from_str(&content).with_context(|| format!("Invalid {:?}", path))?;
+        Ok(Some(manifest))
+    }
+
+    /// The asset with the logical name `name`
+    pub fn get(&self, name: &str) -> Option<&Asset> {
+        self.assets.get(name)
+    }
+}
+
+/// Copies the [`HASHED_ASSETS`] of `out_dir` to content-hashed names and
+/// writes `asset-manifest.json`. The hashed files of the previous manifest
+/// that are not in the new one are removed.
+pub fn hash_assets(out_dir: &Path) -> Result<AssetManifest> {
+    let previous = AssetManifest::load(out_dir).unwrap_or_default();
+
+    let mut manifest = AssetManifest::default();
+    for name in HASHED_ASSETS {
+        let path = out_dir.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        let mut content = fs::read(&path).with_context(|| format!("Error reading {:?}", path))?;
+        let hash = blake3::hash(&content).to_hex();
+        let file = match name.rsplit_once('.') {
+            Some((stem, extension)) => format!("{}.{}.{}", stem, &hash[..8], extension),
+            None => format!("{}.{}", name, &hash[..8]),
+        };
+
+        // The source map is copied too, and the file pointed at the copy
+        let map_comment = format!("//# sourceMappingURL={}.map", name);
+        let map_path = out_dir.join(format!("{}.map", name));
+        let map = match content.trim_ascii_end().ends_with(map_comment.as_bytes()) {
+            true if map_path.is_file() => {
+                let map = format!("{}.map", file);
+                fs::copy(&map_path, out_dir.join(&map))
+                    .with_context(|| format!("Error copying {:?}", map_path))?;
+                let end = content.trim_ascii_end().len();
+                content.truncate(end - map_comment.len());
+                content.extend_from_slice(format!("//# sourceMappingURL={}\n", map).as_bytes());
+                Some(map)
+            }
+            _ => None,
+        };
+
+        fs::write(out_dir.join(&file), &content)
+            .with_context(|| format!("Error writing {:?}", out_dir.join(&file)))?;
+        manifest.assets.insert(
+            name.to_string(),
+            Asset {
+                file,
+                size: content.len() as u64,
+                integrity: integrity(&content),
+                map,
+            },
+        );
+    }
+
+    let kept: Vec<&String> = manifest
+        .assets
+        .values()
+        .flat_map(|asset| std::iter::once(&asset.file).chain(&asset.map))
+        .collect();
+    for asset in previous.unwrap_or_default().assets.values() {
+        for file in std::iter::once(&asset.file).chain(&asset.map) {
+            if !kept.contains(&file) {
+                // Already gone is fine
+                let _ = fs::remove_file(out_dir.join(file));
+            }
+        }
+    }
+
+    fs::write(
+        out_dir.join(ASSET_MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )
+    .context("Error writing the asset manifest")?;
+    Ok(manifest)
+}
+
+/// The URL of the asset `name` of the build in `out_dir`, under `base_url`:
+/// its hashed file when the build has a manifest, `name` itself otherwise.
+#[pyfunction]
+#[pyo3(signature = (name, out_dir = "build", base_url = "/static/"))]
+pub fn asset_url(name: &str, out_dir: &str, base_url: &str) -> PyResult<String> {
+    let asset = resolve(name, Path::new(out_dir))?;
+    Ok(join_url(
+        base_url,
+        asset.as_ref().map_or(name, |asset| &asset.file),
+    ))
+}
+
+/// The `<link>` or `<script>` tag loading the asset `name`, with its
+/// integrity hash when the build has a manifest.
+#[pyfunction]
+#[pyo3(signature = (name, out_dir = "build", base_url = "/static/"))]
+pub fn asset_tag(name: &str, out_dir: &str, base_url: &str) -> PyResult<String> {
+    let asset = resolve(name, Path::new(out_dir))?;
+    let url = escape_attribute(&join_url(
+        base_url,
+        asset.as_ref().map_or(name, |asset| &asset.file),
+    ));
+    let integrity = asset.as_ref().map_or(String::new(), |asset| {
+        format!(
+            " integrity=\"{}\" crossorigin=\"anonymous\"",
+            asset.integrity
+        )
+    });
+    if name.ends_with(".css") {
+        Ok(format!(
+            "<link rel=\"stylesheet\" href=\"{}\"{}>",
+            url, integrity
+        ))
+    } else if name.ends_with(".js") {
+        Ok(format!("<script src=\"{}\"{}></script>", url, integrity))
+    } else {
+        Err(PyValueError::new_err(format!(
+            "No tag loads the asset `{}`; use asset_url",
+            name
+        )))
+    }
+}
+
+/// The asset `name` of the manifest of `out_dir`, or `None` without one
+fn resolve(name: &str, out_dir: &Path) -> PyResult<Option<Asset>> {
+    let path = out_dir.join(ASSET_MANIFEST_FILE);
+    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+    if modified.is_none() && !path.exists() {
+        return Ok(None);
+    }
+    let loaded = match MANIFEST_CACHE.get(&path) {
+        Some(loaded) if loaded.modified == modified => Arc::clone(&loaded),
+        _ => {
+            let manifest = AssetManifest::load(out_dir)
+                .map_err(|e| PyValueError::new_err(format!("{:#}", e)))?
+                .unwrap_or_default();
+            let loaded = Arc::new(LoadedManifest { modified, manifest });
+            MANIFEST_CACHE.insert(path.clone(), Arc::clone(&loaded));
+            loaded
+        }
+    };
+    match loaded.manifest.get(name) {
+        Some(asset) => Ok(Some(asset.clone())),
+        None => Err(PyKeyError::new_err(format!(
+            "No asset `{}` in {}",
+            name,
+            path.display()
+        ))),
+    }
+}
+
+fn join_url(base_url: &str, file: &str) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), file)
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+/// The Subresource Integrity hash of `content`. Browsers only accept SHA-2
+/// hashes there, so it is SHA-384 rather than the blake3 of the file names.
+pub fn integrity(content: &[u8]) -> String {
+    format!("sha384-{}", BASE64.encode(&Sha384::digest(content)))
+}
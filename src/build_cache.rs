@@ -0,0 +1,99 @@
+//! Incremental build cache, kept in `.reactpyx-cache.json` in the output
+//! directory (`build/` by default).
+//!
+//! Every file compiled without warnings is recorded with a blake3 hash of its
+//! source, its path and everything else its output depends on: the compiler
+//! version, the target, the compiler options and the set of project modules
+//! its imports are resolved against. A file whose hash is unchanged, and
+//! whose output is still there, is not compiled again. Files with warnings
+//! are always compiled, so their warnings are reported on every build.
+
+use crate::module_graph::ModuleImport;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// Name of the cache file in the output directory
+pub const CACHE_FILE: &str = ".reactpyx-cache.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BuildCache {
+    /// Entries by source path
+    files: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub hash: String,
+    /// CSS rules of the file, for the stylesheet bundle
+    pub css: Vec<String>,
+    /// Modules the file may import, for the module graph
+    #[serde(default)]
+    pub imports: Vec<ModuleImport>,
+}
+
+impl BuildCache {
+    /// Loads the cache of the output directory `out_dir`; a missing or
+    /// unreadable cache is empty
+    pub fn load(out_dir: &Path) -> Self {
+        std::fs::read_to_string(out_dir.join(CACHE_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, out_dir: &Path) -> anyhow::Result<()> {
+        std::fs::write(out_dir.join(CACHE_FILE), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// The entry of `file_path`, if it was compiled from content with `hash`
+    pub fn get(&self, file_path: &Path, hash: &str) -> Option<&CacheEntry> {
+        self.files
+            .get(&*file_path.to_string_lossy())
+            .filter(|entry| entry.hash == hash)
+    }
+
+    /// The entry of `file_path`, whatever content it was compiled from
+    pub fn get_any(&self, file_path: &Path) -> Option<&CacheEntry> {
+        self.files.get(&*file_path.to_string_lossy())
+    }
+
+    pub fn remove(&mut self, file_path: &Path) {
+        self.files.remove(&*file_path.to_string_lossy());
+    }
+
+    pub fn insert(&mut self, file_path: &Path, entry: CacheEntry) {
+        self.files
+            .insert(file_path.to_string_lossy().to_string(), entry);
+    }
+}
+
+/// Hashes the inputs of a build that are shared by all its files, with the
+/// compiler `options` serialized
+pub fn build_key(target_env: &str, options: &str, modules: &HashSet<String>) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(b"\0");
+    hasher.update(target_env.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(options.as_bytes());
+    let mut modules: Vec<&String> = modules.iter().collect();
+    modules.sort();
+    for module in modules {
+        hasher.update(b"\0");
+        hasher.update(module.as_bytes());
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Hashes a source file for the build with `build_key`
+pub fn file_hash(build_key: &str, file_path: &Path, source: &[u8]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(build_key.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(file_path.to_string_lossy().as_bytes());
+    hasher.update(b"\0");
+    hasher.update(source);
+    hasher.finalize().to_hex().to_string()
+}
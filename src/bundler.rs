@@ -0,0 +1,602 @@
+//! Bundles a `.pyx` entry and the modules it imports into one ES module.
+//!
+//! Every module is compiled for the `node` target, and its ES module becomes
+//! a factory registered with a small loader at the top of the bundle: its
+//! imports of other modules become `__require` calls, and its exports the
+//! getters the factory returns, so they stay live bindings. The imports of
+//! packages, such as React, are gathered at the top of the file.
+//!
+//! The modules only `lazy` components import are split into chunks, which
+//! the loader imports the first time one of these components renders. A
+//! module two `lazy` components need is in the chunk of the first, which the
+//! other then loads too. `manifest.json` lists the files written.
+//!
+//! Modules importing each other at the top level fail the bundle with the
+//! PYX018 error of the build, since the loader could not finish running them.
+
+use crate::compiler::{
+    compile_pyx_module, import_cycle_errors, project_modules, project_of, python_module_name,
+};
+use crate::config::Config;
+use crate::js_minifier::minify_js;
+use crate::module_graph::ModuleGraph;
+use crate::node_target::{emit_module, NODE_MODULE_EXTENSION};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use swc_core::common::sync::Lrc;
+use swc_core::common::{FileName, SourceMap};
+use swc_core::ecma::ast::*;
+use swc_core::ecma::parser::{lexer::Lexer, EsSyntax, Parser, StringInput, Syntax};
+use swc_core::ecma::visit::{VisitMut, VisitMutWith};
+use tokio::fs;
+
+/// File of the bundle, in the output directory
+pub const BUNDLE_FILE: &str = "bundle.js";
+
+/// Directory of the chunks, in the output directory
+pub const CHUNKS_DIR: &str = "chunks";
+
+/// File listing what was written, in the output directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Registers and runs the module factories. `__chunks` maps each module a
+/// `lazy` component imports to the chunks it needs.
+const LOADER: &str = r#"const __factories = {};
+const __modules = {};
+function __define(id, factory) {
+    __factories[id] = factory;
+}
+function __require(id) {
+    if (!(id in __modules)) {
+        const getters = __factories[id](__require, __load);
+        const module = __modules[id] = {};
+        for (const name of Object.keys(getters)) {
+            Object.defineProperty(module, name, { enumerable: true, get: getters[name] });
+        }
+    }
+    return __modules[id];
+}
+function __load(id) {
+    const chunks = (__chunks[id] || []).map((chunk) => import("./" + chunk));
+    return Promise.all(chunks).then((loaded) => {
+        for (const chunk of loaded) {
+            chunk.register(__define);
+        }
+        return __require(id);
+    });
+}
+"#;
+
+/// The files of a bundle and what they hold
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    /// The bundle, which imports the rest
+    pub entry: String,
+    /// Every file written, by its path in the output directory
+    pub files: BTreeMap<String, BundleFile>,
+    /// The chunks each module `lazy` components import needs
+    pub lazy: BTreeMap<String, Vec<String>>,
+}
+
+/// A file of a bundle
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleFile {
+    /// Modules the file defines, each after the modules it imports
+    pub modules: Vec<String>,
+    /// Packages the file imports, such as `react`
+    pub imports: Vec<String>,
+    /// Size in bytes
+    pub size: usize,
+    /// Its source map, when source maps are on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map: Option<String>,
+}
+
+/// A module compiled and turned into a factory of the loader
+struct LinkedModule {
+    /// The `__define` call registering the module
+    define: String,
+    /// Modules it imports, in order
+    imports: Vec<String>,
+    /// Modules its `lazy` components import
+    lazy: Vec<String>,
+    /// Names it imports from each package, local name first
+    packages: BTreeMap<String, BTreeMap<String, String>>,
+    /// Names it exports
+    exports: Vec<String>,
+}
+
+/// Bundles `entry_file`, a `.pyx` file in the `src` directory of a project,
+/// into `output_dir`, with the `config_path` configuration of the project
+pub async fn bundle(
+    entry_file: &Path,
+    config_path: &str,
+    output_dir: &Path,
+) -> Result<BundleManifest> {
+    if !entry_file.is_file() {
+        bail!("Input file does not exist: {}", entry_file.display());
+    }
+    let (project_root, pyx_files) = project_of(entry_file).await;
+    let config = Config::load_project(&project_root, config_path)?;
+    let defines = config.defines();
+    let modules = project_modules(&pyx_files);
+    let files: HashMap<String, PathBuf> = pyx_files
+        .iter()
+        .map(|file| (python_module_name(file), file.clone()))
+        .collect();
+
+    // Every module the entry loads, statically or through `lazy`
+    let entry = python_module_name(entry_file);
+    let mut linked: HashMap<String, LinkedModule> = HashMap::new();
+    let mut graph = ModuleGraph::new();
+    let mut found = vec![entry.clone()];
+    let mut queue = VecDeque::from([entry.clone()]);
+    while let Some(id) = queue.pop_front() {
+        let file = &files[&id];
+        let compiled = compile_pyx_module(file, "node", &modules, &defines).await?;
+        let es_module = compiled
+            .es_module
+            .expect("the node target writes ES modules");
+        let module = link_module(&id, &es_module, &files)
+            .with_context(|| format!("Error bundling {}", file.display()))?;
+        for next in module.imports.iter().chain(&module.lazy) {
+            if !found.contains(next) {
+                found.push(next.clone());
+                queue.push_back(next.clone());
+            }
+        }
+        graph.insert(&id, file, compiled.imports);
+        linked.insert(id, module);
+    }
+    if let Some(cycle) = import_cycle_errors(&graph).into_iter().next() {
+        return Err(cycle.error);
+    }
+
+    // The chunks, named after the first module that needs each
+    let main = static_closure(&entry, &linked);
+    let main_set: HashSet<&String> = main.iter().collect();
+    let roots: Vec<&String> =
+        found
+            .iter()
+            .flat_map(|id| &linked[id].lazy)
+            .fold(Vec::new(), |mut roots, root| {
+                if !roots.contains(&root) {
+                    roots.push(root);
+                }
+                roots
+            });
+    let mut owners: HashMap<String, String> = HashMap::new();
+    let mut chunks: Vec<(String, Vec<String>)> = Vec::new();
+    for root in &roots {
+        let owned: Vec<String> = static_closure(root, &linked)
+            .into_iter()
+            .filter(|id| !main_set.contains(id) && !owners.contains_key(id))
+            .collect();
+        if owned.is_empty() {
+            continue;
+        }
+        for id in &owned {
+            owners.insert(id.clone(), root.to_string());
+        }
+        chunks.push((root.to_string(), owned));
+    }
+
+    let mut manifest = BundleManifest {
+        entry: BUNDLE_FILE.to_string(),
+        ..Default::default()
+    };
+    let chunks_dir = output_dir.join(CHUNKS_DIR);
+    if chunks_dir.exists() {
+        fs::remove_dir_all(&chunks_dir)
+            .await
+            .with_context(|| format!("Error removing {:?}", chunks_dir))?;
+    }
+    let mut chunk_files = HashMap::new();
+    for (root, owned) in &chunks {
+        let mut code = package_imports(owned, &linked)?;
+        code.push_str("export function register(__define) {\n");
+        for id in owned {
+            code.push_str(&linked[id].define);
+        }
+        code.push_str("}\n");
+        let (code, map) = finish(code, &format!("{}.js", root), &config)?;
+        let hash = blake3::hash(code.as_bytes()).to_hex();
+        let path = format!("{}/{}-{}.js", CHUNKS_DIR, root, &hash[..8]);
+        let file = write_output(output_dir, &path, code, map, owned, &linked).await?;
+        manifest.files.insert(path.clone(), file);
+        chunk_files.insert(root.clone(), path);
+    }
+    for root in roots {
+        let mut needed = Vec::new();
+        for id in static_closure(root, &linked) {
+            if let Some(path) = owners.get(&id).map(|owner| &chunk_files[owner]) {
+                if !needed.contains(path) {
+                    needed.push(path.clone());
+                }
+            }
+        }
+        manifest.lazy.insert(root.clone(), needed);
+    }
+
+    let mut code = package_imports(&main, &linked)?;
+    code.push_str(&format!(
+        "const __chunks = {};\n",
+        serde_json::to_string(&manifest.lazy)?
+    ));
+    code.push_str(LOADER);
+    for id in &main {
+        code.push_str(&linked[id].define);
+    }
+    let exports = &linked[&entry].exports;
+    if exports.is_empty() {
+        code.push_str(&format!("__require({});\n", serde_json::to_string(&entry)?));
+    } else {
+        code.push_str(&format!(
+            "export const {{ {} }} = __require({});\n",
+            exports.join(", "),
+            serde_json::to_string(&entry)?
+        ));
+    }
+    let (code, map) = finish(code, BUNDLE_FILE, &config)?;
+    let file = write_output(output_dir, BUNDLE_FILE, code, map, &main, &linked).await?;
+    manifest.files.insert(BUNDLE_FILE.to_string(), file);
+
+    write_file(
+        output_dir,
+        MANIFEST_FILE,
+        &serde_json::to_string_pretty(&manifest)?,
+    )
+    .await?;
+    Ok(manifest)
+}
+
+/// Turns the ES module `code` of the module `id` into a factory
+fn link_module(id: &str, code: &str, files: &HashMap<String, PathBuf>) -> Result<LinkedModule> {
+    let cm: Lrc<SourceMap> = Default::default();
+    let fm = cm.new_source_file(Lrc::new(FileName::Custom(id.into())), code.to_string());
+    let lexer = Lexer::new(
+        Syntax::Es(EsSyntax::default()),
+        EsVersion::Es2022,
+        StringInput::from(&*fm),
+        None,
+    );
+    let module = Parser::new_from(lexer)
+        .parse_module()
+        .map_err(|e| anyhow::anyhow!("Error parsing the ES module of {}: {:?}", id, e))?;
+
+    let mut requires = String::new();
+    let mut imports = Vec::new();
+    let mut packages: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    let mut exports = Vec::new();
+    let mut body = Vec::new();
+    for item in module.body {
+        let decl = match item {
+            ModuleItem::Stmt(stmt) => {
+                body.push(ModuleItem::Stmt(stmt));
+                continue;
+            }
+            ModuleItem::ModuleDecl(decl) => decl,
+        };
+        match decl {
+            ModuleDecl::Import(import) => {
+                let source = import.src.value.to_string_lossy().to_string();
+                if !source.starts_with('.') {
+                    let names = packages.entry(source.clone()).or_default();
+                    for specifier in &import.specifiers {
+                        let ImportSpecifier::Named(named) = specifier else {
+                            bail!("{} imports `{}` as a whole", id, source);
+                        };
+                        let imported = match &named.imported {
+                            Some(name) => export_name(name),
+                            None => named.local.sym.to_string(),
+                        };
+                        names.insert(named.local.sym.to_string(), imported);
+                    }
+                    continue;
+                }
+
+                let dependency = resolve_specifier(id, &source, files)?;
+                let require = format!("__require({})", serde_json::to_string(&dependency)?);
+                let mut names = Vec::new();
+                for specifier in &import.specifiers {
+                    match specifier {
+                        ImportSpecifier::Named(named) => {
+                            let local = named.local.sym.to_string();
+                            names.push(match &named.imported {
+                                Some(name) if export_name(name) != local => {
+                                    format!("{}: {}", export_name(name), local)
+                                }
+                                _ => local,
+                            });
+                        }
+                        ImportSpecifier::Default(default) => {
+                            names.push(format!("default: {}", default.local.sym));
+                        }
+                        ImportSpecifier::Namespace(namespace) => {
+                            requires.push_str(&format!(
+                                "const {} = {};\n",
+                                namespace.local.sym, require
+                            ));
+                        }
+                    }
+                }
+                if !names.is_empty() {
+                    requires.push_str(&format!(
+                        "const {{ {} }} = {};\n",
+                        names.join(", "),
+                        require
+                    ));
+                }
+                if !imports.contains(&dependency) {
+                    imports.push(dependency);
+                }
+            }
+            ModuleDecl::ExportDecl(export) => {
+                match &export.decl {
+                    Decl::Fn(function) => exports.push(function.ident.sym.to_string()),
+                    Decl::Var(var) => {
+                        for declarator in &var.decls {
+                            pattern_names(&declarator.name, &mut exports);
+                        }
+                    }
+                    _ => bail!("{} exports a declaration the bundler does not know", id),
+                }
+                body.push(ModuleItem::Stmt(Stmt::Decl(export.decl)));
+            }
+            _ => bail!("{} has an export the bundler does not know", id),
+        }
+    }
+
+    let mut dynamic = DynamicImports {
+        id,
+        files,
+        lazy: Vec::new(),
+        error: None,
+    };
+    body.visit_mut_with(&mut dynamic);
+    if let Some(error) = dynamic.error {
+        return Err(error);
+    }
+
+    let getters: Vec<String> = exports
+        .iter()
+        .map(|name| format!("{0}: () => {0}", name))
+        .collect();
+    let define = format!(
+        "__define({}, function (__require, __load) {{\n{}{}return {{ {} }};\n}});\n",
+        serde_json::to_string(id)?,
+        requires,
+        emit_module(body),
+        getters.join(", ")
+    );
+    Ok(LinkedModule {
+        define,
+        imports,
+        lazy: dynamic.lazy,
+        packages,
+        exports,
+    })
+}
+
+/// Replaces the `import("./x.mjs")` of `lazy` components with `__load` calls
+struct DynamicImports<'a> {
+    id: &'a str,
+    files: &'a HashMap<String, PathBuf>,
+    lazy: Vec<String>,
+    error: Option<anyhow::Error>,
+}
+
+impl VisitMut for DynamicImports<'_> {
+    fn visit_mut_call_expr(&mut self, call: &mut CallExpr) {
+        call.visit_mut_children_with(self);
+        if !matches!(call.callee, Callee::Import(_)) {
+            return;
+        }
+        let source = match call.args.first().map(|arg| &*arg.expr) {
+            Some(Expr::Lit(Lit::Str(source))) => source.value.to_string_lossy().to_string(),
+            _ => {
+                self.error.get_or_insert_with(|| {
+                    anyhow::anyhow!("{} imports a computed module name", self.id)
+                });
+                return;
+            }
+        };
+        match resolve_specifier(self.id, &source, self.files) {
+            Ok(module) => {
+                call.callee = Callee::Expr(Box::new(Expr::Ident(Ident::new_no_ctxt(
+                    "__load".into(),
+                    Default::default(),
+                ))));
+                *call.args[0].expr = Expr::Lit(Lit::Str(module.as_str().into()));
+                if !self.lazy.contains(&module) {
+                    self.lazy.push(module);
+                }
+            }
+            Err(error) => {
+                self.error.get_or_insert(error);
+            }
+        }
+    }
+}
+
+/// The module an import `specifier` of the module `from` names, such as
+/// `components.Header` for `./components/Header.mjs` imported by `App`
+fn resolve_specifier(
+    from: &str,
+    specifier: &str,
+    files: &HashMap<String, PathBuf>,
+) -> Result<String> {
+    let mut parts: Vec<&str> = from.split('.').collect();
+    parts.pop();
+    let path = specifier
+        .strip_suffix(&format!(".{}", NODE_MODULE_EXTENSION))
+        .unwrap_or(specifier);
+    for segment in path.split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                parts.pop();
+            }
+            segment => parts.push(segment),
+        }
+    }
+    let module = parts.join(".");
+    let package = format!("{}.__init__", module);
+    if files.contains_key(&module) {
+        Ok(module)
+    } else if files.contains_key(&package) {
+        Ok(package)
+    } else {
+        bail!(
+            "{} imports `{}`, which is not a module of the project",
+            from,
+            specifier
+        )
+    }
+}
+
+/// `id` and the modules it imports, each after the modules it imports
+fn static_closure(id: &str, linked: &HashMap<String, LinkedModule>) -> Vec<String> {
+    fn visit<'a>(
+        id: &'a str,
+        linked: &'a HashMap<String, LinkedModule>,
+        seen: &mut HashSet<&'a str>,
+        order: &mut Vec<String>,
+    ) {
+        if !seen.insert(id) {
+            return;
+        }
+        for import in &linked[id].imports {
+            visit(import, linked, seen, order);
+        }
+        order.push(id.to_string());
+    }
+    let mut order = Vec::new();
+    visit(id, linked, &mut HashSet::new(), &mut order);
+    order
+}
+
+/// The package imports of `modules`, merged
+fn package_imports(modules: &[String], linked: &HashMap<String, LinkedModule>) -> Result<String> {
+    let mut packages: BTreeMap<&str, BTreeMap<&str, &str>> = BTreeMap::new();
+    for id in modules {
+        for (package, names) in &linked[id].packages {
+            let merged = packages.entry(package).or_default();
+            for (local, imported) in names {
+                if merged
+                    .insert(local, imported)
+                    .is_some_and(|other| other != imported)
+                {
+                    bail!("`{}` names two imports of `{}`", local, package);
+                }
+            }
+        }
+    }
+    let mut code = String::new();
+    for (package, names) in packages {
+        let names: Vec<String> = names
+            .into_iter()
+            .map(|(local, imported)| match local == imported {
+                true => local.to_string(),
+                false => format!("{} as {}", imported, local),
+            })
+            .collect();
+        code.push_str(&format!(
+            "import {{ {} }} from {};\n",
+            names.join(", "),
+            serde_json::to_string(package)?
+        ));
+    }
+    Ok(code)
+}
+
+/// Minifies `code` as the configuration says, with a source map naming it
+/// `source_name` when source maps are on
+fn finish(code: String, source_name: &str, config: &Config) -> Result<(String, Option<String>)> {
+    if !config.compiler_options.minify.js {
+        return Ok((code, None));
+    }
+    let source_name = config.source_maps().then_some(source_name);
+    minify_js(&code, &config.compiler_options.js_minify, source_name)
+        .context("JS minification failed")
+}
+
+/// Writes the file `path` of the bundle, defining `modules`, with its source
+/// map next to it
+async fn write_output(
+    output_dir: &Path,
+    path: &str,
+    mut code: String,
+    map: Option<String>,
+    modules: &[String],
+    linked: &HashMap<String, LinkedModule>,
+) -> Result<BundleFile> {
+    let map_path = match map {
+        Some(map) => {
+            let map_path = format!("{}.map", path);
+            let map_name = map_path.rsplit('/').next().unwrap();
+            code.push_str(&format!("\n//# sourceMappingURL={}\n", map_name));
+            write_file(output_dir, &map_path, &map).await?;
+            Some(map_path)
+        }
+        None => None,
+    };
+    write_file(output_dir, path, &code).await?;
+
+    let imports: BTreeSet<&String> = modules
+        .iter()
+        .flat_map(|id| linked[id].packages.keys())
+        .collect();
+    Ok(BundleFile {
+        modules: modules.to_vec(),
+        imports: imports.into_iter().cloned().collect(),
+        size: code.len(),
+        map: map_path,
+    })
+}
+
+async fn write_file(output_dir: &Path, path: &str, code: &str) -> Result<()> {
+    let path = output_dir.join(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Error creating {:?}", parent))?;
+    }
+    fs::write(&path, code)
+        .await
+        .with_context(|| format!("Error writing {:?}", path))
+}
+
+fn export_name(name: &ModuleExportName) -> String {
+    match name {
+        ModuleExportName::Ident(ident) => ident.sym.to_string(),
+        ModuleExportName::Str(name) => name.value.to_string_lossy().to_string(),
+    }
+}
+
+fn pattern_names(pattern: &Pat, names: &mut Vec<String>) {
+    match pattern {
+        Pat::Ident(ident) => names.push(ident.id.sym.to_string()),
+        Pat::Array(array) => {
+            for element in array.elems.iter().flatten() {
+                pattern_names(element, names);
+            }
+        }
+        Pat::Object(object) => {
+            for prop in &object.props {
+                match prop {
+                    ObjectPatProp::KeyValue(prop) => pattern_names(&prop.value, names),
+                    ObjectPatProp::Assign(prop) => names.push(prop.key.sym.to_string()),
+                    ObjectPatProp::Rest(rest) => pattern_names(&rest.arg, names),
+                }
+            }
+        }
+        Pat::Rest(rest) => pattern_names(&rest.arg, names),
+        Pat::Assign(assign) => pattern_names(&assign.left, names),
+        _ => {}
+    }
+}
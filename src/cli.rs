@@ -2,18 +2,27 @@ use anyhow::Context;
 use clap::Parser;
 use clap_derive::{Parser, Subcommand};
 use once_cell::sync::Lazy;
+use std::fs;
+use std::path::{Path, PathBuf};
 use tokio::runtime::Runtime;
 
 mod cli_build_project;
 mod cli_create_project;
+mod cli_format_files;
 mod cli_init_project;
 mod cli_install_library;
+mod cli_lint_files;
+mod cli_print_config;
 mod cli_run_server;
 
+use crate::config::{CONFIG_FILE, PRODUCTION};
 use cli_build_project::build_project;
 use cli_create_project::create_project;
+use cli_format_files::format_files;
 use cli_init_project::init_project;
 use cli_install_library::install_library;
+use cli_lint_files::{lint_files, list_rules};
+use cli_print_config::print_config;
 use cli_run_server::run_server;
 use log::{error, info};
 
@@ -26,6 +35,7 @@ static TOKIO_RUNTIME: Lazy<Runtime> = Lazy::new(|| {
 
 const ENV_OPTIONS: &[&str] = &["node", "python"];
 const ALLOWED_LIBRARIES: &[&str] = &["tailwind", "bootstrap"];
+const LINT_FORMATS: &[&str] = &["text", "json"];
 
 #[derive(Parser)]
 #[command(name = "reactpyx")]
@@ -44,7 +54,8 @@ enum Commands {
     },
     /// Initialize project (install dependencies)
     Init {
-        /// Specify the environment (development or production)
+        /// Environment of pyx.config.json (development, production or one of
+        /// its `env` overlays)
         #[arg(short, long, default_value = "development")]
         env: String,
     },
@@ -52,20 +63,66 @@ enum Commands {
     Run,
     /// Build the project for production (Node.js or Python)
     Build {
-        /// Deployment environment (node or python)
+        /// Environment of pyx.config.json (development, production or one of
+        /// its `env` overlays); `node` and `python` mean production for that
+        /// target
         #[arg(short, long)]
         env: String,
-        /// Output directory for compiled files
-        #[arg(short, long, default_value = "dist")]
-        output: String,
+        /// Deployment target (node or python)
+        #[arg(short, long)]
+        target: Option<String>,
+        /// Output directory for compiled files, instead of `outDir`
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Fail on accessibility warnings
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Format .pyx files
+    Fmt {
+        /// Files or directories to format
+        #[arg(default_value = "src")]
+        paths: Vec<String>,
+        /// Report unformatted files instead of writing them, failing if any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Check .pyx files for hook and JSX mistakes
+    Lint {
+        /// Files or directories to lint
+        #[arg(default_value = "src")]
+        paths: Vec<String>,
+        /// Output format (text or json)
+        #[arg(long, default_value = "text")]
+        format: String,
+        /// List the available rules and exit
+        #[arg(long)]
+        list_rules: bool,
     },
     /// Install a style library (e.g., tailwind, bootstrap)
     Install {
         /// Name of the library (e.g., tailwind)
         library: String,
     },
+    /// Inspect the project configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the configuration in effect, with the files it extends and the
+    /// environment overlay merged
+    Print {
+        /// Environment whose overlay is merged
+        #[arg(short, long, default_value = "development")]
+        env: String,
+    },
 }
 
+#[allow(dead_code)]
 pub fn run_cli() -> anyhow::Result<()> {
     let cli = Cli::parse();
     execute_cli(cli)
@@ -88,13 +145,6 @@ fn execute_cli(cli: Cli) -> anyhow::Result<()> {
         }
         Commands::Init { env } => {
             info!("Initializing project in {} mode", env);
-            if !["development", "production"].contains(&env.as_str()) {
-                error!(
-                    "Invalid environment: {}. Use 'development' or 'production'.",
-                    env
-                );
-                std::process::exit(1);
-            }
             init_project(&env).context("Error initializing project")?;
         }
         Commands::Run => {
@@ -103,16 +153,52 @@ fn execute_cli(cli: Cli) -> anyhow::Result<()> {
                 .block_on(run_server())
                 .context("Error running server")?;
         }
-        Commands::Build { env, output } => {
-            info!("Building project for {} environment", env);
-            if !ENV_OPTIONS.contains(&env.as_str()) {
-                error!("Unrecognized environment: {}. Use 'node' or 'python'.", env);
+        Commands::Build {
+            env,
+            target,
+            output,
+            strict,
+        } => {
+            // `--env node` and `--env python` predate configuration environments
+            let (env, target) = match (ENV_OPTIONS.contains(&env.as_str()), target) {
+                (true, None) => (PRODUCTION.to_string(), env),
+                (true, Some(_)) => {
+                    error!("--target cannot be combined with --env {}.", env);
+                    std::process::exit(1);
+                }
+                (false, target) => (env, target.unwrap_or_else(|| "python".to_string())),
+            };
+            info!(
+                "Building project for {} environment, {} target",
+                env, target
+            );
+            if !ENV_OPTIONS.contains(&target.as_str()) {
+                error!("Unrecognized target: {}. Use 'node' or 'python'.", target);
                 std::process::exit(1);
             }
             TOKIO_RUNTIME
-                .block_on(build_project(&output, &env))
+                .block_on(build_project(output.as_deref(), &env, &target, strict))
                 .context("Error building project")?;
         }
+        Commands::Fmt { paths, check } => {
+            info!("Formatting {:?}", paths);
+            format_files(&paths, check)?;
+        }
+        Commands::Lint {
+            paths,
+            format,
+            list_rules: list,
+        } => {
+            if list {
+                list_rules();
+                return Ok(());
+            }
+            if !LINT_FORMATS.contains(&format.as_str()) {
+                error!("Unrecognized format: {}. Use 'text' or 'json'.", format);
+                std::process::exit(1);
+            }
+            lint_files(&paths, CONFIG_FILE, &format)?;
+        }
         Commands::Install { library } => {
             info!("Installing library: {}", library);
             if !ALLOWED_LIBRARIES.contains(&library.as_str()) {
@@ -124,8 +210,30 @@ fn execute_cli(cli: Cli) -> anyhow::Result<()> {
             }
             install_library(&library).context("Error installing library")?;
         }
+        Commands::Config {
+            command: ConfigCommands::Print { env },
+        } => {
+            print_config(CONFIG_FILE, &env)?;
+        }
     }
 
     info!("Command executed successfully.");
     Ok(())
 }
+
+/// The `.pyx` files at `path`, which may be a file or a directory to search
+fn collect_pyx_files(path: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_pyx_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "pyx") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
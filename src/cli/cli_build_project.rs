@@ -1,12 +1,21 @@
-use crate::compiler::compile_all_pyx;
+use crate::assets::hash_assets;
+use crate::compiler::{check_entry, compile_all_pyx_with, BuildOptions, CompileSummary};
+use crate::config::{Config, CONFIG_FILE};
 use anyhow::Result;
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
-use log::{error, info};
+use log::info;
+use std::collections::HashSet;
 use std::env;
+use std::fs;
 use std::time::Duration;
 
-pub async fn build_project(output: &str, env: &str) -> Result<()> {
+pub async fn build_project(
+    output: Option<&str>,
+    env: &str,
+    target: &str,
+    strict: bool,
+) -> Result<()> {
     let pb = ProgressBar::new_spinner();
     pb.enable_steady_tick(Duration::from_millis(120));
     pb.set_style(
@@ -18,13 +27,75 @@ pub async fn build_project(output: &str, env: &str) -> Result<()> {
 
     // Get current directory as project root
     let project_root = env::current_dir()?.to_string_lossy().to_string();
-    let config_path = "pyx.config.json"; // Default config path
+    let mut config = match Config::load_project_env(&project_root, CONFIG_FILE, env) {
+        Ok(config) => config,
+        Err(e) => {
+            pb.finish_and_clear();
+            return Err(e);
+        }
+    };
+    if let Some(output) = output {
+        config.out_dir = output.to_string();
+    }
 
     // Compile all components
-    // This will generate Python files in build/components and styles.css
-    match compile_all_pyx(&project_root, config_path, env).await {
-        Ok((compiled_files, _)) => {
-            pb.set_message(format!("Compiled {} files", compiled_files.len()));
+    // This will generate Python files in <outDir>/components and styles.css
+    let options = BuildOptions {
+        strict,
+        ..BuildOptions::default()
+    };
+    match compile_all_pyx_with(&project_root, &config, target, options).await {
+        Ok(summary) => {
+            let CompileSummary {
+                compiled,
+                unchanged,
+                errors,
+                warnings,
+                ..
+            } = summary;
+            if !warnings.is_empty() || !errors.is_empty() {
+                pb.suspend(|| {
+                    for warning in &warnings {
+                        let file = warning.file.as_deref().unwrap_or_default();
+                        let source = fs::read_to_string(file).unwrap_or_default();
+                        eprintln!("{}", warning.render_colored(&source));
+                    }
+                });
+            }
+            if !errors.is_empty() {
+                pb.finish_and_clear();
+                for error in &errors {
+                    eprintln!("{}", error.render_colored());
+                }
+                let failed: HashSet<&str> = errors.iter().map(|e| e.file.as_str()).collect();
+                let message = format!(
+                    "{} of {} files failed to compile",
+                    failed.len(),
+                    compiled.len() + unchanged.len() + failed.len()
+                );
+                eprintln!("{} {}", "Build failed:".red(), message);
+                return Err(anyhow::anyhow!(message));
+            }
+            if let Err(e) = check_entry(&project_root, &config) {
+                pb.finish_and_clear();
+                eprintln!("{} {}", "Build failed:".red(), e);
+                return Err(e);
+            }
+            // Content-hashed copies of styles.css and bundle.js, and asset-manifest.json
+            let manifest = match hash_assets(&config.out_dir(&project_root)) {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    pb.finish_and_clear();
+                    eprintln!("{} {:#}", "Build failed:".red(), e);
+                    return Err(e);
+                }
+            };
+            pb.set_message(format!(
+                "Compiled {} files, {} unchanged, {} hashed assets",
+                compiled.len(),
+                unchanged.len(),
+                manifest.assets.len()
+            ));
         }
         Err(e) => {
             pb.finish_with_message(format!("{} {}", "Build failed:".red(), e));
@@ -32,25 +103,12 @@ pub async fn build_project(output: &str, env: &str) -> Result<()> {
         }
     }
 
-    match env {
-        "development" => {
-            info!("Development build complete");
-        }
-        "node" | "python" => {
-            info!("Production build complete");
-            // Here we could add extra steps like minification of the final bundle
-            // if it wasn't already handled by compile_all_pyx
-        }
-        _ => {
-            error!("Invalid deployment environment: {}", env);
-            return Err(anyhow::anyhow!("Invalid deployment environment: {}", env));
-        }
-    }
+    info!("{} build complete for {}", env, target);
 
     pb.finish_with_message(format!(
         "{} in {}",
         "Project built successfully!".green(),
-        output
+        config.out_dir
     ));
     Ok(())
 }
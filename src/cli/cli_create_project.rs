@@ -1,4 +1,5 @@
 // src/cli/cli_create_project.rs
+use crate::config::CONFIG_FILE;
 use anyhow::{Context, Result};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
@@ -18,10 +19,8 @@ pub fn create_project(project_name: &str) -> Result<()> {
     let project_root = Path::new(project_name);
 
     // --- Create minimal directories (Vite-like template) ---
-    fs::create_dir_all(project_root.join("src"))
-        .context("Failed to create src directory")?;
-    fs::create_dir_all(project_root.join("public"))
-        .context("Failed to create public directory")?;
+    fs::create_dir_all(project_root.join("src")).context("Failed to create src directory")?;
+    fs::create_dir_all(project_root.join("public")).context("Failed to create public directory")?;
     fs::create_dir_all(project_root.join("templates"))
         .context("Failed to create templates directory")?;
 
@@ -42,7 +41,7 @@ from reactpyx import use_state
 
 
 def App():
-    count, set_count = use_state("app", "count", 0)
+    count, set_count = use_state(0)
 
     def increment():
         set_count.set(count + 1)
@@ -70,7 +69,7 @@ def App():
         "sourceMaps": true
     }
 }"#;
-    fs::write(project_root.join("pyx.config.json"), config_content)
+    fs::write(project_root.join(CONFIG_FILE), config_content)
         .context("Failed to write pyx.config.json")?;
 
     // --- Create minimal main.py (FastAPI + ReactPyx runtime) ---
@@ -110,61 +109,6 @@ if __name__ == "__main__":
     import uvicorn
 
     uvicorn.run("main:app", host="0.0.0.0", port=8000, reload=True)
-"#;
-    return FileResponse(favicon_path) if os.path.exists(favicon_path) else HTMLResponse(status_code=204)
-
-@app.get("/{full_path:path}", response_class=HTMLResponse)
-async def serve_spa(request: Request, full_path: str):
-    # SSR Implementation
-    try:
-        # Add build directory to path to import compiled components
-        build_dir = os.path.join(BASE_DIR, "build")
-        if build_dir not in sys.path:
-            sys.path.append(build_dir)
-            
-        # Import the entry point (compiled from src/main.pyx)
-        # src/main.pyx compiles to build/components/main.py
-        from components import main as app_main
-        
-        # Reload module in development to pick up changes
-        import importlib
-        importlib.reload(app_main)
-        
-        # Render the app
-        app_instance = app_main.MainApp()
-        app_html = app_instance.render()
-        
-        # Serialize state for hydration
-        import json
-        app_state = app_instance.to_dict()
-        app_state_json = json.dumps(app_state)
-    except Exception as e:
-        print(f"SSR Error: {e}")
-        app_html = f"<!-- SSR Error: {e} -->"
-        app_state_json = "{}"
-
-    context = {
-        "request": request,
-        "page_title": "ReactPyx Application",
-        "content": app_html,
-        "initial_state": app_state_json
-    }
-    return templates.TemplateResponse("index.jinja2", context)
-
-@app.exception_handler(404)
-async def custom_404_handler(request: Request, exc: HTTPException):
-    context = {"request": request}
-    template_404_path = os.path.join(templates_dir, "404.jinja2")
-    if os.path.exists(template_404_path):
-        return templates.TemplateResponse("404.jinja2", context, status_code=404)
-    else:
-        return HTMLResponse(content="<h1>404 - Not Found</h1>", status_code=404)
-
-if __name__ == "__main__":
-    import uvicorn
-    port = int(os.environ.get("PORT", 8000))
-    host = os.environ.get("HOST", "0.0.0.0")
-    uvicorn.run("main:app", host=host, port=port, reload=True)
 "#;
     fs::write(project_root.join("main.py"), server_content).context("Failed to write main.py")?;
 
@@ -0,0 +1,66 @@
+use super::collect_pyx_files;
+use crate::diagnostics::display_path;
+use crate::formatter::format_pyx;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::Path;
+
+/// Formats the `.pyx` files in `paths` (files or directories). With `check`,
+/// reports the files that would change instead of writing them, and fails if
+/// there are any.
+pub fn format_files(paths: &[String], check: bool) -> Result<()> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_pyx_files(Path::new(path), &mut files)
+            .with_context(|| format!("Error reading {}", path))?;
+    }
+    files.sort();
+
+    let mut changed = 0;
+    let mut failed = 0;
+    for file in &files {
+        let source =
+            fs::read_to_string(file).with_context(|| format!("Error reading {:?}", file))?;
+        let formatted = match format_pyx(&source) {
+            Ok(formatted) => formatted,
+            Err(diagnostic) => {
+                let diagnostic = diagnostic.with_file(display_path(file));
+                eprintln!("{}", diagnostic.render_colored(&source));
+                failed += 1;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        changed += 1;
+        if check {
+            println!("{} {}", "Would reformat".yellow(), display_path(file));
+        } else {
+            fs::write(file, formatted).with_context(|| format!("Error writing {:?}", file))?;
+            println!("{} {}", "Formatted".green(), display_path(file));
+        }
+    }
+
+    let unchanged = files.len() - changed - failed;
+    if check {
+        println!(
+            "{} files would be reformatted, {} files already formatted",
+            changed, unchanged
+        );
+    } else {
+        println!(
+            "{} files reformatted, {} files left unchanged",
+            changed, unchanged
+        );
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} files could not be parsed", failed);
+    }
+    if check && changed > 0 {
+        anyhow::bail!("{} files are not formatted", changed);
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::config::{Config, CONFIG_FILE, DEVELOPMENT};
 use anyhow::{Context, Result};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
@@ -6,6 +7,10 @@ use std::process::Command;
 use std::time::Duration;
 
 pub fn init_project(env: &str) -> Result<()> {
+    // Report an unknown environment or a broken configuration of it before
+    // installing
+    Config::load_project_env(".", CONFIG_FILE, env)?;
+
     let pb = ProgressBar::new_spinner();
     pb.enable_steady_tick(Duration::from_millis(120));
     pb.set_style(
@@ -70,12 +75,13 @@ body {
         return Err(anyhow::anyhow!("Pip is not properly installed"));
     }
 
-    // Install environment-specific dependencies
+    // Install environment-specific dependencies; overlays other than
+    // `development` are deployed, so they get the production ones
     match env {
-        "development" => {
+        DEVELOPMENT => {
             println!("Installing development dependencies...");
             Command::new("pip")
-                .args(&["install", "fastapi", "uvicorn", "jinja2"])
+                .args(["install", "fastapi", "uvicorn", "jinja2"])
                 .spawn()?
                 .wait()
                 .context("Error installing development dependencies")?;
@@ -122,10 +128,10 @@ def try_except_demo():
             )
             .context("Failed to create Python 3.13 features demo file")?;
         }
-        "production" => {
+        _ => {
             // Only install production-necessary dependencies
             Command::new("pip")
-                .args(&["install", "reactpyx", "fastapi"])
+                .args(["install", "reactpyx", "fastapi"])
                 .spawn()?
                 .wait()
                 .context("Error installing production dependencies")?;
@@ -134,9 +140,6 @@ def try_except_demo():
             // Note: index.html is now handled by Jinja2 templates in templates/
             // fs::write("public/index.html", ...).context("Failed to create production index.html")?;
         }
-        _ => {
-            return Err(anyhow::anyhow!("Unrecognized environment: {}", env));
-        }
     }
 
     // Create a basic CSS integration helper
@@ -18,7 +18,7 @@ pub fn install_library(library: &str) -> Result<()> {
             fs::write("public/tailwind-cdn.html", cdn_integration)
                 .context("Error creating tailwind CDN integration file")?;
 
-            println!("{} {}", "✓".green(), "Tailwind CSS CDN integration created");
+            println!("{} Tailwind CSS CDN integration created", "✓".green());
 
             // Create a sample configuration that doesn't rely on npm
             fs::write(
@@ -66,7 +66,7 @@ def tw_classes(class_string):
             fs::write("public/bootstrap-cdn.html", cdn_integration)
                 .context("Error creating bootstrap CDN integration file")?;
 
-            println!("{} {}", "✓".green(), "Bootstrap CDN integration created");
+            println!("{} Bootstrap CDN integration created", "✓".green());
 
             // Create a Python helper file for bootstrap integration
             fs::write(
@@ -128,7 +128,7 @@ def bs_button(variant="primary", size="", text="Button"):
             println!("\n{}", "Usage example:".cyan());
             println!("```python");
             println!("from src.tailwind_helper import use_tailwind, tw_classes");
-            println!("");
+            println!();
             println!("def MyComponent(props):");
             println!("    # Enable Tailwind");
             println!("    use_tailwind()");
@@ -143,7 +143,7 @@ def bs_button(variant="primary", size="", text="Button"):
             println!("\n{}", "Usage example:".cyan());
             println!("```python");
             println!("from src.bootstrap_helper import use_bootstrap, bs_button");
-            println!("");
+            println!();
             println!("def MyComponent(props):");
             println!("    # Enable Bootstrap");
             println!("    use_bootstrap()");
@@ -0,0 +1,105 @@
+use super::collect_pyx_files;
+use crate::config::Config;
+use crate::diagnostics::{display_path, Diagnostic, Severity};
+use crate::linter::{lint_pyx, RULES};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+/// Lints the `.pyx` files in `paths` with the rule severities of
+/// `config_path`, printing the diagnostics as `text` or `json`. Fails if any
+/// of them is an error.
+pub fn lint_files(paths: &[String], config_path: &str, format: &str) -> Result<()> {
+    let config = Config::load_project(".", config_path)?.lint;
+
+    let mut files = Vec::new();
+    for path in paths {
+        collect_pyx_files(Path::new(path), &mut files)
+            .with_context(|| format!("Error reading {}", path))?;
+    }
+    files.sort();
+
+    let mut results = Vec::new();
+    for file in &files {
+        let source =
+            fs::read_to_string(file).with_context(|| format!("Error reading {:?}", file))?;
+        let diagnostics = lint_pyx(&source, &config).unwrap_or_else(|error| vec![error]);
+        let diagnostics: Vec<Diagnostic> = diagnostics
+            .into_iter()
+            .map(|diagnostic| diagnostic.with_file(display_path(file)))
+            .collect();
+        results.push((source, diagnostics));
+    }
+
+    let count = |severity| {
+        results
+            .iter()
+            .flat_map(|(_, diagnostics)| diagnostics)
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+
+    if format == "json" {
+        let diagnostics: Vec<_> = results
+            .iter()
+            .flat_map(|(_, diagnostics)| diagnostics)
+            .map(|diagnostic| {
+                json!({
+                    "file": diagnostic.file,
+                    "rule": diagnostic.code,
+                    "severity": diagnostic.severity.to_string(),
+                    "message": diagnostic.message,
+                    "line": diagnostic.line,
+                    "column": diagnostic.column,
+                    "span": [diagnostic.span.start, diagnostic.span.end],
+                    "help": diagnostic.help.as_deref(),
+                })
+            })
+            .collect();
+        let report = json!({
+            "diagnostics": diagnostics,
+            "errorCount": errors,
+            "warningCount": warnings,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for (source, diagnostics) in &results {
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic.render_colored(source));
+            }
+        }
+        let summary = format!(
+            "{} errors, {} warnings in {} files",
+            errors,
+            warnings,
+            files.len()
+        );
+        if errors > 0 {
+            eprintln!("{}", summary.red());
+        } else if warnings > 0 {
+            eprintln!("{}", summary.yellow());
+        } else {
+            eprintln!("{}", summary.green());
+        }
+    }
+
+    if errors > 0 {
+        anyhow::bail!("Lint found {} errors", errors);
+    }
+    Ok(())
+}
+
+/// Prints the lint rules with their default severity
+pub fn list_rules() {
+    for rule in RULES {
+        println!(
+            "{:<16} {:<8} {}",
+            rule.name,
+            rule.severity.to_string(),
+            rule.description
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use crate::config::Config;
+use crate::diagnostics::Severity;
+use crate::linter::RULES;
+use anyhow::Result;
+use colored::Colorize;
+use serde_json::{json, Value};
+
+/// Prints the configuration in effect for `environment`: `config_path` merged
+/// over the files it extends, with the overlay of the environment and the
+/// defaults of the keys it leaves out
+pub fn print_config(config_path: &str, environment: &str) -> Result<()> {
+    let config = Config::load_project_env(".", config_path, environment)?;
+
+    let mut effective = serde_json::to_value(&config)?;
+    effective["define"] = serde_json::to_value(config.defines())?;
+    effective["compilerOptions"]["concurrency"] = json!(config.concurrency());
+    effective["compilerOptions"]["targets"] = serde_json::to_value(config.targets())?;
+    effective["lint"]["rules"] = RULES
+        .iter()
+        .map(|rule| {
+            let level = match config.lint.severity(rule) {
+                None => "off",
+                Some(Severity::Warning) => "warning",
+                Some(Severity::Error) => "error",
+            };
+            (rule.name.to_string(), Value::from(level))
+        })
+        .collect();
+
+    let files: Vec<String> = config
+        .files
+        .iter()
+        .map(|file| file.strip_prefix(".").unwrap_or(file).display().to_string())
+        .collect();
+    let source = if files.is_empty() {
+        "defaults, no configuration file".to_string()
+    } else {
+        files.join(" < ")
+    };
+    eprintln!(
+        "{} {} ({} environment)",
+        "Configuration:".blue(),
+        source,
+        environment
+    );
+    println!("{}", serde_json::to_string_pretty(&effective)?);
+    Ok(())
+}
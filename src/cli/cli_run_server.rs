@@ -1,17 +1,20 @@
-use crate::compiler;
+use crate::compiler::{self, BuildOptions};
+use crate::config::{Config, CONFIG_FILE, TOML_CONFIG_FILE};
+use crate::hmr::HmrWatcher;
 use anyhow::Result;
 use colored::Colorize;
-use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::mpsc::channel;
-use std::time::Duration;
 
 pub async fn run_server() -> Result<()> {
+    let project_root = std::env::current_dir()?.to_string_lossy().to_string();
+    let mut config = Config::load_project(&project_root, CONFIG_FILE)?;
+
     println!(
         "{} {}",
         "Running development server...".yellow(),
-        "http://localhost:8000".blue()
+        format!("http://localhost:{}", config.port).blue()
     );
 
     // Check if uvicorn is available
@@ -40,53 +43,54 @@ pub async fn run_server() -> Result<()> {
 
     // Initial compilation of all components
     println!("{}", "Compiling components...".blue());
-    let project_root = std::env::current_dir()?.to_string_lossy().to_string();
-    compiler::compile_all_pyx(&project_root, "pyx.config.json", "python").await?;
+    compile_project(&project_root, &config, None).await?;
+    if let Err(e) = compiler::check_entry(&project_root, &config) {
+        eprintln!("{} {}", "✗".red(), e);
+    }
 
-    // Start FastAPI server in a separate process
+    // Start FastAPI server in a separate process. The server finds the
+    // compiled entry through these variables.
+    let entry_module = compiler::python_module_name(&config.entry_path(&project_root));
     let mut child = Command::new("uvicorn")
         .arg("main:app")
         .arg("--reload")
+        .arg("--port")
+        .arg(config.port.to_string())
+        .env("REACTPYX_OUT_DIR", &config.out_dir)
+        .env("REACTPYX_PUBLIC_PATH", &config.public_path)
+        .env(
+            "REACTPYX_ENTRY_MODULE",
+            format!("components.{}", entry_module),
+        )
+        .env("REACTPYX_ENTRY_FUNCTION", &config.entry_function)
         .spawn()?;
 
-    println!("{} {}", "✓".green(), "Server started successfully");
+    println!("{} Server started successfully", "✓".green());
     println!(
         "{} {}",
         "Watching for changes in".blue(),
         "src/**/*.{pyx,css}".bright_blue()
     );
 
-    // Watch for changes in `src` folder files
-    let (tx, rx) = channel();
-    let mut watcher = RecommendedWatcher::new(
-        tx,
-        notify::Config::default().with_poll_interval(Duration::from_secs(1)),
-    )?;
-
-    watcher.watch(Path::new("src"), RecursiveMode::Recursive)?;
+    // Watch for changes in `src` and in the configuration
+    let mut config_files = vec![
+        Path::new(&project_root).join(CONFIG_FILE),
+        Path::new(&project_root).join(TOML_CONFIG_FILE),
+    ];
+    config_files.extend(config.files.iter().cloned());
+    let watcher = HmrWatcher::new(&Path::new(&project_root).join("src"), &config_files)?;
 
     // Handle change events
-    for res in rx {
-        match res {
-            Ok(event) => {
-                if let EventKind::Create(_) | EventKind::Modify(_) = event.kind {
-                    for path in event.paths {
-                        if let Some(ext) = path.extension() {
-                            let ext_str = ext.to_string_lossy().to_lowercase();
-                            match ext_str.as_str() {
-                                "pyx" => {
-                                    handle_pyx_file_change(&path).await?;
-                                }
-                                "css" => {
-                                    handle_css_file_change(&path).await?;
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                }
-            }
-            Err(e) => println!("{} {:?}", "Error watching:".red(), e),
+    while let Some(changes) = watcher.next_changes() {
+        if changes.config {
+            handle_config_change(&project_root, &mut config).await?;
+            continue;
+        }
+        if !changes.pyx.is_empty() {
+            handle_pyx_file_changes(&changes.pyx, &project_root, &config).await?;
+        }
+        for path in &changes.css {
+            handle_css_file_change(path, &project_root, &config).await?;
         }
     }
 
@@ -96,36 +100,101 @@ pub async fn run_server() -> Result<()> {
     Ok(())
 }
 
-async fn handle_pyx_file_change(path: &Path) -> Result<()> {
-    println!("{} {}", "PyX file changed:".green(), path.display());
-
-    // Recompile modified file
-    let file_path = path.to_string_lossy().to_string();
+/// Compiles the project, printing its warnings and errors. Files that did
+/// not change since the last compilation are skipped; when the `changed`
+/// files are known, only they and the modules importing them are compiled.
+async fn compile_project(
+    project_root: &str,
+    config: &Config,
+    changed: Option<Vec<PathBuf>>,
+) -> Result<bool> {
+    let reported = changed.is_some();
+    let options = BuildOptions {
+        changed,
+        ..BuildOptions::default()
+    };
+    let summary = compiler::compile_all_pyx_with(project_root, config, "python", options).await?;
+    if reported && !summary.compiled.is_empty() {
+        let modules: Vec<String> = summary
+            .compiled
+            .iter()
+            .map(|file| compiler::python_module_name(Path::new(file)))
+            .collect();
+        println!("{} {}", "Recompiled".yellow(), modules.join(", "));
+    }
+    for warning in &summary.warnings {
+        let file = warning.file.as_deref().unwrap_or_default();
+        let source = std::fs::read_to_string(file).unwrap_or_default();
+        eprintln!("{}", warning.render_colored(&source));
+    }
+    for error in &summary.errors {
+        eprintln!("{}", error.render_colored());
+    }
+    Ok(summary.errors.is_empty())
+}
 
-    println!("{} {}", "Recompiling".yellow(), file_path);
+async fn handle_pyx_file_changes(
+    paths: &BTreeSet<PathBuf>,
+    project_root: &str,
+    config: &Config,
+) -> Result<()> {
+    for path in paths {
+        println!("{} {}", "PyX file changed:".green(), path.display());
+    }
 
-    match crate::compiler::compile_pyx_file_to_python(path, "pyx.config.json", "python").await {
-        Ok(_) => println!("{} {}", "✓".green(), "Compilation successful"),
-        Err(e) => println!("{} {}: {}", "✗".red(), "Compilation error".red(), e),
+    // Recompile the modified files and the modules importing them
+    let changed = paths.iter().cloned().collect();
+    if compile_project(project_root, config, Some(changed)).await? {
+        println!("{} Compilation successful", "✓".green());
+    } else {
+        eprintln!("{} {}", "✗".red(), "Compilation error".red());
     }
 
     // Process CSS after PyX changes in case there are <style> tags
-    process_css_files().await?;
+    process_css_files(project_root, config).await?;
 
     Ok(())
 }
 
-async fn handle_css_file_change(path: &Path) -> Result<()> {
+/// Reloads the configuration and compiles the project with it. A broken
+/// configuration is reported and the previous one kept.
+async fn handle_config_change(project_root: &str, config: &mut Config) -> Result<()> {
+    println!("{}", "Configuration changed".green());
+    match Config::load_project(project_root, CONFIG_FILE) {
+        Ok(new_config) => {
+            if new_config.port != config.port {
+                println!(
+                    "{} restart the server to use port {}",
+                    "Note:".yellow(),
+                    new_config.port
+                );
+            }
+            *config = new_config;
+        }
+        Err(e) => {
+            eprintln!("{} {:#}", "✗".red(), e);
+            return Ok(());
+        }
+    }
+    if compile_project(project_root, config, None).await? {
+        println!("{} Compilation successful", "✓".green());
+    } else {
+        eprintln!("{} {}", "✗".red(), "Compilation error".red());
+    }
+    process_css_files(project_root, config).await
+}
+
+async fn handle_css_file_change(path: &Path, project_root: &str, config: &Config) -> Result<()> {
     println!("{} {}", "CSS file changed:".green(), path.display());
-    process_css_files().await?;
+    process_css_files(project_root, config).await?;
     Ok(())
 }
 
-async fn process_css_files() -> Result<()> {
+async fn process_css_files(project_root: &str, config: &Config) -> Result<()> {
     // Create the static directory if it doesn't exist
-    let static_dir = Path::new("public/static");
+    let static_dir = config.public_dir(project_root).join("static");
     if !static_dir.exists() {
-        std::fs::create_dir_all(static_dir)?;
+        std::fs::create_dir_all(&static_dir)?;
     }
 
     // Collect all CSS files
@@ -139,13 +208,13 @@ async fn process_css_files() -> Result<()> {
     }
 
     // Add framework CSS files if they exist
-    let tailwind_css = Path::new("public/tailwind-cdn.html");
+    let tailwind_css = config.public_dir(project_root).join("tailwind-cdn.html");
     if tailwind_css.exists() {
         all_css.push_str("/* Tailwind CSS integration */\n");
         all_css.push_str("/* See public/tailwind-cdn.html for CDN link */\n\n");
     }
 
-    let bootstrap_css = Path::new("public/bootstrap-cdn.html");
+    let bootstrap_css = config.public_dir(project_root).join("bootstrap-cdn.html");
     if bootstrap_css.exists() {
         all_css.push_str("/* Bootstrap CSS integration */\n");
         all_css.push_str("/* See public/bootstrap-cdn.html for CDN link */\n\n");
@@ -157,7 +226,7 @@ async fn process_css_files() -> Result<()> {
         for entry in std::fs::read_dir(styles_dir)? {
             let entry = entry?;
             let path = entry.path();
-            if path.is_file() && path.extension().map_or(false, |e| e == "css") {
+            if path.is_file() && path.extension().is_some_and(|e| e == "css") {
                 // Skip main.css since we already added it
                 if path.file_name().unwrap() != "main.css" {
                     all_css.push_str(&format!("/* {} */\n", path.display()));
@@ -171,7 +240,7 @@ async fn process_css_files() -> Result<()> {
     // Write the combined CSS to the static directory
     let output_path = static_dir.join("styles.css");
     std::fs::write(&output_path, all_css)?;
-    println!("{} {}", "✓".green(), "CSS processed and combined");
+    println!("{} CSS processed and combined", "✓".green());
 
     Ok(())
 }
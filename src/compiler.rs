@@ -1,153 +1,755 @@
-use crate::css_minifier::minify_css_code;
-use crate::js_minifier::minify_js_code;
+use crate::a11y::check_accessibility;
+use crate::build_cache::{build_key, file_hash, BuildCache, CacheEntry};
+use crate::config::{Config, CONFIG_FILE};
+use crate::css_minifier::css_rules;
+use crate::defines::apply_defines;
+use crate::diagnostics::{display_path, Diagnostic, Severity};
+use crate::import_rewriter::rewrite_project_imports;
+use crate::js_minifier::minify_js;
+use crate::jsx_parser::{parse_pyx, JsxChild, LineIndex, PyCode, Span};
+use crate::jsx_transformer::TransformOutput;
+use crate::module_graph::{ModuleGraph, ModuleImport};
+use crate::node_target::{compile_es_module, NODE_MODULE_EXTENSION};
+use crate::python_validator::validate_python;
+use crate::source_map::{build_source_map, source_map_json, Mapping};
 use anyhow::{Context, Result};
 use futures::StreamExt;
 use log::{error, info};
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tokio::fs;
 
+/// What a project build produced
+#[derive(Debug, Default)]
+pub struct CompileSummary {
+    /// Paths of the compiled `.pyx` files
+    pub compiled: Vec<String>,
+    /// Paths of the files skipped because they did not change since the
+    /// last build
+    pub unchanged: Vec<String>,
+    /// Files that failed to compile
+    pub errors: Vec<CompileError>,
+    /// Accessibility warnings of the compiled files
+    pub warnings: Vec<Diagnostic>,
+    /// Imports between the modules of the project
+    pub graph: ModuleGraph,
+}
+
+/// Options of a project build that do not come from `pyx.config.json`
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// Fail files with accessibility warnings instead of reporting them
+    pub strict: bool,
+    /// Files changed since the last build. When set, these files and the
+    /// modules importing them are compiled, and the other files keep the
+    /// output of the last build.
+    pub changed: Option<Vec<PathBuf>>,
+}
+
+/// A `.pyx` file that failed to compile
+#[derive(Debug)]
+pub struct CompileError {
+    pub file: String,
+    pub error: anyhow::Error,
+}
+
+impl CompileError {
+    pub fn new(file: &Path, error: anyhow::Error) -> Self {
+        CompileError {
+            file: file.to_string_lossy().to_string(),
+            error,
+        }
+    }
+
+    /// The structured diagnostic behind the error, if it has one
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        self.error.downcast_ref()
+    }
+
+    /// Renders the error for the terminal, with a colored code frame when it
+    /// comes from a diagnostic
+    pub fn render_colored(&self) -> String {
+        match (self.diagnostic(), std::fs::read_to_string(&self.file)) {
+            (Some(diagnostic), Ok(source)) => diagnostic.render_colored(&source),
+            _ => format!("{}: {:#}\n", self.file, self.error),
+        }
+    }
+}
+
 /// Compiles all `.pyx` files in the project asynchronously and in parallel
 pub async fn compile_all_pyx(
     project_root: &str,
     config_path: &str,
     target_env: &str, // "node" or "python"
-) -> Result<(Vec<String>, Vec<(String, String)>)> {
-    let src_dir = Path::new(project_root).join("src");
-
-    // Recursively find all .pyx files
-    let mut pyx_files = Vec::new();
-    let mut dirs_to_visit = vec![src_dir];
+) -> Result<CompileSummary> {
+    let config = Config::load_project(project_root, config_path)?;
+    compile_all_pyx_with(project_root, &config, target_env, BuildOptions::default()).await
+}
 
-    while let Some(dir) = dirs_to_visit.pop() {
-        if let Ok(mut entries) = fs::read_dir(&dir).await {
-            while let Ok(Some(entry)) = entries.next_entry().await {
-                let path = entry.path();
-                if path.is_dir() {
-                    dirs_to_visit.push(path);
-                } else if let Some(ext) = path.extension() {
-                    if ext == "pyx" {
-                        pyx_files.push(path);
-                    }
-                }
-            }
-        }
-    }
+/// Like [`compile_all_pyx`], with a loaded `config` and build `options`
+pub async fn compile_all_pyx_with(
+    project_root: &str,
+    config: &Config,
+    target_env: &str,
+    options: BuildOptions,
+) -> Result<CompileSummary> {
+    let src_dir = Path::new(project_root).join("src");
+    let out_dir = config.out_dir(project_root);
 
+    let pyx_files = find_pyx_files(&src_dir).await;
     info!("Found {} .pyx files to compile", pyx_files.len());
 
+    let modules = Arc::new(project_modules(&pyx_files));
+    let (pyx_files, collisions) = split_output_collisions(&out_dir, pyx_files);
+
+    let defines = config.defines();
+    let build_key = Arc::new(build_key(
+        target_env,
+        &serde_json::to_string(&(&config.compiler_options, &defines))?,
+        &modules,
+    ));
+    let cache = Arc::new(BuildCache::load(&out_dir));
+    let affected = options
+        .changed
+        .as_ref()
+        .map(|changed| cached_graph(&cache, &pyx_files).affected_files(changed));
+    let strict = options.strict;
+    let new_cache = Arc::new(Mutex::new(BuildCache::default()));
+    let unchanged_files = Arc::new(Mutex::new(Vec::new()));
+
     let compiled_files = Arc::new(Mutex::new(Vec::new()));
-    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors = Arc::new(Mutex::new(collisions));
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let styles = Arc::new(Mutex::new(Vec::new()));
+    let imports = Arc::new(Mutex::new(Vec::new()));
 
-    let concurrency_level = 8;
+    let targets = config.targets();
 
     // Process files in parallel
-    futures::stream::iter(pyx_files)
-        .for_each_concurrent(Some(concurrency_level), |file_path| {
+    futures::stream::iter(pyx_files.clone())
+        .for_each_concurrent(Some(config.concurrency()), |file_path| {
             let compiled_files = Arc::clone(&compiled_files);
             let errors = Arc::clone(&errors);
+            let warnings = Arc::clone(&warnings);
+            let styles = Arc::clone(&styles);
+            let imports = Arc::clone(&imports);
+            let modules = Arc::clone(&modules);
+            let build_key = Arc::clone(&build_key);
+            let cache = Arc::clone(&cache);
+            let new_cache = Arc::clone(&new_cache);
+            let unchanged_files = Arc::clone(&unchanged_files);
             let project_root = project_root.to_string();
-            let config_path = config_path.to_string();
+            let out_dir = &out_dir;
+            let defines = &defines;
+            let affected = &affected;
             let target_env = target_env.to_string();
 
             async move {
-                match compile_pyx_file_to_python(&file_path, &config_path, &target_env).await {
-                    Ok((python_code, css_code, js_code)) => {
+                let source = fs::read(&file_path).await.unwrap_or_default();
+                let hash = file_hash(&build_key, &file_path, &source);
+                let cached = match affected {
+                    Some(affected) if affected.contains(&file_path) => None,
+                    Some(_) => cache.get_any(&file_path),
+                    None => cache.get(&file_path, &hash),
+                }
+                .filter(|_| python_output_path(out_dir, &file_path).exists())
+                .filter(|_| target_env != "node" || node_output_path(out_dir, &file_path).exists());
+                if let Some(entry) = cached {
+                    info!("Unchanged: {:?}", file_path);
+                    let mut unchanged_files = unchanged_files.lock().unwrap();
+                    unchanged_files.push(file_path.to_string_lossy().to_string());
+                    styles
+                        .lock()
+                        .unwrap()
+                        .push((file_path.clone(), entry.css.clone()));
+                    imports
+                        .lock()
+                        .unwrap()
+                        .push((file_path.clone(), entry.imports.clone()));
+                    new_cache.lock().unwrap().insert(&file_path, entry.clone());
+                    return;
+                }
+
+                let compiled = compile_pyx_module(&file_path, &target_env, &modules, defines).await;
+                if let Ok(module) = &compiled {
+                    imports
+                        .lock()
+                        .unwrap()
+                        .push((file_path.clone(), module.imports.clone()));
+                }
+                match compiled {
+                    Ok(module) if strict && !module.warnings.is_empty() => {
+                        error!("Warnings in {:?} fail the strict build", file_path);
+                        // Nothing is written, but its styles stay in the bundle
+                        match css_rules(
+                            &module.css_code,
+                            &targets,
+                            config.compiler_options.minify.css,
+                        ) {
+                            Ok(rules) => styles.lock().unwrap().push((file_path.clone(), rules)),
+                            Err(_) => keep_cached_css(&cache, &file_path, &styles),
+                        }
+                        let mut errors = errors.lock().unwrap();
+                        for warning in module.warnings {
+                            let diagnostic = Diagnostic {
+                                severity: Severity::Error,
+                                ..warning
+                            };
+                            errors.push(CompileError::new(&file_path, diagnostic.into()));
+                        }
+                    }
+                    Ok(module) => {
+                        let rules = match css_rules(
+                            &module.css_code,
+                            &targets,
+                            config.compiler_options.minify.css,
+                        ) {
+                            Ok(rules) => rules,
+                            Err(e) => {
+                                error!("Invalid CSS in {:?}: {}", file_path, e);
+                                keep_cached_css(&cache, &file_path, &styles);
+                                let mut errors = errors.lock().unwrap();
+                                errors.push(CompileError::new(
+                                    &file_path,
+                                    anyhow::anyhow!("CSS minification failed: {}", e),
+                                ));
+                                return;
+                            }
+                        };
                         {
                             let mut compiled_files = compiled_files.lock().unwrap();
                             compiled_files.push(file_path.to_string_lossy().to_string());
                         }
                         info!("Successfully compiled: {:?}", file_path);
+                        warnings
+                            .lock()
+                            .unwrap()
+                            .extend(module.warnings.iter().cloned());
 
                         // Write transformed code to appropriate build directories
-                        if let Err(e) = write_transformed_files(
-                            &project_root,
-                            &file_path,
-                            &python_code,
-                            &css_code,
-                            &js_code,
-                        )
-                        .await
+                        if let Err(e) =
+                            write_transformed_files(&project_root, config, &file_path, &module)
+                                .await
                         {
                             error!("Error writing transformed files for {:?}: {}", file_path, e);
+                            keep_cached_css(&cache, &file_path, &styles);
                             let mut errors = errors.lock().unwrap();
-                            errors.push((file_path.to_string_lossy().to_string(), e.to_string()));
+                            errors.push(CompileError::new(&file_path, e));
+                        } else {
+                            if module.warnings.is_empty() {
+                                let entry = CacheEntry {
+                                    hash,
+                                    css: rules.clone(),
+                                    imports: module.imports.clone(),
+                                };
+                                new_cache.lock().unwrap().insert(&file_path, entry);
+                            }
+                            styles.lock().unwrap().push((file_path, rules));
                         }
                     }
                     Err(e) => {
-                        let mut errors = errors.lock().unwrap();
-                        errors.push((file_path.to_string_lossy().to_string(), e.to_string()));
                         error!("Error compiling {:?}: {}", file_path, e);
+                        keep_cached_css(&cache, &file_path, &styles);
+                        let mut errors = errors.lock().unwrap();
+                        errors.push(CompileError::new(&file_path, e));
                     }
                 }
             }
         })
         .await;
 
-    // Ensure __init__.py exists in build/components
-    let components_out_dir = Path::new(project_root).join("build").join("components");
-    if components_out_dir.exists() {
-        let init_path = components_out_dir.join("__init__.py");
-        if !init_path.exists() {
-            let _ = fs::write(init_path, "").await;
-        }
+    let styles = Arc::try_unwrap(styles).unwrap().into_inner().unwrap();
+    write_css_bundle(&out_dir, styles, config).await?;
+
+    let mut graph = ModuleGraph::new();
+    for file_path in &pyx_files {
+        graph.insert(&python_module_name(file_path), file_path, Vec::new());
+    }
+    for (file_path, imports) in Arc::try_unwrap(imports).unwrap().into_inner().unwrap() {
+        graph.insert(&python_module_name(&file_path), &file_path, imports);
     }
 
-    Ok((
-        Arc::try_unwrap(compiled_files)
+    let mut new_cache = Arc::try_unwrap(new_cache).unwrap().into_inner().unwrap();
+    let mut summary = CompileSummary {
+        compiled: Arc::try_unwrap(compiled_files)
+            .unwrap()
+            .into_inner()
+            .unwrap(),
+        unchanged: Arc::try_unwrap(unchanged_files)
             .unwrap()
             .into_inner()
             .unwrap(),
-        Arc::try_unwrap(errors).unwrap().into_inner().unwrap(),
-    ))
+        errors: Arc::try_unwrap(errors).unwrap().into_inner().unwrap(),
+        warnings: Arc::try_unwrap(warnings).unwrap().into_inner().unwrap(),
+        graph,
+    };
+
+    // A cycle fails its first module, which is compiled again next time
+    for error in import_cycle_errors(&summary.graph) {
+        let file = PathBuf::from(&error.file);
+        summary.compiled.retain(|compiled| *compiled != error.file);
+        summary
+            .unchanged
+            .retain(|unchanged| *unchanged != error.file);
+        new_cache.remove(&file);
+        summary.errors.push(error);
+    }
+    if let Err(e) = new_cache.save(&out_dir) {
+        error!("Error writing the build cache: {}", e);
+    }
+
+    summary
+        .warnings
+        .sort_by(|a, b| (&a.file, a.span.start).cmp(&(&b.file, b.span.start)));
+    Ok(summary)
+}
+
+/// Adds the CSS of the last good build of `file_path`, which failed this
+/// time, to `styles`, so `styles.css` goes with the Python output it left
+fn keep_cached_css(
+    cache: &BuildCache,
+    file_path: &Path,
+    styles: &Mutex<Vec<(PathBuf, Vec<String>)>>,
+) {
+    if let Some(entry) = cache.get_any(file_path) {
+        styles
+            .lock()
+            .unwrap()
+            .push((file_path.to_path_buf(), entry.css.clone()));
+    }
+}
+
+/// The module graph of `pyx_files` as of the last build, from the imports in
+/// `cache`
+fn cached_graph(cache: &BuildCache, pyx_files: &[PathBuf]) -> ModuleGraph {
+    let mut graph = ModuleGraph::new();
+    for file_path in pyx_files {
+        let imports = cache
+            .get_any(file_path)
+            .map(|entry| entry.imports.clone())
+            .unwrap_or_default();
+        graph.insert(&python_module_name(file_path), file_path, imports);
+    }
+    graph
+}
+
+/// A `PYX018` error for each cycle of module-level imports, at the import
+/// that starts it
+pub(crate) fn import_cycle_errors(graph: &ModuleGraph) -> Vec<CompileError> {
+    let mut errors = Vec::new();
+    for cycle in graph.cycles() {
+        let (module, next) = (&cycle[0], &cycle[1]);
+        let Some(file_path) = graph.file_of(module) else {
+            continue;
+        };
+        let source = std::fs::read_to_string(file_path).unwrap_or_default();
+        let span = graph
+            .imports_of(module)
+            .into_iter()
+            .find(|import| import.top_level && import.module == *next)
+            .map_or(Span::new(0, 0), |import| {
+                Span::new(import.start, import.end)
+            });
+        let diagnostic = Diagnostic::error(
+            "PYX018",
+            format!("Import cycle: {}", cycle.join(" -> ")),
+            span,
+            &LineIndex::new(&source),
+        )
+        .with_help(
+            "Python cannot finish importing modules that import each other; move one of these imports into the function that uses it",
+        )
+        .with_file(display_path(file_path));
+        errors.push(CompileError::new(file_path, diagnostic.into()));
+    }
+    errors
+}
+
+/// Finds the `.pyx` files under `dir`, recursively
+async fn find_pyx_files(dir: &Path) -> Vec<PathBuf> {
+    let mut pyx_files = Vec::new();
+    let mut dirs_to_visit = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs_to_visit.pop() {
+        if let Ok(mut entries) = fs::read_dir(&dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                if path.is_dir() {
+                    dirs_to_visit.push(path);
+                } else if let Some(ext) = path.extension() {
+                    if ext == "pyx" {
+                        pyx_files.push(path);
+                    }
+                }
+            }
+        }
+    }
+    pyx_files.sort();
+    pyx_files
+}
+
+/// Names of the modules compiled from `pyx_files` and of the packages they
+/// are in, which are the imports rewritten as relative imports
+pub(crate) fn project_modules(pyx_files: &[PathBuf]) -> HashSet<String> {
+    let mut modules = HashSet::new();
+    for file_path in pyx_files {
+        let module_name = python_module_name(file_path);
+        let mut name = module_name
+            .strip_suffix(".__init__")
+            .unwrap_or(&module_name);
+        loop {
+            modules.insert(name.to_string());
+            match name.rsplit_once('.') {
+                Some((package, _)) => name = package,
+                None => break,
+            }
+        }
+    }
+    modules
+}
+
+/// Path of the Python module compiled from a `.pyx` source, mirroring its
+/// place under `src` (`src/pages/Home.pyx` is `build/components/pages/Home.py`)
+pub fn python_output_path(out_dir: &Path, file_path: &Path) -> PathBuf {
+    let mut output_path = out_dir.join("components");
+    output_path.extend(python_module_name(file_path).split('.'));
+    output_path.set_extension("py");
+    output_path
+}
+
+/// Path of the ES module compiled from a `.pyx` source for the `node`
+/// target (`src/pages/Home.pyx` is `build/node/pages/Home.mjs`)
+pub fn node_output_path(out_dir: &Path, file_path: &Path) -> PathBuf {
+    let mut output_path = out_dir.join("node");
+    output_path.extend(python_module_name(file_path).split('.'));
+    output_path.set_extension(NODE_MODULE_EXTENSION);
+    output_path
+}
+
+/// Separates the files whose output would overwrite, or be shadowed by, the
+/// output of another file. Outputs that only differ in case collide on
+/// case-insensitive file systems, and a module is hidden by a package with
+/// the same name.
+fn split_output_collisions(
+    out_dir: &Path,
+    pyx_files: Vec<PathBuf>,
+) -> (Vec<PathBuf>, Vec<CompileError>) {
+    let outputs: Vec<(PathBuf, String)> = pyx_files
+        .into_iter()
+        .map(|file_path| {
+            let module_name = python_module_name(&file_path).to_lowercase();
+            (file_path, module_name)
+        })
+        .collect();
+    let mut claimed: HashMap<&str, Vec<&Path>> = HashMap::new();
+    for (file_path, module_name) in &outputs {
+        claimed.entry(module_name).or_default().push(file_path);
+    }
+
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    for (file_path, module_name) in &outputs {
+        let others: Vec<&Path> = claimed[module_name.as_str()]
+            .iter()
+            .copied()
+            .filter(|other| other != file_path)
+            .collect();
+        let package = format!("{}.", module_name);
+        let shadowing = outputs
+            .iter()
+            .find(|(_, other)| other.starts_with(&package));
+        let output_path = python_output_path(out_dir, file_path);
+        let output = display_path(&output_path);
+        let error = if let Some(other) = others.first() {
+            anyhow::anyhow!(
+                "{} and {} both compile to {}",
+                display_path(file_path),
+                display_path(other),
+                output
+            )
+        } else if let Some((other, _)) = shadowing {
+            anyhow::anyhow!(
+                "{} compiles to {}, which the package of {} hides",
+                display_path(file_path),
+                output,
+                display_path(other)
+            )
+        } else {
+            files.push(file_path.clone());
+            continue;
+        };
+        error!("{}", error);
+        errors.push(CompileError::new(file_path, error));
+    }
+    (files, errors)
 }
 
-/// Writes Python, CSS and JS transformed files to build directories
+/// Writes the Python and JS output of a file to the output directory, with
+/// `.map` files next to them when `compilerOptions.sourceMaps` is set
 async fn write_transformed_files(
     project_root: &str,
+    config: &Config,
     file_path: &Path,
-    python_code: &str,
-    css_code: &str,
-    js_code: &str,
+    module: &CompiledModule,
 ) -> Result<()> {
-    let output_path = Path::new(project_root)
-        .join("build")
-        .join("components")
-        .join(format!(
-            "{}.py",
-            file_path.file_stem().unwrap().to_str().unwrap()
-        ));
+    let out_dir = config.out_dir(project_root);
+    let source_maps = config.source_maps();
+    let output_path = python_output_path(&out_dir, file_path);
     fs::create_dir_all(output_path.parent().unwrap())
         .await
         .context("Error creating output directory")?;
-
-    fs::write(&output_path, python_code)
+    create_package_inits(&out_dir, &output_path).await?;
+
+    if source_maps {
+        let file_name = output_path.file_name().unwrap().to_string_lossy();
+        let map_name = format!("{}.map", file_name);
+        let source_name = relative_source_path(project_root, file_path, &output_path);
+        let source_map = build_source_map(
+            &file_name,
+            &source_name,
+            &module.source_code,
+            &module.python_code,
+            &module.mappings,
+        );
+        fs::write(
+            output_path.with_file_name(&map_name),
+            source_map_json(&source_map),
+        )
         .await
-        .context("Error writing transformed Python code")?;
+        .context("Error writing Python source map")?;
 
-    // Minify and write CSS
-    let css_output_path = Path::new(project_root).join("build").join("styles.css");
-    let minified_css = minify_css_code(css_code).context("CSS minification failed")?;
-    fs::write(css_output_path, minified_css)
-        .await
-        .context("Error writing minified CSS")?;
+        let mut python_code = module.python_code.clone();
+        if !python_code.ends_with('\n') {
+            python_code.push('\n');
+        }
+        python_code.push_str(&format!("# sourceMappingURL={}\n", map_name));
+        fs::write(&output_path, python_code)
+            .await
+            .context("Error writing transformed Python code")?;
+    } else {
+        fs::write(&output_path, &module.python_code)
+            .await
+            .context("Error writing transformed Python code")?;
+    }
+
+    if let Some(es_module) = &module.es_module {
+        let node_path = node_output_path(&out_dir, file_path);
+        fs::create_dir_all(node_path.parent().unwrap())
+            .await
+            .context("Error creating the node output directory")?;
+        fs::write(node_path, es_module)
+            .await
+            .context("Error writing the ES module")?;
+    }
+
+    let js_code = &module.js_code;
 
     // Minify and write JS
-    let js_output_path = Path::new(project_root).join("build").join("bundle.js");
-    let minified_js = minify_js_code(js_code).context("JS minification failed")?;
-    fs::write(js_output_path, minified_js)
+    let js_output_path = out_dir.join("bundle.js");
+    if !config.compiler_options.minify.js {
+        fs::write(js_output_path, js_code)
+            .await
+            .context("Error writing JS")?;
+    } else {
+        let js_options = &config.compiler_options.js_minify;
+        let (mut minified_js, js_map) =
+            minify_js(js_code, js_options, source_maps.then_some("bundle.js"))
+                .context("JS minification failed")?;
+        if let Some(js_map) = js_map {
+            minified_js.push_str("\n//# sourceMappingURL=bundle.js.map\n");
+            fs::write(js_output_path.with_file_name("bundle.js.map"), js_map)
+                .await
+                .context("Error writing JS source map")?;
+        }
+        fs::write(js_output_path, minified_js)
+            .await
+            .context("Error writing minified JS")?;
+    }
+
+    Ok(())
+}
+
+/// Writes the CSS rules of every compiled file to `styles.css` in `out_dir`,
+/// in source path order. A rule repeated by several files is only kept where
+/// it appears last, which leaves the cascade unchanged. With
+/// `compilerOptions.cssSplit`, each file's rules are also written to
+/// `styles/`, mirroring `src` like the Python modules.
+async fn write_css_bundle(
+    out_dir: &Path,
+    mut styles: Vec<(PathBuf, Vec<String>)>,
+    config: &Config,
+) -> Result<()> {
+    styles.sort();
+    let separator = if config.compiler_options.minify.css {
+        ""
+    } else {
+        "\n"
+    };
+
+    let rules: Vec<&str> = styles
+        .iter()
+        .flat_map(|(_, rules)| rules.iter().map(String::as_str))
+        .collect();
+    let last: HashMap<&str, usize> = rules
+        .iter()
+        .enumerate()
+        .map(|(i, rule)| (*rule, i))
+        .collect();
+    let bundle: String = rules
+        .iter()
+        .enumerate()
+        .filter(|(i, rule)| last[*rule] == *i)
+        .map(|(_, rule)| *rule)
+        .collect::<Vec<_>>()
+        .join(separator);
+    fs::create_dir_all(out_dir)
+        .await
+        .context("Error creating output directory")?;
+    fs::write(out_dir.join("styles.css"), bundle)
         .await
-        .context("Error writing minified JS")?;
+        .context("Error writing CSS bundle")?;
+
+    if config.css_split() {
+        for (file_path, rules) in styles.iter().filter(|(_, rules)| !rules.is_empty()) {
+            let mut output_path = out_dir.join("styles");
+            output_path.extend(python_module_name(file_path).split('.'));
+            output_path.set_extension("css");
+            fs::create_dir_all(output_path.parent().unwrap())
+                .await
+                .context("Error creating output directory")?;
+            fs::write(&output_path, rules.join(separator))
+                .await
+                .with_context(|| format!("Error writing {:?}", output_path))?;
+        }
+    }
+    Ok(())
+}
 
+/// Creates an empty `__init__.py` in every directory from `components` in
+/// `out_dir` down to the one of `output_path`, unless one was already written
+async fn create_package_inits(out_dir: &Path, output_path: &Path) -> Result<()> {
+    let components_dir = out_dir.join("components");
+    for dir in output_path.ancestors().skip(1) {
+        let init_path = dir.join("__init__.py");
+        let created = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&init_path)
+            .await;
+        match created {
+            Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => {
+                return Err(e).with_context(|| format!("Error creating {:?}", init_path));
+            }
+            _ => {}
+        }
+        if dir == components_dir {
+            break;
+        }
+    }
     Ok(())
 }
 
-/// Compiles a `.pyx` file to Python, CSS and JavaScript
+/// Path of a `.pyx` source as seen from the directory of its output, for
+/// source maps
+fn relative_source_path(project_root: &str, file_path: &Path, output_path: &Path) -> String {
+    let depth = output_path
+        .strip_prefix(project_root)
+        .map_or(0, |relative| relative.components().count() - 1);
+    match file_path.strip_prefix(project_root) {
+        Ok(relative) => format!(
+            "{}{}",
+            "../".repeat(depth),
+            relative.to_string_lossy().replace('\\', "/")
+        ),
+        Err(_) => file_path.to_string_lossy().replace('\\', "/"),
+    }
+}
+
+/// Checks that the `entry` of the project was compiled and defines its
+/// `entryFunction`
+pub fn check_entry(project_root: &str, config: &Config) -> Result<()> {
+    let entry_path = config.entry_path(project_root);
+    if !entry_path.exists() {
+        anyhow::bail!(
+            "The entry `{}` set in {} does not exist",
+            config.entry,
+            config.file_name()
+        );
+    }
+    let output_path = python_output_path(&config.out_dir(project_root), &entry_path);
+    let python_code = std::fs::read_to_string(&output_path)
+        .with_context(|| format!("The entry `{}` was not compiled", config.entry))?;
+    let name = regex::escape(&config.entry_function);
+    let definition =
+        regex::Regex::new(&format!(r"(?m)^(async\s+)?def\s+{}\b|^{}\s*=", name, name)).unwrap();
+    if !definition.is_match(&python_code) {
+        anyhow::bail!(
+            "The entry `{}` does not define `{}`, the `entryFunction` of {}",
+            config.entry,
+            config.entry_function,
+            config.file_name()
+        );
+    }
+    Ok(())
+}
+
+/// Output of compiling a single `.pyx` file
+#[derive(Debug, Clone)]
+pub struct CompiledModule {
+    pub source_code: String,
+    pub python_code: String,
+    pub css_code: String,
+    pub js_code: String,
+    /// The ES module of the source, for the `node` target
+    pub es_module: Option<String>,
+    /// Positions in `python_code` and the `.pyx` positions they come from
+    pub mappings: Vec<Mapping>,
+    /// Accessibility warnings about the JSX of the source
+    pub warnings: Vec<Diagnostic>,
+    /// Modules the source may import
+    pub imports: Vec<ModuleImport>,
+}
+
+/// Compiles a `.pyx` file to Python, CSS and JavaScript. Imports of the other
+/// `.pyx` modules of its `src` directory become relative imports, and the
+/// `define` values of the development environment are substituted.
 pub async fn compile_pyx_file_to_python(
     file_path: &Path,
-    _config_path: &str,
+    config_path: &str,
+    target_env: &str,
+) -> Result<CompiledModule> {
+    let (project_root, pyx_files) = project_of(file_path).await;
+    let config = Config::load_project(&project_root, config_path)?;
+    let modules = project_modules(&pyx_files);
+    compile_pyx_module(file_path, target_env, &modules, &config.defines()).await
+}
+
+/// The root of the project `file_path` belongs to, the parent of its closest
+/// `src` directory, and the `.pyx` files of that directory
+pub(crate) async fn project_of(file_path: &Path) -> (String, Vec<PathBuf>) {
+    let src_dir = file_path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.file_name().is_some_and(|name| name == "src"));
+    let pyx_files = match src_dir {
+        Some(src_dir) => find_pyx_files(src_dir).await,
+        None => vec![file_path.to_path_buf()],
+    };
+    let project_root = src_dir
+        .and_then(Path::parent)
+        .map_or(".".to_string(), |root| root.to_string_lossy().to_string());
+    (project_root, pyx_files)
+}
+
+/// Compiles a `.pyx` file of a project with the given module and package
+/// names and `define` values
+pub(crate) async fn compile_pyx_module(
+    file_path: &Path,
     target_env: &str,
-) -> Result<(String, String, String)> {
+    modules: &HashSet<String>,
+    defines: &BTreeMap<String, serde_json::Value>,
+) -> Result<CompiledModule> {
     if !["node", "python"].contains(&target_env) {
         return Err(anyhow::anyhow!(
             "Unsupported target environment: {}",
@@ -164,68 +766,166 @@ pub async fn compile_pyx_file_to_python(
     }
 
     // Transform `.pyx` code to Python
-    let python_code: String = transform_pyx_to_python(&source_code).await?.to_string();
-
-    // Transform Python styles to CSS and logic to JavaScript
-    let (css_code, js_code) = transform_styles_and_js(&python_code, target_env)?;
-
-    Ok((python_code, css_code, js_code))
+    let module_name = python_module_name(file_path);
+    let mut output = transform_pyx_to_python_mapped(&source_code, &module_name)
+        .await
+        .map_err(|e| match e.downcast::<Diagnostic>() {
+            Ok(diagnostic) => diagnostic.with_file(display_path(file_path)).into(),
+            Err(e) => e,
+        })?;
+    let imports = rewrite_project_imports(&source_code, &mut output, &module_name, modules)
+        .map_err(|diagnostic| diagnostic.with_file(display_path(file_path)))?;
+    apply_defines(&mut output, defines);
+
+    // Refuse output the server could not import, so the last good build stays
+    validate_python(&source_code, &output.code, &output.mappings)
+        .map_err(|diagnostic| diagnostic.with_file(display_path(file_path)))?;
+
+    let es_module = if target_env == "node" {
+        let es_module = compile_es_module(&source_code, &output, &module_name, modules)
+            .map_err(|diagnostic| diagnostic.with_file(display_path(file_path)))?;
+        Some(es_module)
+    } else {
+        None
+    };
+    let TransformOutput {
+        code: python_code,
+        mappings,
+    } = output;
+
+    let code = parse_pyx(&source_code).ok();
+    let warnings = code
+        .as_ref()
+        .map(|code| check_accessibility(&source_code, code))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|warning| warning.with_file(display_path(file_path)))
+        .collect();
+    let css_code = code.as_ref().map(extract_css).unwrap_or_default();
+    let js_code = include_str!("runtime/client.js").to_string();
+
+    Ok(CompiledModule {
+        source_code,
+        python_code,
+        css_code,
+        js_code,
+        es_module,
+        mappings,
+        warnings,
+        imports,
+    })
 }
 
-/// Transform Python styles and logic to CSS and JavaScript
-fn transform_styles_and_js(python_code: &str, target_env: &str) -> Result<(String, String)> {
-    // Extract CSS from <style> tags in the code
-    // This is a basic implementation that looks for strings containing <style>...</style>
-    // In a real implementation, we would parse the Python AST to find these strings.
+/// Dotted Python module name of a `.pyx` source, relative to the closest
+/// `src` directory above it (`src/components/Counter.pyx` is
+/// `components.Counter`)
+pub fn python_module_name(file_path: &Path) -> String {
+    let parts: Vec<String> = file_path
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    let start = parts
+        .iter()
+        .rposition(|part| part == "src")
+        .map_or(parts.len().saturating_sub(1), |i| i + 1);
+    parts[start..].join(".")
+}
 
+/// The contents of the `<style>` elements of a `.pyx` source
+fn extract_css(code: &PyCode) -> String {
     let mut css_code = String::new();
-    // let mut cleaned_python = python_code.to_string();
-
-    let style_regex = regex::Regex::new(r"(?s)<style>(.*?)</style>").unwrap();
-
-    // Collect all styles
-    for cap in style_regex.captures_iter(python_code) {
-        if let Some(style_content) = cap.get(1) {
-            css_code.push_str(style_content.as_str());
-            css_code.push('\n');
+    code.for_each_element(&mut |element| {
+        if element.name != "style" {
+            return;
         }
-    }
-
-    // Remove style tags from python code (optional, but cleaner)
-    // For now we keep them or replace them with empty strings to avoid breaking line numbers too much
-    // cleaned_python = style_regex.replace_all(python_code, "").to_string();
-
-    // Transform styles and animations to JS
-    let runtime_code = include_str!("runtime/client.js");
-
-    let js_code = match target_env {
-        "node" => format!(
-            "// JS logic generated for Node.js environment\n{}\n",
-            runtime_code
-        ),
-        "python" => format!(
-            "// JS logic generated for Python environment\n{}\n",
-            runtime_code
-        ),
-        _ => unreachable!(),
-    };
-
-    Ok((css_code, js_code))
+        for child in &element.children {
+            if let JsxChild::Text { value, .. } = child {
+                css_code.push_str(value);
+                css_code.push('\n');
+            }
+        }
+    });
+    css_code
 }
 
 /// Transforms `.pyx` code to Python
 pub async fn transform_pyx_to_python(pyx_code: &str) -> Result<String> {
+    Ok(transform_pyx_to_python_mapped(pyx_code, "__main__")
+        .await?
+        .code)
+}
+
+/// Transforms the `.pyx` code of the module `module_name` to Python, keeping
+/// the source mappings
+pub async fn transform_pyx_to_python_mapped(
+    pyx_code: &str,
+    module_name: &str,
+) -> Result<TransformOutput> {
     // Process the transformation from `.pyx` to Python
     // We use the JSX transformer instead of syn/prettyplease which are for Rust
 
     let pyx_code_cloned = pyx_code.to_string();
-    let python_code = tokio::task::spawn_blocking(move || {
-        crate::jsx_transformer::parse_jsx(&pyx_code_cloned)
-            .map_err(|e| anyhow::anyhow!("JSX Transformation error: {}", e))
+    let module_name = module_name.to_string();
+    let mut output = tokio::task::spawn_blocking(move || {
+        crate::jsx_transformer::transform_pyx_module(&pyx_code_cloned, &module_name)
+            .map_err(anyhow::Error::new)
     })
     .await??;
 
-    Ok(python_code)
+    add_runtime_import(&mut output);
+    Ok(output)
+}
+
+/// Import statement that makes `create_element` available to compiled JSX
+const RUNTIME_IMPORT: &str = "from reactpyx import create_element\n";
+
+/// Adds the runtime import to a compiled module that uses `create_element`
+/// without importing it. The import goes after the module docstring and any
+/// `from __future__` imports, which must stay first.
+fn add_runtime_import(output: &mut TransformOutput) {
+    let python_code = &output.code;
+    let import_regex =
+        regex::Regex::new(r"(?m)^\s*(from\s+\S+\s+)?import\b.*\bcreate_element\b").unwrap();
+    if !python_code.contains("create_element(") || import_regex.is_match(python_code) {
+        return;
+    }
+
+    let mut offset = 0;
+    let mut in_docstring: Option<&str> = None;
+    let mut seen_statement = false;
+    for line in python_code.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if let Some(quote) = in_docstring {
+            if trimmed.contains(quote) {
+                in_docstring = None;
+            }
+        } else if trimmed.is_empty() || trimmed.starts_with('#') {
+            // Comments and blank lines before the first statement
+        } else if !seen_statement && (trimmed.starts_with("\"\"\"") || trimmed.starts_with("'''")) {
+            let quote = &trimmed[..3];
+            if !trimmed[3..].contains(quote) {
+                in_docstring = Some(quote);
+            }
+        } else if !trimmed.starts_with("from __future__") {
+            break;
+        }
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            seen_statement = true;
+        }
+        offset += line.len();
+    }
+
+    let mut import = RUNTIME_IMPORT.to_string();
+    if offset > 0 && !python_code[..offset].ends_with('\n') {
+        import.insert(0, '\n');
+    }
+    output.code.insert_str(offset, &import);
+    for mapping in &mut output.mappings {
+        if mapping.generated >= offset {
+            mapping.generated += import.len();
+        }
+    }
 }
 
 /// Updates the application by recompiling components and applying necessary changes.
@@ -239,7 +939,7 @@ pub async fn update_application(
     info!("Updating application for module: {}", module_name);
 
     // Optionally, call compile_all_pyx to recompile all components
-    compile_all_pyx(&project_root, "config.json", "python").await?;
+    compile_all_pyx(&project_root, CONFIG_FILE, "python").await?;
 
     // Example log for successful update
     info!(
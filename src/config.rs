@@ -1,32 +1,613 @@
-use anyhow::{Context, Result};
-use serde::Deserialize;
+use crate::linter::LintConfig;
+use anyhow::{bail, Context, Result};
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize)]
+/// Name of the project configuration file
+pub const CONFIG_FILE: &str = "pyx.config.json";
+
+/// Name of the project configuration file in TOML, read when there is no
+/// [`CONFIG_FILE`]
+pub const TOML_CONFIG_FILE: &str = "pyx.config.toml";
+
+/// Environment of `reactpyx run` and of the configuration by default
+pub const DEVELOPMENT: &str = "development";
+
+/// Environment of `reactpyx build` by default
+pub const PRODUCTION: &str = "production";
+
+/// The contents of `pyx.config.json` or `pyx.config.toml`. Unknown keys are
+/// errors, so a typo is reported instead of silently ignored.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase", default)]
 pub struct Config {
-    pub port: Option<u16>,
-    pub entry: Option<String>,
-    #[serde(rename = "entryFunction")]
-    pub entry_function: Option<String>,
-    #[serde(rename = "publicPath")]
-    pub public_path: Option<String>,
-    #[serde(rename = "compilerOptions")]
-    pub compiler_options: Option<serde_json::Value>,
+    /// Configuration file this one is merged over, relative to it
+    #[serde(skip_serializing)]
+    pub extends: Option<String>,
+    /// Port of the development server
+    pub port: u16,
+    /// The `.pyx` module the application starts from
+    pub entry: String,
+    /// Function of the entry module that renders the application
+    pub entry_function: String,
+    /// Directory of the static files
+    pub public_path: String,
+    /// Directory the compiled project is written to
+    pub out_dir: String,
+    pub compiler_options: CompilerOptions,
+    pub lint: LintConfig,
+    /// Names the compiler replaces with a value (`"__DEV__": false`)
+    pub define: BTreeMap<String, Value>,
+    /// Overlays of the configuration by environment name, merged over it
+    /// when that environment is built
+    #[serde(skip_serializing)]
+    pub env: BTreeMap<String, Value>,
+    /// The environment the configuration was loaded for
+    #[serde(skip)]
+    pub environment: String,
+    /// Files the configuration was read from, the bases first
+    #[serde(skip)]
+    pub files: Vec<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            extends: None,
+            port: 8000,
+            entry: "./src/main.pyx".to_string(),
+            entry_function: "MainApp".to_string(),
+            public_path: "./public".to_string(),
+            out_dir: "build".to_string(),
+            compiler_options: CompilerOptions::default(),
+            lint: LintConfig::default(),
+            define: BTreeMap::new(),
+            env: BTreeMap::new(),
+            environment: DEVELOPMENT.to_string(),
+            files: Vec::new(),
+        }
+    }
+}
+
+/// The `compilerOptions` section of `pyx.config.json`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase", default)]
+pub struct CompilerOptions {
+    /// `true` or `false` for all outputs, or `{"css": ..., "js": ...}`
+    pub minify: Minify,
+    /// How JavaScript is minified
+    pub js_minify: JsMinifyOptions,
+    /// Write `.map` files for the compiled Python and JS
+    pub source_maps: bool,
+    /// Also write the CSS of each component to its own file
+    pub css_split: bool,
+    /// Files compiled at the same time; the number of CPUs by default
+    pub concurrency: Option<usize>,
+    /// Browsers the CSS is prefixed and lowered for
+    pub targets: Option<BrowserTargets>,
+}
+
+/// Which outputs are minified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Minify {
+    pub css: bool,
+    pub js: bool,
+}
+
+impl Default for Minify {
+    fn default() -> Self {
+        Minify {
+            css: true,
+            js: true,
+        }
+    }
+}
+
+/// The `compilerOptions.jsMinify` section: how minified JavaScript is
+/// compressed and mangled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase", default)]
+pub struct JsMinifyOptions {
+    /// ECMAScript version the output is printed for
+    pub target: JsTarget,
+    /// Run swc's compressor: constant folding, dead code and unused local
+    /// removal, inlining; the top level is kept
+    pub compress: bool,
+    /// Shorten the names of local variables and functions; the names of
+    /// the top level are kept, as other scripts may use them
+    pub mangle: bool,
+    /// Keep the names of functions and classes when compressing and mangling,
+    /// for `Function.prototype.name` and React's component names
+    pub keep_fnames: bool,
+    /// Remove the calls of `console` methods
+    pub drop_console: bool,
+}
+
+impl Default for JsMinifyOptions {
+    fn default() -> Self {
+        JsMinifyOptions {
+            target: JsTarget::default(),
+            compress: true,
+            mangle: true,
+            keep_fnames: false,
+            drop_console: false,
+        }
+    }
+}
+
+/// ECMAScript version of the minified JavaScript, as `"es5"` to `"es2022"`
+/// or `"esnext"`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsTarget {
+    Es5,
+    Es2015,
+    Es2016,
+    Es2017,
+    Es2018,
+    Es2019,
+    #[default]
+    Es2020,
+    Es2021,
+    Es2022,
+    EsNext,
+}
+
+impl std::str::FromStr for JsTarget {
+    type Err = de::value::Error;
+
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        JsTarget::deserialize(de::value::StrDeserializer::new(target))
+    }
+}
+
+/// Minimum browser versions of the CSS output, as `"major[.minor[.patch]]"`
+/// strings or numbers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase", default)]
+pub struct BrowserTargets {
+    #[serde(
+        deserialize_with = "browser_version",
+        serialize_with = "browser_version_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub android: Option<u32>,
+    #[serde(
+        deserialize_with = "browser_version",
+        serialize_with = "browser_version_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub chrome: Option<u32>,
+    #[serde(
+        deserialize_with = "browser_version",
+        serialize_with = "browser_version_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub edge: Option<u32>,
+    #[serde(
+        deserialize_with = "browser_version",
+        serialize_with = "browser_version_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub firefox: Option<u32>,
+    #[serde(
+        deserialize_with = "browser_version",
+        serialize_with = "browser_version_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ie: Option<u32>,
+    #[serde(
+        deserialize_with = "browser_version",
+        serialize_with = "browser_version_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ios_safari: Option<u32>,
+    #[serde(
+        deserialize_with = "browser_version",
+        serialize_with = "browser_version_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub opera: Option<u32>,
+    #[serde(
+        deserialize_with = "browser_version",
+        serialize_with = "browser_version_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub safari: Option<u32>,
+    #[serde(
+        deserialize_with = "browser_version",
+        serialize_with = "browser_version_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub samsung: Option<u32>,
+}
+
+impl BrowserTargets {
+    /// The targets used when `compilerOptions.targets` is not set: Chrome 90,
+    /// Firefox 88 and Safari 14
+    pub fn modern() -> Self {
+        BrowserTargets {
+            chrome: Some(90 << 16),
+            firefox: Some(88 << 16),
+            safari: Some(14 << 16),
+            ..BrowserTargets::default()
+        }
+    }
 }
 
 impl Config {
-    /// Loads and parses the configuration file
+    /// Loads, parses and validates the configuration file for the
+    /// development environment
     pub fn load(config_path: &str) -> Result<Self> {
-        let config_content = fs::read_to_string(config_path).with_context(|| {
-            format!(
-                "Error reading configuration file '{}'",
-                config_path
-            )
-        })?;
+        Config::load_env(config_path, DEVELOPMENT)
+    }
+
+    /// Loads, parses and validates the configuration file, and the files it
+    /// extends, with the overlay of `environment` merged over it
+    pub fn load_env(config_path: &str, environment: &str) -> Result<Self> {
+        let mut files = Vec::new();
+        let value = read_config_file(Path::new(config_path), &mut files, &mut Vec::new())?;
+        let mut config = Config::from_value(value, environment)
+            .with_context(|| format!("Invalid configuration file '{}'", config_path))?;
+        config.check_environment(environment)?;
+        config.files = files;
+
+        Ok(config)
+    }
+
+    /// Loads the configuration of the project at `project_root`, which is
+    /// the default configuration if the project has no `config_path`
+    pub fn load_project(project_root: &str, config_path: &str) -> Result<Self> {
+        Config::load_project_env(project_root, config_path, DEVELOPMENT)
+    }
+
+    /// Like [`Config::load_project`] for `environment`
+    pub fn load_project_env(
+        project_root: &str,
+        config_path: &str,
+        environment: &str,
+    ) -> Result<Self> {
+        match Config::find(project_root, config_path)? {
+            Some(config_file) => Config::load_env(&config_file.to_string_lossy(), environment),
+            None => {
+                let config = Config {
+                    environment: environment.to_string(),
+                    ..Config::default()
+                };
+                config.check_environment(environment)?;
+                Ok(config)
+            }
+        }
+    }
+
+    /// The configuration file of the project at `project_root`: `config_path`,
+    /// or `pyx.config.toml` when `config_path` is the missing
+    /// `pyx.config.json`
+    pub fn find(project_root: &str, config_path: &str) -> Result<Option<PathBuf>> {
+        let config_file = Path::new(project_root).join(config_path);
+        if config_path != CONFIG_FILE {
+            return Ok(config_file.exists().then_some(config_file));
+        }
+        let toml_file = Path::new(project_root).join(TOML_CONFIG_FILE);
+        match (config_file.exists(), toml_file.exists()) {
+            (true, true) => bail!(
+                "Both {} and {} exist in '{}'; keep one of them",
+                CONFIG_FILE,
+                TOML_CONFIG_FILE,
+                project_root
+            ),
+            (true, false) => Ok(Some(config_file)),
+            (false, true) => Ok(Some(toml_file)),
+            (false, false) => Ok(None),
+        }
+    }
+
+    /// The name of the file the configuration was read from, for messages
+    pub fn file_name(&self) -> String {
+        self.files
+            .last()
+            .and_then(|file| file.file_name())
+            .map_or(CONFIG_FILE.to_string(), |name| {
+                name.to_string_lossy().to_string()
+            })
+    }
 
-        let config: Config = serde_json::from_str(&config_content)
-            .with_context(|| "Error parsing JSON configuration file")?;
+    /// Deserializes and validates a configuration, merging the overlay of
+    /// `environment` over it. Every overlay is checked, not only the one
+    /// used, so that a mistake in one is found before that environment is
+    /// built.
+    fn from_value(base: Value, environment: &str) -> Result<Self> {
+        let mut config: Config = serde_json::from_value(base.clone())?;
+        config.validate()?;
 
+        let mut selected = None;
+        for (name, overlay) in &config.env {
+            if !overlay.is_object() {
+                bail!("`env.{}` must be an object", name);
+            }
+            if overlay.get("env").is_some() || overlay.get("extends").is_some() {
+                bail!("`env.{}` cannot contain `env` or `extends`", name);
+            }
+            let mut merged = base.clone();
+            merge(&mut merged, overlay.clone());
+            let overlaid: Config = serde_json::from_value(merged)
+                .with_context(|| format!("Invalid `env.{}`", name))?;
+            overlaid
+                .validate()
+                .with_context(|| format!("Invalid `env.{}`", name))?;
+            if name == environment {
+                selected = Some(overlaid);
+            }
+        }
+        if let Some(overlaid) = selected {
+            config = overlaid;
+        }
+        config.environment = environment.to_string();
         Ok(config)
     }
+
+    /// Fails unless `environment` is `development`, `production` or one of
+    /// the `env` overlays
+    fn check_environment(&self, environment: &str) -> Result<()> {
+        if [DEVELOPMENT, PRODUCTION].contains(&environment) || self.env.contains_key(environment) {
+            return Ok(());
+        }
+        let mut known = vec![DEVELOPMENT, PRODUCTION];
+        known.extend(self.env.keys().map(String::as_str));
+        known.sort_unstable();
+        known.dedup();
+        bail!(
+            "Unknown environment `{}`; the configuration defines {}",
+            environment,
+            known.join(", ")
+        )
+    }
+
+    /// Checks the values the schema cannot
+    pub fn validate(&self) -> Result<()> {
+        if self.compiler_options.concurrency == Some(0) {
+            bail!("`compilerOptions.concurrency` must be at least 1");
+        }
+        if !is_identifier(&self.entry_function) {
+            bail!(
+                "`entryFunction` must be a Python function name, not `{}`",
+                self.entry_function
+            );
+        }
+        if !self.entry.ends_with(".pyx") {
+            bail!("`entry` must be a `.pyx` file, not `{}`", self.entry);
+        }
+        for (name, value) in &self.define {
+            if !is_identifier(name) {
+                bail!("`define` names must be Python identifiers, not `{}`", name);
+            }
+            if value.is_array() || value.is_object() {
+                bail!(
+                    "`define.{}` must be a string, number, boolean or null",
+                    name
+                );
+            }
+        }
+        self.lint.validate()
+    }
+
+    /// The output directory of the project at `project_root`
+    pub fn out_dir(&self, project_root: &str) -> PathBuf {
+        Path::new(project_root).join(&self.out_dir)
+    }
+
+    /// The static files directory of the project at `project_root`
+    pub fn public_dir(&self, project_root: &str) -> PathBuf {
+        Path::new(project_root).join(&self.public_path)
+    }
+
+    /// The entry module of the project at `project_root`
+    pub fn entry_path(&self, project_root: &str) -> PathBuf {
+        Path::new(project_root).join(&self.entry)
+    }
+
+    /// Whether `compilerOptions.sourceMaps` is enabled
+    pub fn source_maps(&self) -> bool {
+        self.compiler_options.source_maps
+    }
+
+    /// Whether `compilerOptions.cssSplit` is enabled
+    pub fn css_split(&self) -> bool {
+        self.compiler_options.css_split
+    }
+
+    /// Number of files compiled at the same time
+    pub fn concurrency(&self) -> usize {
+        self.compiler_options.concurrency.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(8, |parallelism| parallelism.get())
+        })
+    }
+
+    /// Values of the `define` names, with `__DEV__` true in the development
+    /// environment and false in the others unless it is defined
+    pub fn defines(&self) -> BTreeMap<String, Value> {
+        let mut defines = self.define.clone();
+        defines
+            .entry("__DEV__".to_string())
+            .or_insert_with(|| Value::Bool(self.environment == DEVELOPMENT));
+        defines
+    }
+
+    /// Browser targets of the CSS output
+    pub fn targets(&self) -> BrowserTargets {
+        self.compiler_options
+            .targets
+            .unwrap_or_else(BrowserTargets::modern)
+    }
+}
+
+/// Reads a configuration file, JSON or TOML by its extension, merged over
+/// the files it `extends`. The files read are added to `files`, the bases
+/// first; `chain` holds the files extending this one.
+fn read_config_file(
+    path: &Path,
+    files: &mut Vec<PathBuf>,
+    chain: &mut Vec<PathBuf>,
+) -> Result<Value> {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if chain.contains(&canonical) {
+        bail!(
+            "Configuration file '{}' extends itself through `extends`",
+            path.display()
+        );
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Error reading configuration file '{}'", path.display()))?;
+    let invalid = || format!("Invalid configuration file '{}'", path.display());
+
+    // Each file is checked on its own first, so errors name the file they are in
+    let value = if path.extension().is_some_and(|ext| ext == "toml") {
+        let value = toml::from_str::<Value>(&content).with_context(invalid)?;
+        serde_json::from_value::<Config>(value.clone()).with_context(invalid)?;
+        value
+    } else {
+        serde_json::from_str::<Config>(&content).with_context(invalid)?;
+        serde_json::from_str::<Value>(&content).with_context(invalid)?
+    };
+
+    let value = match value.get("extends").and_then(Value::as_str) {
+        Some(extends) => {
+            let base_path = path.parent().unwrap_or(Path::new("")).join(extends);
+            chain.push(canonical);
+            let base = read_config_file(&base_path, files, chain);
+            chain.pop();
+            let mut base = base.with_context(|| {
+                format!("Error in '{}', extended by '{}'", extends, path.display())
+            })?;
+            merge(&mut base, value);
+            base
+        }
+        None => value,
+    };
+    files.push(path.to_path_buf());
+    Ok(value)
+}
+
+/// Merges `overlay` into `base`: objects key by key, other values replaced
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+impl<'de> Deserialize<'de> for Minify {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MinifyVisitor;
+
+        impl<'de> Visitor<'de> for MinifyVisitor {
+            type Value = Minify;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a boolean or an object with `css` and `js` booleans")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Minify, E> {
+                Ok(Minify {
+                    css: value,
+                    js: value,
+                })
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<Minify, M::Error> {
+                #[derive(Deserialize)]
+                #[serde(deny_unknown_fields)]
+                struct Fields {
+                    #[serde(default = "enabled")]
+                    css: bool,
+                    #[serde(default = "enabled")]
+                    js: bool,
+                }
+                fn enabled() -> bool {
+                    true
+                }
+                let fields = Fields::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                Ok(Minify {
+                    css: fields.css,
+                    js: fields.js,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(MinifyVisitor)
+    }
+}
+
+/// Writes a browser version as a `"major[.minor[.patch]]"` string
+fn browser_version_string<S: Serializer>(
+    version: &Option<u32>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let version = version.unwrap_or_default();
+    let (major, minor, patch) = (version >> 16, version >> 8 & 0xff, version & 0xff);
+    let version = match (minor, patch) {
+        (0, 0) => major.to_string(),
+        (_, 0) => format!("{}.{}", major, minor),
+        _ => format!("{}.{}.{}", major, minor, patch),
+    };
+    serializer.serialize_str(&version)
+}
+
+/// Parses a browser version into the `major << 16 | minor << 8 | patch`
+/// form of lightningcss
+fn browser_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    deserializer.deserialize_any(VersionVisitor).map(Some)
+}
+
+struct VersionVisitor;
+
+impl<'de> Visitor<'de> for VersionVisitor {
+    type Value = u32;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a browser version such as `90` or `\"14.1\"`")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<u32, E> {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<u32, E> {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<u32, E> {
+        let parts: Vec<Option<u32>> = value
+            .split('.')
+            .map(|part| part.parse().ok().filter(|part| *part <= 255))
+            .collect();
+        match parts.as_slice() {
+            [Some(major), rest @ ..] if rest.len() <= 2 && rest.iter().all(Option::is_some) => {
+                let minor = rest.first().copied().flatten().unwrap_or(0);
+                let patch = rest.get(1).copied().flatten().unwrap_or(0);
+                Ok(major << 16 | minor << 8 | patch)
+            }
+            _ => Err(E::custom(format!("invalid browser version `{}`", value))),
+        }
+    }
 }
@@ -1,5 +1,7 @@
+use crate::config::BrowserTargets;
 use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
 use lightningcss::targets::{Browsers, Targets};
+use lightningcss::traits::ToCss;
 use pyo3::prelude::*;
 
 /// Minifies CSS code using `lightningcss`.
@@ -11,13 +13,12 @@ pub fn minify_css_code(css_code: &str) -> PyResult<String> {
         PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Error parsing CSS: {}", e))
     })?;
 
-    // Define targets for autoprefixing (targeting recent modern browsers)
-    // This ensures compatibility with Chrome 90+, Firefox 88+, Safari 14+
+    let browsers = BrowserTargets::modern();
     let targets = Targets {
         browsers: Some(Browsers {
-            chrome: Some(90 << 16),
-            firefox: Some(88 << 16),
-            safari: Some(14 << 16),
+            chrome: browsers.chrome,
+            firefox: browsers.firefox,
+            safari: browsers.safari,
             ..Browsers::default()
         }),
         ..Targets::default()
@@ -46,3 +47,51 @@ pub fn minify_css_code(css_code: &str) -> PyResult<String> {
 
     Ok(minified.code)
 }
+
+/// Processes a stylesheet for the `browsers`, returning each top-level rule
+/// on its own so identical rules of different components can be merged.
+/// With `minify`, the rules are also optimized and printed minified.
+pub(crate) fn css_rules(
+    css_code: &str,
+    browsers: &BrowserTargets,
+    minify: bool,
+) -> Result<Vec<String>, String> {
+    let mut stylesheet = StyleSheet::parse(css_code, ParserOptions::default())
+        .map_err(|e| format!("Error parsing CSS: {}", e))?;
+    let targets = Targets {
+        browsers: Some(Browsers {
+            android: browsers.android,
+            chrome: browsers.chrome,
+            edge: browsers.edge,
+            firefox: browsers.firefox,
+            ie: browsers.ie,
+            ios_saf: browsers.ios_safari,
+            opera: browsers.opera,
+            safari: browsers.safari,
+            samsung: browsers.samsung,
+        }),
+        ..Targets::default()
+    };
+    if minify {
+        stylesheet
+            .minify(MinifyOptions {
+                targets,
+                ..MinifyOptions::default()
+            })
+            .map_err(|e| format!("Error optimizing CSS: {}", e))?;
+    }
+
+    stylesheet
+        .rules
+        .0
+        .iter()
+        .map(|rule| {
+            rule.to_css_string(PrinterOptions {
+                minify,
+                targets,
+                ..PrinterOptions::default()
+            })
+            .map_err(|e| format!("Error generating CSS: {}", e))
+        })
+        .collect()
+}
@@ -0,0 +1,284 @@
+//! Substitutes the `define` values of `pyx.config.json` into compiled code
+//! and removes the branches they make dead.
+//!
+//! Every read of a defined name (`if __DEV__:`, `fetch(API_URL)`) becomes the
+//! Python literal of its value. An `if` whose condition is then constant keeps
+//! only the branch it takes, so `if __DEV__:` blocks are not shipped in
+//! production builds. Removed code is replaced by blank lines, so the lines
+//! after it keep their numbers.
+
+use crate::jsx_transformer::TransformOutput;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tree_sitter::{Node, Parser, Tree};
+
+/// Substitutes `defines` in `output`, compiled Python code, and removes the
+/// branches of the `if` statements they decide
+pub fn apply_defines(output: &mut TransformOutput, defines: &BTreeMap<String, Value>) {
+    if !defines
+        .keys()
+        .any(|name| output.code.contains(name.as_str()))
+    {
+        return;
+    }
+    let Some(tree) = parse(&output.code) else {
+        return;
+    };
+    let mut edits = Vec::new();
+    collect_substitutions(tree.root_node(), &output.code, defines, &mut edits);
+    if edits.is_empty() {
+        return;
+    }
+    output.replace_ranges(&edits);
+
+    let Some(tree) = parse(&output.code) else {
+        return;
+    };
+    let mut edits = Vec::new();
+    collect_dead_branches(tree.root_node(), &output.code, &mut edits);
+    output.replace_ranges(&edits);
+}
+
+fn parse(code: &str) -> Option<Tree> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::LANGUAGE.into())
+        .expect("the tree-sitter Python grammar is compatible");
+    parser.parse(code, None)
+}
+
+/// Replacements of the defined names read in `node`, in source order
+fn collect_substitutions(
+    node: Node<'_>,
+    code: &str,
+    defines: &BTreeMap<String, Value>,
+    edits: &mut Vec<(usize, usize, String)>,
+) {
+    if node.kind() == "identifier" {
+        if let Some(value) = defines.get(&code[node.start_byte()..node.end_byte()]) {
+            if is_read(node) {
+                let literal = python_literal(value, quote_inside(node, code));
+                edits.push((node.start_byte(), node.end_byte(), literal));
+            }
+        }
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_substitutions(child, code, defines, edits);
+    }
+}
+
+/// Whether the identifier `node` reads a variable, rather than naming an
+/// attribute, a keyword argument, a parameter, an import or an assignment
+/// target
+fn is_read(node: Node<'_>) -> bool {
+    let Some(parent) = node.parent() else {
+        return true;
+    };
+    let is_field = |field: &str| parent.child_by_field_name(field) == Some(node);
+    match parent.kind() {
+        "attribute" => !is_field("attribute"),
+        "keyword_argument" | "default_parameter" | "typed_default_parameter" => !is_field("name"),
+        "function_definition" | "class_definition" => !is_field("name"),
+        "assignment" | "augmented_assignment" | "for_statement" | "for_in_clause" => {
+            !is_field("left")
+        }
+        "named_expression" => !is_field("name"),
+        "parameters"
+        | "lambda_parameters"
+        | "typed_parameter"
+        | "list_splat_pattern"
+        | "dictionary_splat_pattern"
+        | "pattern_list"
+        | "tuple_pattern"
+        | "list_pattern"
+        | "as_pattern_target"
+        | "dotted_name"
+        | "aliased_import"
+        | "global_statement"
+        | "nonlocal_statement" => false,
+        _ => true,
+    }
+}
+
+/// The quote a string literal needs inside an f-string interpolation around
+/// `node`, which cannot reuse the quote of the f-string before Python 3.12
+fn quote_inside(node: Node<'_>, code: &str) -> char {
+    let mut ancestor = node.parent();
+    while let Some(current) = ancestor {
+        if current.kind() == "string" {
+            let delimiter = current
+                .child(0)
+                .map_or("", |start| &code[start.start_byte()..start.end_byte()]);
+            return if delimiter.contains('"') { '\'' } else { '"' };
+        }
+        ancestor = current.parent();
+    }
+    '"'
+}
+
+/// The Python literal of a `define` value: a string, number, boolean or null
+fn python_literal(value: &Value, quote: char) -> String {
+    match value {
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        Value::Number(number) => number.to_string(),
+        Value::String(text) => {
+            let mut literal = String::with_capacity(text.len() + 2);
+            literal.push(quote);
+            for c in text.chars() {
+                match c {
+                    '\\' => literal.push_str("\\\\"),
+                    '\n' => literal.push_str("\\n"),
+                    '\r' => literal.push_str("\\r"),
+                    '\t' => literal.push_str("\\t"),
+                    c if c == quote => {
+                        literal.push('\\');
+                        literal.push(c);
+                    }
+                    c if c.is_control() => literal.push_str(&format!("\\x{:02x}", c as u32)),
+                    c => literal.push(c),
+                }
+            }
+            literal.push(quote);
+            literal
+        }
+        // Rejected by `Config::validate`
+        Value::Array(_) | Value::Object(_) => "None".to_string(),
+    }
+}
+
+/// Edits that remove the branches of `if` statements with constant
+/// conditions, in source order
+fn collect_dead_branches(node: Node<'_>, code: &str, edits: &mut Vec<(usize, usize, String)>) {
+    if node.kind() != "if_statement" {
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            collect_dead_branches(child, code, edits);
+        }
+        return;
+    }
+
+    let mut cursor = node.walk();
+    let alternatives: Vec<Node<'_>> = node
+        .children_by_field_name("alternative", &mut cursor)
+        .collect();
+    let mut clauses = vec![node];
+    clauses.extend(alternatives);
+
+    for (index, clause) in clauses.iter().enumerate() {
+        let condition = clause.child_by_field_name("condition");
+        let body_field = if clause.kind() == "else_clause" {
+            "body"
+        } else {
+            "consequence"
+        };
+        let Some(body) = clause.child_by_field_name(body_field) else {
+            return;
+        };
+        match condition.and_then(|condition| truthiness(condition, code)) {
+            Some(false) if clauses.len() == 1 => {
+                edits.push(removal(node.start_byte(), node.end_byte(), "pass", code));
+                return;
+            }
+            Some(false) => {
+                edits.push(removal(body.start_byte(), body.end_byte(), "pass", code));
+            }
+            Some(true) => {
+                collect_dead_branches(body, code, edits);
+                if index + 1 < clauses.len() {
+                    edits.push(removal(body.end_byte(), node.end_byte(), "", code));
+                }
+                return;
+            }
+            None => collect_dead_branches(body, code, edits),
+        }
+    }
+}
+
+/// An edit replacing `start..end` with `text` and as many line breaks as it
+/// removes
+fn removal(start: usize, end: usize, text: &str, code: &str) -> (usize, usize, String) {
+    let mut replacement = text.to_string();
+    replacement.extend(code[start..end].matches('\n').map(|_| '\n'));
+    (start, end, replacement)
+}
+
+/// A literal value of a constant expression
+#[derive(PartialEq)]
+enum Constant {
+    None,
+    Number(f64),
+    Str(String),
+}
+
+/// Whether the expression `node` is always true or always false, when its
+/// operands are literals
+fn truthiness(node: Node<'_>, code: &str) -> Option<bool> {
+    match node.kind() {
+        "parenthesized_expression" => truthiness(node.named_child(0)?, code),
+        "not_operator" => truthiness(node.child_by_field_name("argument")?, code).map(|v| !v),
+        "boolean_operator" => {
+            let left = truthiness(node.child_by_field_name("left")?, code)?;
+            let operator = node.child_by_field_name("operator")?.kind();
+            match (operator, left) {
+                ("and", false) => Some(false),
+                ("or", true) => Some(true),
+                _ => truthiness(node.child_by_field_name("right")?, code),
+            }
+        }
+        "comparison_operator" if node.named_child_count() == 2 => {
+            let left = constant(node.named_child(0)?, code)?;
+            let right = constant(node.named_child(1)?, code)?;
+            let mut cursor = node.walk();
+            let operators: Vec<&str> = node
+                .children_by_field_name("operators", &mut cursor)
+                .map(|operator| operator.kind())
+                .collect();
+            match operators.as_slice() {
+                ["=="] => Some(left == right),
+                ["!="] => Some(left != right),
+                _ => None,
+            }
+        }
+        _ => match constant(node, code)? {
+            Constant::None => Some(false),
+            Constant::Number(value) => Some(value != 0.0),
+            Constant::Str(value) => Some(!value.is_empty()),
+        },
+    }
+}
+
+fn constant(node: Node<'_>, code: &str) -> Option<Constant> {
+    let text = &code[node.start_byte()..node.end_byte()];
+    match node.kind() {
+        "none" => Some(Constant::None),
+        "true" => Some(Constant::Number(1.0)),
+        "false" => Some(Constant::Number(0.0)),
+        "integer" => text
+            .replace('_', "")
+            .parse::<i64>()
+            .ok()
+            .map(|value| Constant::Number(value as f64)),
+        "float" => text.replace('_', "").parse().ok().map(Constant::Number),
+        "string" => {
+            // Only plain strings, without prefixes, escapes or interpolations
+            let mut cursor = node.walk();
+            let mut value = String::new();
+            for child in node.children(&mut cursor) {
+                match child.kind() {
+                    "string_start" if matches!(&code[child.byte_range()], "\"" | "'") => {}
+                    "string_end" => {}
+                    "string_content" if child.named_child_count() == 0 => {
+                        value.push_str(&code[child.byte_range()]);
+                    }
+                    _ => return None,
+                }
+            }
+            Some(Constant::Str(value))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,241 @@
+//! Structured diagnostics reported by the `.pyx` parser and the compiler.
+//!
+//! A [`Diagnostic`] carries its position both as a byte [`Span`] and as a
+//! 1-based line/column, so it can be rendered with a code frame in the CLI or
+//! raised in Python as a `PyxSyntaxError`.
+
+use crate::jsx_parser::{LineIndex, Span};
+use colored::Colorize;
+use pyo3::create_exception;
+use pyo3::prelude::*;
+use std::fmt::{self, Write};
+use std::path::Path;
+
+create_exception!(
+    _core,
+    PyxSyntaxError,
+    pyo3::exceptions::PySyntaxError,
+    "Raised when a `.pyx` source cannot be compiled."
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A problem found in a `.pyx` source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier such as `PYX005`
+    pub code: &'static str,
+    pub file: Option<String>,
+    pub message: String,
+    pub span: Span,
+    /// 1-based line of `span.start`
+    pub line: usize,
+    /// 1-based byte column of `span.start`
+    pub column: usize,
+    pub help: Option<Box<str>>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        severity: Severity,
+        code: &'static str,
+        message: impl Into<String>,
+        span: Span,
+        lines: &LineIndex,
+    ) -> Self {
+        let (line, column) = lines.line_col(span.start);
+        Diagnostic {
+            severity,
+            code,
+            file: None,
+            message: message.into(),
+            span,
+            line,
+            column,
+            help: None,
+        }
+    }
+
+    pub fn error(
+        code: &'static str,
+        message: impl Into<String>,
+        span: Span,
+        lines: &LineIndex,
+    ) -> Self {
+        Diagnostic::new(Severity::Error, code, message, span, lines)
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into().into_boxed_str());
+        self
+    }
+
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// Renders the diagnostic with a code frame of `source`
+    pub fn render(&self, source: &str) -> String {
+        self.render_with(source, false)
+    }
+
+    /// Like [`Diagnostic::render`], with terminal colors
+    pub fn render_colored(&self, source: &str) -> String {
+        self.render_with(source, true)
+    }
+
+    fn render_with(&self, source: &str, color: bool) -> String {
+        let header = format!("{}[{}]", self.severity, self.code);
+        let header = match (color, self.severity) {
+            (false, _) => header,
+            (true, Severity::Error) => header.red().bold().to_string(),
+            (true, Severity::Warning) => header.yellow().bold().to_string(),
+        };
+        let message = if color {
+            self.message.bold().to_string()
+        } else {
+            self.message.clone()
+        };
+
+        let mut text = format!("{}: {}\n", header, message);
+        let _ = writeln!(
+            text,
+            "  --> {}:{}:{}",
+            self.file.as_deref().unwrap_or("<input>"),
+            self.line,
+            self.column
+        );
+        let line_end = source[self.span.start.min(source.len())..]
+            .find('\n')
+            .map_or(source.len(), |i| self.span.start + i);
+        let width = self.span.end.min(line_end).saturating_sub(self.span.start);
+        for line in code_frame(source, self.line, self.column, width, color).lines() {
+            let _ = writeln!(text, "  {}", line);
+        }
+        if let Some(help) = &self.help {
+            let label = if color {
+                "help".cyan().bold().to_string()
+            } else {
+                "help".to_string()
+            };
+            let _ = writeln!(text, "  = {}: {}", label, help);
+        }
+        text
+    }
+
+    /// Converts the diagnostic into a `PyxSyntaxError` with `code`, `severity`,
+    /// `column`, `help`, `span` and the standard `SyntaxError` attributes set
+    pub fn to_py_err(&self, source: Option<&str>) -> PyErr {
+        Python::attach(|py| {
+            let text = source
+                .and_then(|s| s.lines().nth(self.line - 1))
+                .map(str::to_string);
+            let err = PyxSyntaxError::new_err((
+                self.message.clone(),
+                (self.file.clone(), self.line, self.column, text),
+            ));
+            let value = err.value(py);
+            let set_attributes = || -> PyResult<()> {
+                value.setattr("code", self.code)?;
+                value.setattr("severity", self.severity.to_string())?;
+                value.setattr("column", self.column)?;
+                value.setattr("help", self.help.as_deref())?;
+                value.setattr("span", (self.span.start, self.span.end))?;
+                Ok(())
+            };
+            if let Err(e) = set_attributes() {
+                return e;
+            }
+            err
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(
+                f,
+                "{}:{}:{}: {}[{}]: {}",
+                file, self.line, self.column, self.severity, self.code, self.message
+            ),
+            None => write!(
+                f,
+                "{}[{}]: {} (line {}, column {})",
+                self.severity, self.code, self.message, self.line, self.column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Renders the lines around `line` with a `>` marker and `width` carets under
+/// `column` (both 1-based)
+pub fn code_frame(source: &str, line: usize, column: usize, width: usize, color: bool) -> String {
+    const CONTEXT: usize = 2;
+    let lines: Vec<&str> = source.lines().collect();
+    if line == 0 || line > lines.len() {
+        return String::new();
+    }
+    let first = line.saturating_sub(CONTEXT).max(1);
+    let last = (line + CONTEXT).min(lines.len());
+    let gutter = last.to_string().len();
+
+    let mut frame = String::new();
+    for number in first..=last {
+        let text = lines[number - 1];
+        if number != line {
+            let _ = writeln!(frame, "  {:>gutter$} | {}", number, text);
+            continue;
+        }
+        let marker = if color {
+            ">".red().bold().to_string()
+        } else {
+            ">".to_string()
+        };
+        let _ = writeln!(frame, "{} {:>gutter$} | {}", marker, number, text);
+
+        let prefix = text.get(..column.saturating_sub(1)).unwrap_or(text);
+        let padding: String = prefix
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underlined = text
+            .get(prefix.len()..(prefix.len() + width).min(text.len()))
+            .unwrap_or("");
+        let carets = "^".repeat(underlined.chars().count().max(1));
+        let carets = if color {
+            carets.red().bold().to_string()
+        } else {
+            carets
+        };
+        let _ = writeln!(frame, "  {:>gutter$} | {}{}", "", padding, carets);
+    }
+    frame
+}
+
+/// Shows paths under the working directory relative to it
+pub fn display_path(path: &Path) -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+        .to_string_lossy()
+        .replace('\\', "/")
+}
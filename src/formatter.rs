@@ -0,0 +1,262 @@
+//! Formatter for `.pyx` sources.
+//!
+//! Only the JSX is reformatted; the Python around it (and inside `{...}`
+//! expressions) is copied as written. An element stays on one line when it
+//! fits in [`MAX_WIDTH`] columns. Otherwise its attributes go one per line
+//! and its children one per line, indented by four spaces from the line the
+//! element starts on. Childless elements are written self-closing (`<div />`).
+//!
+//! Formatting never changes the compiled output: text whose leading or
+//! trailing whitespace is significant keeps its children on one line.
+
+use crate::diagnostics::Diagnostic;
+use crate::jsx_parser::{
+    parse_pyx, JsxAttr, JsxAttrValue, JsxChild, JsxElement, PyCode, PyPart, RAW_TEXT_ELEMENTS,
+};
+use crate::jsx_transformer::collapse_jsx_text;
+
+/// Width up to which an element is kept on a single line
+pub const MAX_WIDTH: usize = 88;
+
+const INDENT: &str = "    ";
+
+/// Formats the JSX of a `.pyx` source
+pub fn format_pyx(source: &str) -> Result<String, Diagnostic> {
+    let code = parse_pyx(source)?;
+    let mut printer = Printer::new(source, false);
+    for part in &code.parts {
+        match part {
+            PyPart::Code { text, .. } => printer.out.push_str(text),
+            PyPart::Element(element) => printer.element(element),
+        }
+    }
+    Ok(printer.out)
+}
+
+/// A child as it is printed: text is collapsed with the JSX whitespace rules
+enum Child<'e> {
+    Text(String),
+    Expr(&'e PyCode),
+    Element(&'e JsxElement),
+    /// The comment lines of a `{# ...}` child
+    Comment(Vec<&'e str>),
+}
+
+struct Printer<'s> {
+    source: &'s str,
+    out: String,
+    /// Print everything on one line
+    flat: bool,
+}
+
+impl<'s> Printer<'s> {
+    fn new(source: &'s str, flat: bool) -> Self {
+        Printer {
+            source,
+            out: String::with_capacity(source.len()),
+            flat,
+        }
+    }
+
+    fn current_line(&self) -> &str {
+        let start = self.out.rfind('\n').map_or(0, |i| i + 1);
+        &self.out[start..]
+    }
+
+    /// The indentation of the line being printed
+    fn indentation(&self) -> String {
+        let line = self.current_line();
+        line[..line.len() - line.trim_start().len()].to_string()
+    }
+
+    /// Prints `print` on a single line, if the result has no line breaks and
+    /// fits after the current column
+    fn try_flat(&self, print: impl FnOnce(&mut Printer<'s>)) -> Option<String> {
+        let mut printer = Printer::new(self.source, true);
+        print(&mut printer);
+        let width = self.current_line().chars().count() + printer.out.chars().count();
+        (!printer.out.contains('\n') && width <= MAX_WIDTH).then_some(printer.out)
+    }
+
+    fn newline(&mut self, indentation: &str) {
+        self.out.push('\n');
+        self.out.push_str(indentation);
+    }
+
+    fn element(&mut self, element: &JsxElement) {
+        if !self.flat {
+            if let Some(flat) = self.try_flat(|printer| printer.element(element)) {
+                self.out.push_str(&flat);
+                return;
+            }
+        }
+
+        let indentation = self.indentation();
+        let inner = format!("{}{}", indentation, INDENT);
+        let self_closing = is_empty(self.source, element);
+        let end = if self_closing { " />" } else { ">" };
+
+        self.out.push('<');
+        self.out.push_str(&element.name);
+        let attributes = self.try_flat(|printer| {
+            for attr in &element.attributes {
+                printer.out.push(' ');
+                printer.attribute(attr);
+            }
+            printer.out.push_str(end);
+        });
+        match attributes {
+            Some(attributes) => self.out.push_str(&attributes),
+            None => {
+                for attr in &element.attributes {
+                    self.newline(&inner);
+                    self.attribute(attr);
+                }
+                self.newline(&indentation);
+                self.out.push_str(end.trim_start());
+            }
+        }
+        if self_closing {
+            return;
+        }
+
+        if RAW_TEXT_ELEMENTS.contains(&element.name.as_str()) {
+            let content = element.children.iter().map(|child| match child {
+                JsxChild::Text { value, .. } => value.as_str(),
+                _ => "",
+            });
+            self.out.extend(content);
+        } else {
+            let children = printed_children(self.source, element);
+            if !self.flat && can_break(&children) {
+                for child in &children {
+                    self.newline(&inner);
+                    self.child(child);
+                }
+                self.newline(&indentation);
+            } else {
+                for child in &children {
+                    self.child(child);
+                }
+            }
+        }
+        self.out.push_str("</");
+        self.out.push_str(&element.name);
+        self.out.push('>');
+    }
+
+    fn attribute(&mut self, attr: &JsxAttr) {
+        let attribute = match attr {
+            JsxAttr::Spread { code, .. } => return self.expression("...", code),
+            JsxAttr::Named(attribute) => attribute,
+        };
+        self.out.push_str(&attribute.name);
+        match &attribute.value {
+            JsxAttrValue::Str { value, span } => {
+                self.out.push('=');
+                if value.contains('"') {
+                    self.out.push_str(&self.source[span.start..span.end]);
+                } else {
+                    self.out.push('"');
+                    self.out.push_str(value);
+                    self.out.push('"');
+                }
+            }
+            JsxAttrValue::Expr(code) => {
+                self.out.push('=');
+                self.expression("", code);
+            }
+            JsxAttrValue::Implicit => {}
+        }
+    }
+
+    fn child(&mut self, child: &Child<'_>) {
+        match child {
+            Child::Text(text) => self.out.push_str(text),
+            Child::Expr(code) => self.expression("", code),
+            Child::Element(element) => self.element(element),
+            Child::Comment(lines) => {
+                let indentation = self.indentation();
+                self.out.push('{');
+                for (i, line) in lines.iter().enumerate() {
+                    if i > 0 {
+                        self.newline(&indentation);
+                    }
+                    self.out.push_str(line);
+                }
+                self.newline(&indentation);
+                self.out.push('}');
+            }
+        }
+    }
+
+    /// Prints `{prefix code}`, trimming the whitespace around the code unless
+    /// it ends in a comment that needs the line break after it
+    fn expression(&mut self, prefix: &str, code: &PyCode) {
+        self.out.push('{');
+        self.out.push_str(prefix);
+        let last = code.parts.len().saturating_sub(1);
+        for (i, part) in code.parts.iter().enumerate() {
+            match part {
+                PyPart::Code { text, .. } => {
+                    let mut text = text.as_str();
+                    if i == 0 {
+                        text = text.trim_start();
+                    }
+                    if i == last {
+                        let trimmed = text.trim_end();
+                        if !trimmed.rsplit('\n').next().unwrap_or("").contains('#') {
+                            text = trimmed;
+                        }
+                    }
+                    self.out.push_str(text);
+                }
+                PyPart::Element(element) => self.element(element),
+            }
+        }
+        self.out.push('}');
+    }
+}
+
+/// Whether an element can be written as `<tag />` without changing its
+/// children
+fn is_empty(source: &str, element: &JsxElement) -> bool {
+    if element.is_fragment() {
+        return false;
+    }
+    if element.self_closing || RAW_TEXT_ELEMENTS.contains(&element.name.as_str()) {
+        return element.children.is_empty();
+    }
+    printed_children(source, element).is_empty()
+}
+
+/// The children of a non-raw element, without the whitespace-only text the
+/// compiler drops
+fn printed_children<'e>(source: &'e str, element: &'e JsxElement) -> Vec<Child<'e>> {
+    element
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            JsxChild::Text { value, .. } => collapse_jsx_text(value).map(Child::Text),
+            JsxChild::Expr { code, .. } => Some(Child::Expr(code)),
+            JsxChild::Element(element) => Some(Child::Element(element)),
+            JsxChild::Comment { span } => {
+                let lines = source[span.start + 1..span.end - 1]
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect();
+                Some(Child::Comment(lines))
+            }
+        })
+        .collect()
+}
+
+/// Children can go on separate lines unless a text child starts or ends with
+/// whitespace, which the line breaks would trim
+fn can_break(children: &[Child<'_>]) -> bool {
+    children.iter().all(|child| match child {
+        Child::Text(text) => text.trim() == text,
+        _ => true,
+    })
+}
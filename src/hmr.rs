@@ -1,70 +1,117 @@
-use anyhow::Result;
+//! File watcher of the development server. Events are gathered into batches,
+//! so that saving several files at once rebuilds them together, and sorted
+//! into `.pyx` sources, stylesheets and configuration files.
+
+use log::error;
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
-use tokio::sync::mpsc;
-use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
 
-/// Start a watcher for changes in component files
-pub async fn start_hmr_watcher(
-    project_root: &str,
-    tx: mpsc::Sender<String>,
-    config_tx: mpsc::Sender<String>, // Sends paths for configuration changes
-) -> Result<()> {
-    // Channel for file system change notifications
-    let (notify_tx, notify_rx) = std::sync::mpsc::channel();
-    let mut watcher: RecommendedWatcher =
-        Watcher::new(notify_tx, std::time::Duration::from_secs(1))?;
+/// How long a batch waits for more events after its first one
+const BATCH_DELAY: Duration = Duration::from_millis(100);
 
-    // Directories and files to watch
-    let components_path = Path::new(project_root).join("src").join("components");
-    let config_path = Path::new(project_root).join("pyx.config.json");
+/// Files changed together
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub pyx: BTreeSet<PathBuf>,
+    pub css: BTreeSet<PathBuf>,
+    /// Whether a configuration file changed
+    pub config: bool,
+}
 
-    // Watch component directories and configuration file
-    watcher.watch(&components_path, RecursiveMode::Recursive)?;
-    watcher.watch(&config_path, RecursiveMode::NonRecursive)?;
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.pyx.is_empty() && self.css.is_empty() && !self.config
+    }
+}
 
-    // Convert std_mpsc::Receiver to a Tokio stream
-    let (stream_tx, mut stream_rx) = mpsc::channel(100);
-    tokio::spawn(async move {
-        while let Ok(event) = notify_rx.recv() {
-            if let Err(_) = stream_tx.send(event).await {
-                break; // Stop watching if the channel is closed
+pub struct HmrWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    config_files: Vec<PathBuf>,
+}
+
+impl HmrWatcher {
+    /// Watches the sources under `src_dir` and the `config_files`, which need
+    /// not exist yet
+    pub fn new(src_dir: &Path, config_files: &[PathBuf]) -> notify::Result<Self> {
+        let (tx, events) = channel();
+        let mut watcher = RecommendedWatcher::new(
+            tx,
+            notify::Config::default().with_poll_interval(Duration::from_secs(1)),
+        )?;
+        watcher.watch(src_dir, RecursiveMode::Recursive)?;
+
+        // Editors often replace a file instead of writing it, so the
+        // directories of the configuration files are watched
+        let config_files: Vec<PathBuf> = config_files.iter().map(|file| absolute(file)).collect();
+        let dirs: BTreeSet<&Path> = config_files
+            .iter()
+            .filter_map(|file| file.parent())
+            .collect();
+        for dir in dirs {
+            if dir.exists() {
+                watcher.watch(dir, RecursiveMode::NonRecursive)?;
             }
         }
-    });
 
-    // Process async events using Tokio Stream
-    let mut stream = ReceiverStream::new(stream_rx);
+        Ok(HmrWatcher {
+            _watcher: watcher,
+            events,
+            config_files,
+        })
+    }
+
+    /// Waits for files to change and returns them, or `None` once the
+    /// watcher stopped
+    pub fn next_changes(&self) -> Option<Changes> {
+        let mut changes = Changes::default();
+        while changes.is_empty() {
+            self.add(&mut changes, self.events.recv().ok()?);
+        }
+        loop {
+            match self.events.recv_timeout(BATCH_DELAY) {
+                Ok(event) => self.add(&mut changes, event),
+                Err(RecvTimeoutError::Timeout) => return Some(changes),
+                Err(RecvTimeoutError::Disconnected) => return Some(changes),
+            }
+        }
+    }
 
-    while let Some(event) = stream.next().await {
-        match event {
-            Event {
-                kind: EventKind::Modify(_) | EventKind::Create(_),
-                paths,
-                ..
-            } => {
-                for path in paths {
-                    if let Some(ext) = path.extension() {
-                        // Process `.pyx` files
-                        if ext == "pyx" {
-                            if let Some(file_path) = path.to_str() {
-                                tx.send(file_path.to_string()).await.unwrap_or_default();
-                            }
-                        } else if path.ends_with("pyx.config.json") {
-                            // Process configuration file changes
-                            if let Some(file_path) = path.to_str() {
-                                config_tx
-                                    .send(file_path.to_string())
-                                    .await
-                                    .unwrap_or_default();
-                            }
-                        }
-                    }
+    fn add(&self, changes: &mut Changes, event: notify::Result<Event>) {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                error!("Error watching files: {}", e);
+                return;
+            }
+        };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        for path in event.paths {
+            if self.config_files.contains(&absolute(&path)) {
+                changes.config = true;
+                continue;
+            }
+            let ext = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase());
+            match ext.as_deref() {
+                Some("pyx") => {
+                    changes.pyx.insert(path);
                 }
+                Some("css") => {
+                    changes.css.insert(path);
+                }
+                _ => {}
             }
-            _ => {}
         }
     }
+}
 
-    Ok(())
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
 }
@@ -1,16 +1,19 @@
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
+use pyo3::exceptions::PyTypeError;
 use pyo3::prelude::*;
-use pyo3::types::PyAny;
+use pyo3::types::{PyAny, PyTuple};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
+type StateCell = Arc<Mutex<Py<PyAny>>>;
+type ComponentState = DashMap<String, StateCell>;
+
 // Global state managed by components
 // SessionID -> ComponentID -> Key -> Value
-static GLOBAL_STATE: Lazy<
-    DashMap<String, DashMap<String, DashMap<String, Arc<Mutex<Py<PyAny>>>>>>,
-> = Lazy::new(DashMap::new);
+static GLOBAL_STATE: Lazy<DashMap<String, DashMap<String, ComponentState>>> =
+    Lazy::new(DashMap::new);
 
 fn get_session_id(py: Python<'_>) -> PyResult<String> {
     let context_module = py.import("reactpyx.context")?;
@@ -20,6 +23,35 @@ fn get_session_id(py: Python<'_>) -> PyResult<String> {
     Ok(session_id.unwrap_or_else(|| "global".to_string()))
 }
 
+/// Instance path of the component being rendered (e.g. `App/0:Counter`), or
+/// `root` outside of component nodes
+fn get_component_path(py: Python<'_>) -> PyResult<String> {
+    let context_module = py.import("reactpyx.context")?;
+    let path: Option<String> = context_module
+        .call_method0("get_current_component_path")?
+        .extract()?;
+    Ok(path.unwrap_or_else(|| "root".to_string()))
+}
+
+/// State slot of a hook called in its short form: the current component
+/// instance, and the call site id the `.pyx` compiler passes as `hook_id`
+fn hook_slot(py: Python<'_>, hook: &str, hook_id: Option<&str>) -> PyResult<(String, String)> {
+    let Some(hook_id) = hook_id else {
+        return Err(PyTypeError::new_err(format!(
+            "{}() without a component_id and key must be called from a compiled .pyx component",
+            hook
+        )));
+    };
+    Ok((get_component_path(py)?, hook_id.to_string()))
+}
+
+fn wrong_arity(hook: &str, forms: &str, given: usize) -> PyErr {
+    PyTypeError::new_err(format!(
+        "{}() takes {}, got {} positional arguments",
+        hook, forms, given
+    ))
+}
+
 thread_local! {
     // Cache for effect dependencies, organized by effect ID
     static LAST_EFFECT_DEPS: RefCell<HashMap<String, u64>> = RefCell::new(HashMap::new());
@@ -117,9 +149,37 @@ impl Dispatch {
     }
 }
 
-/// Hook to manage component state
+/// Hook to manage component state, called as `use_state(initial_value)` in
+/// compiled components or `use_state(component_id, key, initial_value)`
 #[pyfunction]
+#[pyo3(signature = (*args, hook_id=None))]
 pub fn use_state(
+    py: Python<'_>,
+    args: &Bound<'_, PyTuple>,
+    hook_id: Option<&str>,
+) -> PyResult<(Py<PyAny>, Py<SetState>)> {
+    let (component_id, key, initial_value) = match args.len() {
+        1 => {
+            let (component_id, key) = hook_slot(py, "use_state", hook_id)?;
+            (component_id, key, args.get_item(0)?)
+        }
+        3 => (
+            args.get_item(0)?.extract()?,
+            args.get_item(1)?.extract()?,
+            args.get_item(2)?,
+        ),
+        n => {
+            return Err(wrong_arity(
+                "use_state",
+                "(initial_value) or (component_id, key, initial_value)",
+                n,
+            ))
+        }
+    };
+    state_hook(py, &component_id, &key, initial_value.unbind())
+}
+
+fn state_hook(
     py: Python<'_>,
     component_id: &str,
     key: &str,
@@ -216,9 +276,47 @@ pub fn use_context(py: Python<'_>, component_id: &str, key: &str) -> PyResult<Op
     }))
 }
 
-/// Hook for effects with dependencies
+/// Hook for effects with dependencies, called as
+/// `use_effect_with_deps(effect_function, dependencies)` in compiled
+/// components or `use_effect_with_deps(effect_id, effect_function, dependencies)`
 #[pyfunction]
+#[pyo3(signature = (*args, hook_id=None))]
 pub fn use_effect_with_deps(
+    py: Python<'_>,
+    args: &Bound<'_, PyTuple>,
+    hook_id: Option<&str>,
+) -> PyResult<()> {
+    let (effect_id, effect_function, dependencies) = match args.len() {
+        2 => {
+            let (component_id, key) = hook_slot(py, "use_effect_with_deps", hook_id)?;
+            (
+                format!("{}#{}", component_id, key),
+                args.get_item(0)?,
+                args.get_item(1)?,
+            )
+        }
+        3 => (
+            args.get_item(0)?.extract()?,
+            args.get_item(1)?,
+            args.get_item(2)?,
+        ),
+        n => {
+            return Err(wrong_arity(
+                "use_effect_with_deps",
+                "(effect_function, dependencies) or (effect_id, effect_function, dependencies)",
+                n,
+            ))
+        }
+    };
+    effect_hook(
+        py,
+        &effect_id,
+        effect_function.unbind(),
+        dependencies.extract()?,
+    )
+}
+
+fn effect_hook(
     py: Python<'_>,
     effect_id: &str,
     effect_function: Py<PyAny>,
@@ -273,9 +371,45 @@ fn hash_dependencies(dependencies: &[Py<PyAny>], py: Python<'_>) -> PyResult<u64
     Ok(hasher.finish())
 }
 
-/// Hook to manage state with a reducer
+/// Hook to manage state with a reducer, called as
+/// `use_reducer(reducer, initial_state)` in compiled components or
+/// `use_reducer(component_id, key, reducer, initial_state)`
 #[pyfunction]
+#[pyo3(signature = (*args, hook_id=None))]
 pub fn use_reducer(
+    py: Python<'_>,
+    args: &Bound<'_, PyTuple>,
+    hook_id: Option<&str>,
+) -> PyResult<(Py<PyAny>, Py<Dispatch>)> {
+    let (component_id, key, reducer, initial_state) = match args.len() {
+        2 => {
+            let (component_id, key) = hook_slot(py, "use_reducer", hook_id)?;
+            (component_id, key, args.get_item(0)?, args.get_item(1)?)
+        }
+        4 => (
+            args.get_item(0)?.extract()?,
+            args.get_item(1)?.extract()?,
+            args.get_item(2)?,
+            args.get_item(3)?,
+        ),
+        n => {
+            return Err(wrong_arity(
+                "use_reducer",
+                "(reducer, initial_state) or (component_id, key, reducer, initial_state)",
+                n,
+            ))
+        }
+    };
+    reducer_hook(
+        py,
+        &component_id,
+        &key,
+        reducer.unbind(),
+        initial_state.unbind(),
+    )
+}
+
+fn reducer_hook(
     py: Python<'_>,
     component_id: &str,
     key: &str,
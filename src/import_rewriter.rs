@@ -0,0 +1,253 @@
+//! Rewrites the imports between compiled `.pyx` modules into relative
+//! imports.
+//!
+//! Sources import each other by their path under `src` (`from
+//! components.Header import Header`). The compiled modules live in the
+//! `build/components` package, where those absolute names do not resolve, so
+//! they are rewritten relative to the importing module (`from .components.Header
+//! import Header` in `App`, `from .Header import Header` in `components.Home`).
+//!
+//! The modules each import may load are returned for the module graph.
+
+use crate::diagnostics::Diagnostic;
+use crate::jsx_parser::{LineIndex, Span};
+use crate::jsx_transformer::TransformOutput;
+use crate::module_graph::ModuleImport;
+use crate::python_validator::original_offset;
+use std::collections::HashSet;
+use tree_sitter::{Node, Parser};
+
+/// Rewrites the imports of project `modules` (module and package names) in
+/// `output`, the compiled code of `source` as the module `module`. Returns
+/// the modules the imports of the code may load.
+pub fn rewrite_project_imports(
+    source: &str,
+    output: &mut TransformOutput,
+    module: &str,
+    modules: &HashSet<String>,
+) -> Result<Vec<ModuleImport>, Diagnostic> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::LANGUAGE.into())
+        .expect("the tree-sitter Python grammar is compatible");
+    let Some(tree) = parser.parse(&output.code, None) else {
+        return Ok(Vec::new());
+    };
+
+    let mut imports = Vec::new();
+    collect_imports(tree.root_node(), &mut imports);
+
+    let code = output.code.as_str();
+    let text = |node: Node<'_>| &code[node.start_byte()..node.end_byte()];
+    let mut loaded = Vec::new();
+    let mut edits = Vec::new();
+    for import in imports {
+        let (start, end) = source_span(source, output, import);
+        let top_level = !has_function_ancestor(import);
+        for target in loaded_modules(import, code, module) {
+            loaded.push(ModuleImport {
+                module: target,
+                start,
+                end,
+                top_level,
+            });
+        }
+
+        if import.kind() == "import_from_statement" {
+            let Some(name) = import.child_by_field_name("module_name") else {
+                continue;
+            };
+            if name.kind() == "dotted_name" && modules.contains(text(name)) {
+                let relative = relative_module(module, text(name));
+                edits.push((name.start_byte(), name.end_byte(), relative));
+            }
+            continue;
+        }
+
+        let mut cursor = import.walk();
+        let names: Vec<Node<'_>> = import.children_by_field_name("name", &mut cursor).collect();
+        let target = |name: Node<'_>| match name.kind() {
+            "aliased_import" => name.child_by_field_name("name").map(text),
+            _ => Some(text(name)),
+        };
+        if !names
+            .iter()
+            .any(|name| target(*name).is_some_and(|target| modules.contains(target)))
+        {
+            continue;
+        }
+
+        let mut statements = Vec::new();
+        for name in names {
+            let Some(target) = target(name).filter(|target| modules.contains(*target)) else {
+                statements.push(format!("import {}", text(name)));
+                continue;
+            };
+            let alias = name.child_by_field_name("alias").map(text);
+            let (package, last) = target.rsplit_once('.').unwrap_or(("", target));
+            if alias.is_none() && !package.is_empty() {
+                return Err(unaliased_import(
+                    source, output, name, target, package, last,
+                ));
+            }
+            let mut statement =
+                format!("from {} import {}", relative_module(module, package), last);
+            if let Some(alias) = alias {
+                statement.push_str(" as ");
+                statement.push_str(alias);
+            }
+            statements.push(statement);
+        }
+        // Keep the line count, so the lines after the import keep their numbers
+        let mut replacement = statements.join("; ");
+        replacement.extend(text(import).matches('\n').map(|_| '\n'));
+        edits.push((import.start_byte(), import.end_byte(), replacement));
+    }
+
+    output.replace_ranges(&edits);
+    Ok(loaded)
+}
+
+/// Import statements in source order, nested ones included
+fn collect_imports<'t>(node: Node<'t>, imports: &mut Vec<Node<'t>>) {
+    if matches!(node.kind(), "import_statement" | "import_from_statement") {
+        imports.push(node);
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_imports(child, imports);
+    }
+}
+
+/// The modules an import statement of `module` may load: the packages on the
+/// way, the module it names and, for `from` imports, the submodules of the
+/// names imported
+fn loaded_modules(import: Node<'_>, code: &str, module: &str) -> Vec<String> {
+    let text = |node: Node<'_>| &code[node.start_byte()..node.end_byte()];
+    let dotted = |name: Node<'_>| match name.kind() {
+        "aliased_import" => name.child_by_field_name("name").map(text),
+        "dotted_name" => Some(text(name)),
+        _ => None,
+    };
+    let mut cursor = import.walk();
+    let names: Vec<&str> = import
+        .children_by_field_name("name", &mut cursor)
+        .filter_map(dotted)
+        .collect();
+
+    let mut loaded = Vec::new();
+    let mut add_with_packages = |target: &str| {
+        let mut prefix = String::new();
+        for part in target.split('.') {
+            if !prefix.is_empty() {
+                prefix.push('.');
+            }
+            prefix.push_str(part);
+            loaded.push(prefix.clone());
+        }
+    };
+    if import.kind() == "import_statement" {
+        names.iter().for_each(|name| add_with_packages(name));
+        return loaded;
+    }
+
+    let Some(base) = import
+        .child_by_field_name("module_name")
+        .and_then(|name| match name.kind() {
+            "relative_import" => absolute_module(module, text(name)),
+            _ => Some(text(name).to_string()),
+        })
+    else {
+        return loaded;
+    };
+    add_with_packages(&base);
+    for name in names {
+        if base.is_empty() {
+            add_with_packages(name);
+        } else {
+            add_with_packages(&format!("{}.{}", base, name));
+        }
+    }
+    loaded.retain(|target| !target.is_empty());
+    loaded
+}
+
+/// The absolute name of the relative module `relative` (`..shared.Button`)
+/// imported from `module`, or `None` when it goes above the top package
+pub(crate) fn absolute_module(module: &str, relative: &str) -> Option<String> {
+    let name = relative.trim_start_matches('.');
+    let dots = relative.len() - name.len();
+    let mut package: Vec<&str> = module.split('.').collect();
+    package.pop();
+    if dots - 1 > package.len() {
+        return None;
+    }
+    package.truncate(package.len() - (dots - 1));
+    package.extend(name.split('.').filter(|part| !part.is_empty()));
+    Some(package.join("."))
+}
+
+/// Whether `node` is inside a function, where its imports run when the
+/// function is called
+fn has_function_ancestor(node: Node<'_>) -> bool {
+    let mut ancestor = node.parent();
+    while let Some(current) = ancestor {
+        if matches!(current.kind(), "function_definition" | "lambda") {
+            return true;
+        }
+        ancestor = current.parent();
+    }
+    false
+}
+
+/// The span of `node`, a node of the compiled code, in the source
+pub(crate) fn source_span(
+    source: &str,
+    output: &TransformOutput,
+    node: Node<'_>,
+) -> (usize, usize) {
+    let offset = |offset| original_offset(source, &output.code, &output.mappings, offset);
+    let start = offset(node.start_byte());
+    let end = offset(node.end_byte()).max(start + 1).min(source.len());
+    (start, end.max(start))
+}
+
+/// The name `target` imported from `module`, as a relative module name
+fn relative_module(module: &str, target: &str) -> String {
+    let mut package: Vec<&str> = module.split('.').collect();
+    package.pop();
+    let target: Vec<&str> = target.split('.').filter(|part| !part.is_empty()).collect();
+    let common = package
+        .iter()
+        .zip(&target)
+        .take_while(|(a, b)| a == b)
+        .count();
+    format!(
+        "{}{}",
+        ".".repeat(package.len() - common + 1),
+        target[common..].join(".")
+    )
+}
+
+/// `import package.module` binds `package`, which has no relative form
+fn unaliased_import(
+    source: &str,
+    output: &TransformOutput,
+    name: Node<'_>,
+    target: &str,
+    package: &str,
+    last: &str,
+) -> Diagnostic {
+    let (start, end) = source_span(source, output, name);
+    Diagnostic::error(
+        "PYX017",
+        format!("`import {}` cannot be compiled", target),
+        Span::new(start, end),
+        &LineIndex::new(source),
+    )
+    .with_help(format!(
+        "use `from {} import {}` or `import {} as {}`; compiled modules import each other relatively",
+        package, last, target, last
+    ))
+}
@@ -0,0 +1,597 @@
+//! Incremental `.pyx` transformation for editors and HMR.
+//!
+//! A [`PyxDocument`] keeps a source split into its module-level statements,
+//! each with the Python generated for it. Statements are found with
+//! tree-sitter on a copy of the source in which every JSX element is blanked
+//! out to an equally long `( ... )`, so the host code parses as plain Python
+//! and byte offsets line up with the `.pyx` source.
+//!
+//! After a batch of text edits, tree-sitter reparses the host code
+//! incrementally, and only the statements touched by an edit or whose Python
+//! structure changed (e.g. after typing an opening quote) are transformed
+//! again.
+
+use crate::diagnostics::Diagnostic;
+use crate::jsx_parser::{parse_pyx_module, parse_pyx_range, PyCode, PyPart, Span};
+use crate::jsx_transformer::transform_statements;
+use crate::source_map::Mapping;
+use anyhow::{anyhow, bail, Result};
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use tree_sitter::{InputEdit, Parser, Point, Tree};
+
+/// Replaces `start..end` (byte offsets in the previous text) with `text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// A changed region of the generated Python: `start..old_end` of the previous
+/// output became `start..new_end`. Edits are listed in order, each relative
+/// to the output with the earlier ones already applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputEdit {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+/// `(start, old_end, new_end)` output regions, as returned to Python
+type PyOutputEdits = Vec<(usize, usize, usize)>;
+
+/// A module-level statement, with the blank lines and comments before it
+#[derive(Debug, Clone)]
+struct Chunk {
+    span: Span,
+    code: String,
+    /// Generated offsets relative to `code`, original ones to `span.start`
+    mappings: Vec<Mapping>,
+    /// Hook calls per scope in this statement
+    hook_counts: HashMap<String, usize>,
+    /// The statement failed to compile and `code` is its last good output
+    stale: bool,
+}
+
+/// A `.pyx` source together with its generated Python, updated by text edits
+#[pyclass]
+pub struct PyxDocument {
+    module: String,
+    source: String,
+    /// `source` with the JSX elements blanked out, as parsed by tree-sitter
+    masked: Vec<u8>,
+    tree: Tree,
+    chunks: Vec<Chunk>,
+}
+
+impl PyxDocument {
+    /// Parses and transforms `source`, the code of the Python module `module`
+    pub fn new(source: &str, module: &str) -> Result<Self> {
+        let parsed = parse_pyx_module(source)?;
+        let mut masked = source.as_bytes().to_vec();
+        mask_elements(&mut masked, &parsed.code);
+        let tree = parse_host(&masked, None)?;
+        let mut document = PyxDocument {
+            module: module.to_string(),
+            source: source.to_string(),
+            masked,
+            tree,
+            chunks: Vec::new(),
+        };
+        document.chunks = document
+            .transform_range(Span::new(0, source.len()), &HashMap::new())?
+            .ok_or_else(|| anyhow!("Could not split the module into statements"))?;
+        Ok(document)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The generated Python; statements that failed to compile after the
+    /// last edits keep their last good output
+    pub fn output(&self) -> String {
+        self.chunks
+            .iter()
+            .map(|chunk| chunk.code.as_str())
+            .collect()
+    }
+
+    /// Positions in [`PyxDocument::output`] and the source positions they
+    /// come from
+    pub fn mappings(&self) -> Vec<Mapping> {
+        let mut mappings = Vec::new();
+        let mut generated = 0;
+        for chunk in &self.chunks {
+            mappings.extend(chunk.mappings.iter().map(|m| Mapping {
+                generated: generated + m.generated,
+                original: chunk.span.start + m.original,
+            }));
+            generated += chunk.code.len();
+        }
+        mappings
+    }
+
+    /// Whether some statements failed to compile after the last edits
+    pub fn is_stale(&self) -> bool {
+        self.chunks.iter().any(|chunk| chunk.stale)
+    }
+
+    /// Applies `edits`, which must not overlap, and transforms the affected
+    /// statements again. Returns the changed regions of the output.
+    ///
+    /// The edits are applied even if the result does not compile; the error
+    /// is returned and the affected statements keep their last good output
+    /// until a later edit fixes them.
+    pub fn apply_edits(&mut self, edits: &[TextEdit]) -> Result<Vec<OutputEdit>> {
+        let mut edits = edits.to_vec();
+        edits.sort_by_key(|edit| (edit.start, edit.end));
+        for edit in &edits {
+            if edit.start > edit.end
+                || edit.end > self.source.len()
+                || !self.source.is_char_boundary(edit.start)
+                || !self.source.is_char_boundary(edit.end)
+            {
+                bail!("Invalid edit range {}..{}", edit.start, edit.end);
+            }
+        }
+        if let Some(pair) = edits.windows(2).find(|pair| pair[0].end > pair[1].start) {
+            bail!(
+                "Overlapping edits {}..{} and {}..{}",
+                pair[0].start,
+                pair[0].end,
+                pair[1].start,
+                pair[1].end
+            );
+        }
+        if edits.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Statements touched by an edit, with their spans in the edited text
+        let mut pieces: Vec<(Chunk, bool)> = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .map(|mut chunk| {
+                let touched = edits
+                    .iter()
+                    .any(|e| chunk.span.start <= e.end && e.start <= chunk.span.end);
+                chunk.span = Span::new(
+                    shift(&edits, chunk.span.start, false),
+                    shift(&edits, chunk.span.end, true),
+                );
+                let dirty = touched || chunk.stale;
+                (chunk, dirty)
+            })
+            .collect();
+
+        for edit in edits.iter().rev() {
+            let start_position = point_at(self.source.as_bytes(), edit.start);
+            let old_end_position = point_at(self.source.as_bytes(), edit.end);
+            self.source.replace_range(edit.start..edit.end, &edit.text);
+            self.masked.splice(edit.start..edit.end, edit.text.bytes());
+            let new_end = edit.start + edit.text.len();
+            self.tree.edit(&InputEdit {
+                start_byte: edit.start,
+                old_end_byte: edit.end,
+                new_end_byte: new_end,
+                start_position,
+                old_end_position,
+                new_end_position: point_at(self.source.as_bytes(), new_end),
+            });
+        }
+
+        match self.update(&mut pieces) {
+            Ok(replacements) => Ok(self.finish(pieces, replacements)),
+            Err(e) => {
+                // Keep the tree in sync with the edited text for the next edits
+                self.tree = parse_host(&self.masked, Some(&self.tree))?;
+                self.chunks = merge_dirty(pieces)
+                    .into_iter()
+                    .map(|(mut chunk, dirty)| {
+                        chunk.stale |= dirty;
+                        chunk
+                    })
+                    .collect();
+                Err(e)
+            }
+        }
+    }
+
+    /// Transforms the dirty pieces again, growing them until the statements
+    /// around them are unaffected. Returns the new chunks of each dirty piece
+    /// of the final `pieces`, by index.
+    fn update(&mut self, pieces: &mut Vec<(Chunk, bool)>) -> Result<HashMap<usize, Vec<Chunk>>> {
+        'retry: loop {
+            *pieces = merge_dirty(std::mem::take(pieces));
+
+            // Parse each dirty region to find its elements, growing regions
+            // that end inside brackets or a string
+            for i in 0..pieces.len() {
+                if !pieces[i].1 {
+                    continue;
+                }
+                let span = pieces[i].0.span;
+                match parse_pyx_range(&self.source, span, HashMap::new()) {
+                    Ok(parsed) if parsed.balanced || i + 1 == pieces.len() => {
+                        let mut region = self.source.as_bytes()[span.start..span.end].to_vec();
+                        mask_elements_at(&mut region, &parsed.code, span.start);
+                        self.remask(span, &region);
+                    }
+                    Err(e) if i + 1 == pieces.len() => return Err(e.into()),
+                    _ => {
+                        pieces[i + 1].1 = true;
+                        continue 'retry;
+                    }
+                }
+            }
+
+            // Reparse the host code; statements whose structure changed, or
+            // that now continue across a region boundary, are dirty too
+            let tree = parse_host(&self.masked, Some(&self.tree))?;
+            let changed: Vec<Span> = self
+                .tree
+                .changed_ranges(&tree)
+                .map(|r| Span::new(r.start_byte, r.end_byte.max(r.start_byte + 1)))
+                .collect();
+            self.tree = tree;
+
+            let mut grown = false;
+            for i in 0..pieces.len() {
+                let span = pieces[i].0.span;
+                if pieces[i].1 {
+                    if i > 0 && !pieces[i - 1].1 && self.statement_crosses(span.start) {
+                        pieces[i - 1].1 = true;
+                        grown = true;
+                    }
+                    if i + 1 < pieces.len() && !pieces[i + 1].1 && self.statement_crosses(span.end)
+                    {
+                        pieces[i + 1].1 = true;
+                        grown = true;
+                    }
+                } else if changed
+                    .iter()
+                    .any(|r| r.start < span.end && span.start < r.end)
+                {
+                    pieces[i].1 = true;
+                    grown = true;
+                }
+            }
+            if grown {
+                continue 'retry;
+            }
+
+            // Transform the dirty regions statement by statement, continuing
+            // the hook numbering of the statements before them
+            let mut replacements = HashMap::new();
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            let mut renumbered: Vec<String> = Vec::new();
+            for i in 0..pieces.len() {
+                let (piece, dirty) = &pieces[i];
+                if !dirty {
+                    if piece
+                        .hook_counts
+                        .keys()
+                        .any(|scope| renumbered.contains(scope))
+                    {
+                        pieces[i].1 = true;
+                        continue 'retry;
+                    }
+                    add_counts(&mut counts, &piece.hook_counts);
+                    continue;
+                }
+                let Some(chunks) = self.transform_range(piece.span, &counts)? else {
+                    pieces[i + 1].1 = true;
+                    continue 'retry;
+                };
+                let mut new_counts = HashMap::new();
+                for chunk in &chunks {
+                    add_counts(&mut new_counts, &chunk.hook_counts);
+                }
+                for scope in piece.hook_counts.keys().chain(new_counts.keys()) {
+                    if piece.hook_counts.get(scope) != new_counts.get(scope) {
+                        renumbered.push(scope.clone());
+                    }
+                }
+                add_counts(&mut counts, &new_counts);
+                replacements.insert(i, chunks);
+            }
+            return Ok(replacements);
+        }
+    }
+
+    /// Replaces the dirty pieces by their new chunks and lists the output
+    /// regions that changed
+    fn finish(
+        &mut self,
+        pieces: Vec<(Chunk, bool)>,
+        mut replacements: HashMap<usize, Vec<Chunk>>,
+    ) -> Vec<OutputEdit> {
+        let mut output_edits = Vec::new();
+        let mut offset = 0;
+        for (i, (piece, _)) in pieces.into_iter().enumerate() {
+            let Some(chunks) = replacements.remove(&i) else {
+                offset += piece.code.len();
+                self.chunks.push(piece);
+                continue;
+            };
+            let old = &piece.code;
+            let new: String = chunks.iter().map(|chunk| chunk.code.as_str()).collect();
+            let mut prefix = old
+                .bytes()
+                .zip(new.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            while !old.is_char_boundary(prefix) {
+                prefix -= 1;
+            }
+            let mut suffix = old[prefix..]
+                .bytes()
+                .rev()
+                .zip(new[prefix..].bytes().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            while !old.is_char_boundary(old.len() - suffix) {
+                suffix -= 1;
+            }
+            if prefix < old.len() || old.len() != new.len() {
+                output_edits.push(OutputEdit {
+                    start: offset + prefix,
+                    old_end: offset + old.len() - suffix,
+                    new_end: offset + new.len() - suffix,
+                });
+            }
+            offset += new.len();
+            self.chunks.extend(chunks);
+        }
+        output_edits
+    }
+
+    /// Transforms the statements in `span`, which must start at a statement,
+    /// one chunk per statement. Returns `None` when `span` ends inside
+    /// brackets before the end of the source.
+    fn transform_range(
+        &self,
+        span: Span,
+        counts: &HashMap<String, usize>,
+    ) -> Result<Option<Vec<Chunk>>, Diagnostic> {
+        let mut ends = self.statement_starts(span);
+        ends.push(span.end);
+
+        let mut chunks = Vec::new();
+        let mut counts = counts.clone();
+        let mut start = span.start;
+        for end in ends {
+            let last = end == span.end;
+            let chunk_span = Span::new(start, end);
+            let (output, parsed) = match transform_statements(
+                &self.source,
+                chunk_span,
+                &self.module,
+                counts.clone(),
+            ) {
+                Ok((output, parsed)) if parsed.balanced || end == self.source.len() => {
+                    (output, parsed)
+                }
+                Ok(_) if last => return Ok(None),
+                Err(e) if last => return Err(e),
+                // Not a statement boundary for the `.pyx` parser: merge
+                // with the next statement
+                _ => continue,
+            };
+
+            let mut hook_counts = HashMap::new();
+            for call in &parsed.hook_calls {
+                *hook_counts.entry(call.scope.clone()).or_insert(0) += 1;
+            }
+            counts = parsed.hook_counts;
+            chunks.push(Chunk {
+                span: chunk_span,
+                code: output.code,
+                mappings: output
+                    .mappings
+                    .into_iter()
+                    .map(|m| Mapping {
+                        generated: m.generated,
+                        original: m.original - start,
+                    })
+                    .collect(),
+                hook_counts,
+                stale: false,
+            });
+            start = end;
+        }
+        Ok(Some(chunks))
+    }
+
+    /// Starts of the module-level statements strictly inside `span`
+    fn statement_starts(&self, span: Span) -> Vec<usize> {
+        let root = self.tree.root_node();
+        let mut cursor = root.walk();
+        root.children(&mut cursor)
+            .filter(|node| !node.is_extra())
+            .map(|node| node.start_byte())
+            .filter(|&start| {
+                start > span.start && start < span.end && self.source.as_bytes()[start - 1] == b'\n'
+            })
+            .collect()
+    }
+
+    /// Whether a module-level statement starts before `offset` and ends after it
+    fn statement_crosses(&self, offset: usize) -> bool {
+        let root = self.tree.root_node();
+        let mut cursor = root.walk();
+        let crosses = root
+            .children(&mut cursor)
+            .any(|node| node.start_byte() < offset && offset < node.end_byte());
+        crosses
+    }
+
+    /// Replaces the masked text of `span` with `region`, of the same length
+    fn remask(&mut self, span: Span, region: &[u8]) {
+        if self.masked[span.start..span.end] == *region {
+            return;
+        }
+        self.masked[span.start..span.end].copy_from_slice(region);
+        let start_position = point_at(&self.masked, span.start);
+        let end_position = point_at(&self.masked, span.end);
+        self.tree.edit(&InputEdit {
+            start_byte: span.start,
+            old_end_byte: span.end,
+            new_end_byte: span.end,
+            start_position,
+            old_end_position: end_position,
+            new_end_position: end_position,
+        });
+    }
+}
+
+#[pymethods]
+impl PyxDocument {
+    #[new]
+    #[pyo3(signature = (source, module=None))]
+    fn py_new(source: &str, module: Option<&str>) -> PyResult<Self> {
+        PyxDocument::new(source, module.unwrap_or("__main__")).map_err(|e| to_py_err(e, source))
+    }
+
+    #[getter(source)]
+    fn py_source(&self) -> String {
+        self.source.clone()
+    }
+
+    #[getter(output)]
+    fn py_output(&self) -> String {
+        self.output()
+    }
+
+    #[getter(stale)]
+    fn py_stale(&self) -> bool {
+        self.is_stale()
+    }
+
+    /// Applies `(start, end, text)` edits (byte offsets in the current
+    /// source) and returns the changed `(start, old_end, new_end)` regions of
+    /// the output
+    #[pyo3(name = "apply_edits")]
+    fn py_apply_edits(&mut self, edits: Vec<(usize, usize, String)>) -> PyResult<PyOutputEdits> {
+        let edits: Vec<TextEdit> = edits
+            .into_iter()
+            .map(|(start, end, text)| TextEdit { start, end, text })
+            .collect();
+        self.apply_edits(&edits)
+            .map(|changes| {
+                changes
+                    .into_iter()
+                    .map(|c| (c.start, c.old_end, c.new_end))
+                    .collect()
+            })
+            .map_err(|e| to_py_err(e, &self.source))
+    }
+}
+
+/// Applies text edits to the previous parse of a `.pyx` source, in place,
+/// transforming only the affected statements again. Returns the new output and
+/// its changed `(start, old_end, new_end)` regions.
+#[pyfunction]
+pub fn incremental_jsx_transform(
+    previous: &Bound<'_, PyxDocument>,
+    edits: Vec<(usize, usize, String)>,
+) -> PyResult<(String, PyOutputEdits)> {
+    let mut document = previous.borrow_mut();
+    let changes = document.py_apply_edits(edits)?;
+    Ok((document.output(), changes))
+}
+
+fn to_py_err(error: anyhow::Error, source: &str) -> PyErr {
+    match error.downcast_ref::<Diagnostic>() {
+        Some(diagnostic) => diagnostic.to_py_err(Some(source)),
+        None => PyErr::new::<pyo3::exceptions::PyValueError, _>(error.to_string()),
+    }
+}
+
+/// Maps an offset of the text before `edits` to the edited text. Insertions
+/// at `offset` land before it unless `after_insertions` is set.
+fn shift(edits: &[TextEdit], offset: usize, after_insertions: bool) -> usize {
+    let mut shifted = offset as isize;
+    for edit in edits {
+        if edit.start < offset || (after_insertions && edit.start == offset) {
+            shifted += edit.text.len() as isize - (edit.end - edit.start) as isize;
+        }
+    }
+    shifted as usize
+}
+
+/// Merges each run of dirty pieces into a single piece that keeps their
+/// combined output
+fn merge_dirty(pieces: Vec<(Chunk, bool)>) -> Vec<(Chunk, bool)> {
+    let mut merged: Vec<(Chunk, bool)> = Vec::with_capacity(pieces.len());
+    for (chunk, dirty) in pieces {
+        match merged.last_mut() {
+            Some((previous, true)) if dirty => {
+                let generated = previous.code.len();
+                let original = chunk.span.start - previous.span.start;
+                previous
+                    .mappings
+                    .extend(chunk.mappings.iter().map(|m| Mapping {
+                        generated: generated + m.generated,
+                        original: original + m.original,
+                    }));
+                previous.span.end = chunk.span.end;
+                previous.code.push_str(&chunk.code);
+                add_counts(&mut previous.hook_counts, &chunk.hook_counts);
+                previous.stale |= chunk.stale;
+            }
+            _ => merged.push((chunk, dirty)),
+        }
+    }
+    merged
+}
+
+fn add_counts(total: &mut HashMap<String, usize>, counts: &HashMap<String, usize>) {
+    for (scope, count) in counts {
+        *total.entry(scope.clone()).or_insert(0) += count;
+    }
+}
+
+/// Blanks out the module-level JSX elements of `code` in `text`
+fn mask_elements(text: &mut [u8], code: &PyCode) {
+    mask_elements_at(text, code, 0);
+}
+
+/// Like [`mask_elements`] for a `text` that starts at `offset` of the source
+fn mask_elements_at(text: &mut [u8], code: &PyCode, offset: usize) {
+    for part in &code.parts {
+        let PyPart::Element(element) = part else {
+            continue;
+        };
+        let (start, end) = (element.span.start - offset, element.span.end - offset);
+        for byte in &mut text[start..end] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+        text[start] = b'(';
+        text[end - 1] = b')';
+    }
+}
+
+fn parse_host(text: &[u8], old_tree: Option<&Tree>) -> Result<Tree> {
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::LANGUAGE.into())?;
+    parser
+        .parse(text, old_tree)
+        .ok_or_else(|| anyhow!("tree-sitter could not parse the module"))
+}
+
+fn point_at(text: &[u8], offset: usize) -> Point {
+    let before = &text[..offset];
+    let row = before.iter().filter(|&&b| b == b'\n').count();
+    let line_start = before
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1);
+    Point {
+        row,
+        column: offset - line_start,
+    }
+}
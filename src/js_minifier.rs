@@ -1,24 +1,94 @@
+use crate::config::{JsMinifyOptions, JsTarget};
+use pyo3::prelude::*;
 use std::io::{self};
 use swc_core::common::sync::Lrc;
-use swc_core::common::{FileName, SourceMap};
-use swc_core::ecma::ast::EsVersion;
+use swc_core::common::{
+    source_map::SourceMapGenConfig, BytePos, FileName, LineCol, Mark, SourceMap, GLOBALS,
+};
+use swc_core::ecma::ast::*;
 use swc_core::ecma::codegen::text_writer::JsWriter;
-use swc_core::ecma::codegen::Emitter;
+use swc_core::ecma::codegen::{Config as CodegenConfig, Emitter};
+use swc_core::ecma::minifier::optimize;
+use swc_core::ecma::minifier::option::{
+    CompressOptions, ExtraOptions, MangleOptions, MinifyOptions,
+};
 use swc_core::ecma::parser::{lexer::Lexer, EsSyntax, Parser, StringInput, Syntax};
-use pyo3::prelude::*;
+use swc_core::ecma::transforms::base::{fixer::fixer, hygiene::hygiene, resolver};
 
 /// Minifies JavaScript code using `swc_core`.
 #[pyfunction]
-pub fn minify_js_code(js: &str) -> Result<String, io::Error> {
+#[pyo3(signature = (js, target = "es2020", compress = true, mangle = true, keep_fnames = false, drop_console = false))]
+pub fn minify_js_code(
+    js: &str,
+    target: &str,
+    compress: bool,
+    mangle: bool,
+    keep_fnames: bool,
+    drop_console: bool,
+) -> Result<String, io::Error> {
+    let options = py_options(target, compress, mangle, keep_fnames, drop_console)?;
+    minify_js(js, &options, None).map(|(code, _)| code)
+}
+
+/// Minifies JavaScript code and returns it together with a Source Map v3
+/// document whose single source is `source_name`.
+#[pyfunction]
+#[pyo3(signature = (js, source_name, target = "es2020", compress = true, mangle = true, keep_fnames = false, drop_console = false))]
+pub fn minify_js_code_with_source_map(
+    js: &str,
+    source_name: &str,
+    target: &str,
+    compress: bool,
+    mangle: bool,
+    keep_fnames: bool,
+    drop_console: bool,
+) -> Result<(String, String), io::Error> {
+    let options = py_options(target, compress, mangle, keep_fnames, drop_console)?;
+    let (code, source_map) = minify_js(js, &options, Some(source_name))?;
+    Ok((code, source_map.unwrap_or_default()))
+}
+
+fn py_options(
+    target: &str,
+    compress: bool,
+    mangle: bool,
+    keep_fnames: bool,
+    drop_console: bool,
+) -> Result<JsMinifyOptions, io::Error> {
+    let target = target.parse().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid target: {}", e),
+        )
+    })?;
+    Ok(JsMinifyOptions {
+        target,
+        compress,
+        mangle,
+        keep_fnames,
+        drop_console,
+    })
+}
+
+/// Minifies JavaScript code with `options`, and with a Source Map v3
+/// document whose single source is `source_name` when one is given.
+pub fn minify_js(
+    js: &str,
+    options: &JsMinifyOptions,
+    source_name: Option<&str>,
+) -> Result<(String, Option<String>), io::Error> {
     let cm: Lrc<SourceMap> = Default::default();
 
     // Create a source file for the compiler
-    let fm = cm.new_source_file(Lrc::new(FileName::Custom("input.js".into())), js.to_string());
+    let fm = cm.new_source_file(
+        Lrc::new(FileName::Custom(source_name.unwrap_or("input.js").into())),
+        js.to_string(),
+    );
 
     // Use a lexer to parse JavaScript
     let lexer = Lexer::new(
         Syntax::Es(EsSyntax::default()), // Define syntax for ES
-        EsVersion::Es2021,               // Target ES version
+        EsVersion::latest(),             // Accept any syntax, the target is for output
         StringInput::from(&*fm),         // Input source
         None,
     );
@@ -32,29 +102,137 @@ pub fn minify_js_code(js: &str) -> Result<String, io::Error> {
         )
     })?;
 
+    // Compress and mangle the AST with swc's minifier
+    let module = GLOBALS.set(&Default::default(), || {
+        let unresolved_mark = Mark::new();
+        let top_level_mark = Mark::new();
+        let program =
+            Program::Module(module).apply(resolver(unresolved_mark, top_level_mark, false));
+        let mut program = optimize(
+            program,
+            cm.clone(),
+            None,
+            None,
+            &minify_options(options),
+            &ExtraOptions {
+                unresolved_mark,
+                top_level_mark,
+                mangle_name_cache: None,
+            },
+        );
+        // Mangling leaves names unique; without it, compressing may not
+        if !options.mangle {
+            program = program.apply(hygiene());
+        }
+        match program.apply(fixer(None)) {
+            Program::Module(module) => module,
+            Program::Script(_) => unreachable!("the minifier keeps a module a module"),
+        }
+    });
+
     // Emit and minify JavaScript code
     let mut buf = vec![];
-    let writer = Box::new(JsWriter::new(cm.clone(), "\n", &mut buf, None));
-    let mut emitter = Emitter {
-        cfg: Default::default(),
-        cm,
-        comments: None,
-        wr: writer,
-    };
-
-    // Emit code as JavaScript from the AST
-    emitter.emit_module(&module).map_err(|e| {
+    let mut mappings: Vec<(BytePos, LineCol)> = vec![];
+    {
+        let srcmap = if source_name.is_some() {
+            Some(&mut mappings)
+        } else {
+            None
+        };
+        let writer = Box::new(JsWriter::new(cm.clone(), "\n", &mut buf, srcmap));
+        let mut emitter = Emitter {
+            cfg: CodegenConfig::default()
+                .with_minify(true)
+                .with_target(es_version(options.target)),
+            cm: cm.clone(),
+            comments: None,
+            wr: writer,
+        };
+
+        // Emit code as JavaScript from the AST
+        emitter.emit_module(&module).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Error emitting JS: {:?}", e),
+            )
+        })?;
+    }
+
+    // Convert buffer to a UTF-8 string
+    let code = String::from_utf8(buf).map_err(|e| {
         io::Error::new(
             io::ErrorKind::InvalidData,
-            format!("Error emitting JS: {:?}", e),
+            format!("Invalid UTF-8 output: {}", e),
         )
     })?;
 
-    // Convert buffer to a UTF-8 string and return it
-    String::from_utf8(buf).map_err(|e| {
+    if source_name.is_none() {
+        return Ok((code, None));
+    }
+
+    let mut map_buf = vec![];
+    cm.build_source_map(&mappings, None, InlineSources)
+        .to_writer(&mut map_buf)
+        .map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Error writing JS source map: {}", e),
+            )
+        })?;
+    let map = String::from_utf8(map_buf).map_err(|e| {
         io::Error::new(
             io::ErrorKind::InvalidData,
-            format!("Invalid UTF-8 output: {}", e),
+            format!("Invalid UTF-8 source map: {}", e),
         )
-    })
+    })?;
+    Ok((code, Some(map)))
+}
+
+/// Source map options: keep file names as given and embed the sources
+struct InlineSources;
+
+impl SourceMapGenConfig for InlineSources {
+    fn file_name_to_source(&self, f: &FileName) -> String {
+        f.to_string()
+    }
+
+    fn inline_sources_content(&self, _f: &FileName) -> bool {
+        true
+    }
+}
+
+/// swc's minifier options for `options`. The top level is neither dropped
+/// nor renamed, as other scripts may use it.
+fn minify_options(options: &JsMinifyOptions) -> MinifyOptions {
+    MinifyOptions {
+        compress: options.compress.then(|| CompressOptions {
+            ecma: es_version(options.target),
+            keep_fnames: options.keep_fnames,
+            keep_classnames: options.keep_fnames,
+            drop_console: options.drop_console,
+            ..CompressOptions::default()
+        }),
+        mangle: options.mangle.then(|| MangleOptions {
+            top_level: Some(false),
+            keep_fn_names: options.keep_fnames,
+            keep_class_names: options.keep_fnames,
+            ..MangleOptions::default()
+        }),
+        ..MinifyOptions::default()
+    }
+}
+
+fn es_version(target: JsTarget) -> EsVersion {
+    match target {
+        JsTarget::Es5 => EsVersion::Es5,
+        JsTarget::Es2015 => EsVersion::Es2015,
+        JsTarget::Es2016 => EsVersion::Es2016,
+        JsTarget::Es2017 => EsVersion::Es2017,
+        JsTarget::Es2018 => EsVersion::Es2018,
+        JsTarget::Es2019 => EsVersion::Es2019,
+        JsTarget::Es2020 => EsVersion::Es2020,
+        JsTarget::Es2021 => EsVersion::Es2021,
+        JsTarget::Es2022 => EsVersion::Es2022,
+        JsTarget::EsNext => EsVersion::EsNext,
+    }
 }
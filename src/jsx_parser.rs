@@ -0,0 +1,1009 @@
+//! Tokenizer and syntax tree for `.pyx` sources.
+//!
+//! A `.pyx` file is ordinary Python in which JSX elements may appear wherever
+//! an expression may start. The parser scans the Python host code token by
+//! token (so string literals, f-strings and comments are never mistaken for
+//! markup) and switches to the JSX grammar only when a `<` shows up in
+//! expression position, e.g. after `return`, `=`, `(` or `,`.
+
+use crate::diagnostics::Diagnostic;
+use std::collections::HashMap;
+
+/// Byte range in the original `.pyx` source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
+
+/// A run of Python code, possibly interleaved with JSX elements
+#[derive(Debug, Clone)]
+pub struct PyCode {
+    pub parts: Vec<PyPart>,
+    pub span: Span,
+}
+
+impl PyCode {
+    /// Calls `visit` on every element of the code, nested ones included
+    pub fn for_each_element<'c>(&'c self, visit: &mut impl FnMut(&'c JsxElement)) {
+        for part in &self.parts {
+            if let PyPart::Element(element) = part {
+                element.for_each_element(visit);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PyPart {
+    /// Python source copied verbatim to the output
+    Code { text: String, span: Span },
+    /// A JSX element embedded in the Python code
+    Element(JsxElement),
+}
+
+#[derive(Debug, Clone)]
+pub struct JsxElement {
+    /// Tag name; empty for a fragment (`<>...</>`)
+    pub name: String,
+    pub name_span: Span,
+    pub attributes: Vec<JsxAttr>,
+    pub children: Vec<JsxChild>,
+    pub self_closing: bool,
+    /// Span of the closing tag (`</div>`), or of `/>` for self-closing tags
+    pub closing_span: Span,
+    pub span: Span,
+}
+
+impl JsxElement {
+    pub fn is_fragment(&self) -> bool {
+        self.name.is_empty()
+    }
+
+    /// Capitalised (`<Button>`) and dotted (`<ui.Button>`) tags name a Python
+    /// component rather than an HTML element
+    pub fn is_component(&self) -> bool {
+        is_component_name(&self.name)
+    }
+
+    /// The last attribute written with `name`, ignoring spreads
+    pub fn attribute(&self, name: &str) -> Option<&JsxAttribute> {
+        self.attributes.iter().rev().find_map(|attr| match attr {
+            JsxAttr::Named(attribute) if attribute.name == name => Some(attribute),
+            _ => None,
+        })
+    }
+
+    /// Calls `visit` on the element and every element nested in it, in its
+    /// attributes or its children
+    pub fn for_each_element<'c>(&'c self, visit: &mut impl FnMut(&'c JsxElement)) {
+        visit(self);
+        for attr in &self.attributes {
+            match attr {
+                JsxAttr::Spread { code, .. } => code.for_each_element(visit),
+                JsxAttr::Named(attribute) => {
+                    if let JsxAttrValue::Expr(code) = &attribute.value {
+                        code.for_each_element(visit);
+                    }
+                }
+            }
+        }
+        for child in &self.children {
+            match child {
+                JsxChild::Expr { code, .. } => code.for_each_element(visit),
+                JsxChild::Element(child) => child.for_each_element(visit),
+                JsxChild::Text { .. } | JsxChild::Comment { .. } => {}
+            }
+        }
+    }
+}
+
+/// An entry of a tag's attribute list, in source order
+#[derive(Debug, Clone)]
+pub enum JsxAttr {
+    Named(JsxAttribute),
+    /// `{...expression}`; `span` includes the braces
+    Spread {
+        code: PyCode,
+        span: Span,
+    },
+}
+
+impl JsxAttr {
+    pub fn span(&self) -> Span {
+        match self {
+            JsxAttr::Named(attribute) => attribute.span,
+            JsxAttr::Spread { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JsxAttribute {
+    /// Name as written, which may be hyphenated (`data-id`) or namespaced
+    /// (`xlink:href`)
+    pub name: String,
+    pub value: JsxAttrValue,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum JsxAttrValue {
+    /// Quoted string value, without the quotes
+    Str { value: String, span: Span },
+    /// `{expression}` value
+    Expr(PyCode),
+    /// Boolean shorthand (`<input disabled />`), meaning `True`
+    Implicit,
+}
+
+#[derive(Debug, Clone)]
+pub enum JsxChild {
+    Text {
+        value: String,
+        span: Span,
+    },
+    /// `{expression}` child; `span` includes the braces
+    Expr {
+        code: PyCode,
+        span: Span,
+    },
+    Element(JsxElement),
+    /// `{# ...}` holding nothing but comments, which compiles to nothing;
+    /// `span` includes the braces
+    Comment {
+        span: Span,
+    },
+}
+
+/// Hooks whose call sites get a compile-time identity (see [`HookCall`])
+pub const IDENTIFIED_HOOKS: &[&str] = &["use_state", "use_reducer", "use_effect_with_deps"];
+
+/// A call to one of [`IDENTIFIED_HOOKS`], found while scanning the host code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookCall {
+    pub hook: String,
+    /// Qualified name of the enclosing function or class (`Counter`,
+    /// `Page.render`); empty at module level
+    pub scope: String,
+    /// Position among the hook calls of `scope`, in source order
+    pub index: usize,
+    /// Offset of the closing parenthesis of the call
+    pub close: usize,
+    /// Whether an argument appended before `close` needs a leading comma,
+    /// i.e. the argument list is neither empty nor ends with a comma
+    pub needs_comma: bool,
+}
+
+/// A parsed `.pyx` module, or a range of its module-level statements
+#[derive(Debug, Clone)]
+pub struct PyxModule {
+    pub code: PyCode,
+    pub hook_calls: Vec<HookCall>,
+    /// Hook calls per scope, including those counted before the parsed range
+    pub hook_counts: HashMap<String, usize>,
+    /// Whether the code ends outside of any bracket
+    pub balanced: bool,
+}
+
+/// Maps byte offsets to 1-based line and column numbers
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        for (i, b) in source.bytes().enumerate() {
+            if b == b'\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        LineIndex { line_starts }
+    }
+
+    /// Returns the 1-based `(line, column)` of a byte offset
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        (line + 1, offset - self.line_starts[line] + 1)
+    }
+}
+
+/// Parses a complete `.pyx` module
+pub fn parse_pyx(source: &str) -> Result<PyCode, Diagnostic> {
+    parse_pyx_module(source).map(|module| module.code)
+}
+
+/// Like [`parse_pyx`], also collecting the hook call sites
+pub fn parse_pyx_module(source: &str) -> Result<PyxModule, Diagnostic> {
+    parse_pyx_range(source, Span::new(0, source.len()), HashMap::new())
+}
+
+/// Parses the module-level statements in `span` of `source`, numbering hook
+/// calls after the ones already counted in `hook_counts`
+pub fn parse_pyx_range(
+    source: &str,
+    span: Span,
+    hook_counts: HashMap<String, usize>,
+) -> Result<PyxModule, Diagnostic> {
+    let mut parser = Parser::new(&source[..span.end]);
+    parser.pos = span.start;
+    parser.hook_counts = hook_counts;
+    let code = parser.parse_python(Terminator::Eof)?;
+    Ok(PyxModule {
+        code,
+        hook_calls: parser.hook_calls,
+        hook_counts: parser.hook_counts,
+        balanced: parser.open_brackets == 0,
+    })
+}
+
+/// What ends a run of Python code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Terminator {
+    /// The end of the file (module level)
+    Eof,
+    /// An unbalanced `}` (embedded `{expression}`)
+    CloseBrace,
+}
+
+/// The previous significant Python token, used to tell a JSX `<` from a
+/// less-than operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prev {
+    /// Start of a statement or of an embedded expression
+    Start,
+    /// A token after which an expression may start (`(`, `,`, `=`, `return`...)
+    ExprStart,
+    /// An operand (name, literal, closing bracket, JSX element)
+    Operand,
+    /// Any other operator
+    Operator,
+}
+
+/// Keywords that may be directly followed by an expression
+const EXPR_KEYWORDS: &[&str] = &[
+    "return", "yield", "and", "or", "not", "else", "if", "in", "await", "assert",
+];
+
+/// Operators after which `<` starts a JSX element rather than a comparison
+const EXPR_OPERATORS: &[&str] = &["=", ":", ":="];
+
+/// Elements whose content is raw text (CSS, JavaScript) rather than JSX
+pub(crate) const RAW_TEXT_ELEMENTS: &[&str] = &["style", "script"];
+
+const OPERATORS: &[&str] = &[
+    "**=", "//=", ">>=", "<<=", "...", "**", "//", ">>", "<<", "<=", ">=", "==", "!=", "->", ":=",
+    "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "@=",
+];
+
+struct Parser<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    lines: LineIndex,
+    hook_calls: Vec<HookCall>,
+    /// Enclosing `def`/`class` statements with their indentation
+    scopes: Vec<(String, usize)>,
+    /// Hook calls seen so far per scope
+    hook_counts: HashMap<String, usize>,
+    /// Indentation of the current module-level statement
+    statement_indent: usize,
+    /// Set after `def`/`class`, until the name that follows is read
+    pending_scope: Option<usize>,
+    /// Brackets left open at the end of the module
+    open_brackets: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Parser {
+            src,
+            bytes: src.as_bytes(),
+            pos: 0,
+            lines: LineIndex::new(src),
+            hook_calls: Vec::new(),
+            scopes: Vec::new(),
+            hook_counts: HashMap::new(),
+            statement_indent: 0,
+            pending_scope: None,
+            open_brackets: 0,
+        }
+    }
+
+    fn error(&self, code: &'static str, message: impl Into<String>, span: Span) -> Diagnostic {
+        Diagnostic::error(code, message, span, &self.lines)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        self.src[self.pos..].starts_with(s)
+    }
+
+    /// Scans Python code until `terminator`, collecting embedded JSX elements
+    fn parse_python(&mut self, terminator: Terminator) -> Result<PyCode, Diagnostic> {
+        let start = self.pos;
+        let mut parts = Vec::new();
+        let mut code_start = self.pos;
+        let mut depth = 0usize;
+        let mut prev = Prev::Start;
+        // Start of the last token, to tell empty and trailing-comma argument lists
+        let mut last_token = self.pos;
+        // Hook name read just before a `(`, and the hook calls still open
+        let mut pending_hook: Option<String> = None;
+        let mut open_hooks: Vec<(usize, usize, HookCall)> = Vec::new();
+
+        loop {
+            let Some(b) = self.peek() else {
+                if terminator == Terminator::CloseBrace {
+                    return Err(self.error(
+                        "PYX001",
+                        "Unterminated `{` expression",
+                        Span::new(start.saturating_sub(1), start),
+                    ));
+                }
+                self.open_brackets = depth;
+                break;
+            };
+
+            let token_start = self.pos;
+            let significant = !matches!(b, b' ' | b'\t' | b'\r' | b'\x0c' | b'\n' | b'\\' | b'#');
+            if significant && prev == Prev::Start && depth == 0 && terminator == Terminator::Eof {
+                self.start_statement();
+            }
+
+            match b {
+                b' ' | b'\t' | b'\r' | b'\x0c' => self.pos += 1,
+                b'\\' => self.pos += 2.min(self.bytes.len() - self.pos),
+                b'\n' => {
+                    self.pos += 1;
+                    if depth == 0 && terminator == Terminator::Eof {
+                        prev = Prev::Start;
+                    }
+                }
+                b'#' => self.skip_comment(),
+                b'"' | b'\'' => {
+                    self.skip_string(0)?;
+                    prev = Prev::Operand;
+                }
+                b'0'..=b'9' => {
+                    self.skip_number();
+                    prev = Prev::Operand;
+                }
+                b'<' if jsx_may_start(prev) && self.at_jsx_tag_start() => {
+                    if code_start < self.pos {
+                        parts.push(PyPart::Code {
+                            text: self.src[code_start..self.pos].to_string(),
+                            span: Span::new(code_start, self.pos),
+                        });
+                    }
+                    let element = self.parse_element()?;
+                    parts.push(PyPart::Element(element));
+                    code_start = self.pos;
+                    prev = Prev::Operand;
+                }
+                b'(' | b'[' | b'{' => {
+                    if let Some(hook) = pending_hook.take().filter(|_| b == b'(') {
+                        open_hooks.push((depth, self.pos, self.hook_call(hook)));
+                    }
+                    depth += 1;
+                    self.pos += 1;
+                    prev = Prev::ExprStart;
+                }
+                b')' | b']' | b'}' => {
+                    if depth == 0 && b == b'}' && terminator == Terminator::CloseBrace {
+                        break;
+                    }
+                    depth = depth.saturating_sub(1);
+                    if b == b')' && open_hooks.last().is_some_and(|(d, ..)| *d == depth) {
+                        let (_, open, mut call) = open_hooks.pop().unwrap();
+                        call.close = self.pos;
+                        call.needs_comma = last_token != open && self.bytes[last_token] != b',';
+                        self.hook_calls.push(call);
+                    }
+                    self.pos += 1;
+                    prev = Prev::Operand;
+                }
+                b',' => {
+                    self.pos += 1;
+                    prev = Prev::ExprStart;
+                }
+                b';' => {
+                    self.pos += 1;
+                    prev = Prev::Start;
+                }
+                b if is_ident_start(b) => {
+                    let word_start = self.pos;
+                    self.skip_identifier();
+                    let word = &self.src[word_start..self.pos];
+                    if matches!(self.peek(), Some(b'"' | b'\'')) && is_string_prefix(word) {
+                        self.skip_string(word.len())?;
+                        prev = Prev::Operand;
+                    } else if EXPR_KEYWORDS.contains(&word) {
+                        prev = Prev::ExprStart;
+                    } else {
+                        prev = Prev::Operand;
+                    }
+                    if let Some(indent) = self.pending_scope.take() {
+                        self.scopes.push((word.to_string(), indent));
+                    } else if matches!(word, "def" | "class") && terminator == Terminator::Eof {
+                        self.pending_scope = Some(self.statement_indent);
+                    } else if IDENTIFIED_HOOKS.contains(&word) && self.next_is_call() {
+                        pending_hook = Some(word.to_string());
+                    }
+                }
+                _ => {
+                    let op = OPERATORS
+                        .iter()
+                        .find(|op| self.starts_with(op))
+                        .map_or(1, |op| op.len());
+                    let op_text = &self.src[self.pos..self.pos + op];
+                    prev = if EXPR_OPERATORS.contains(&op_text) {
+                        Prev::ExprStart
+                    } else {
+                        Prev::Operator
+                    };
+                    self.pos += op;
+                }
+            }
+            if significant {
+                last_token = token_start;
+            }
+        }
+
+        if code_start < self.pos {
+            parts.push(PyPart::Code {
+                text: self.src[code_start..self.pos].to_string(),
+                span: Span::new(code_start, self.pos),
+            });
+        }
+
+        Ok(PyCode {
+            parts,
+            span: Span::new(start, self.pos),
+        })
+    }
+
+    /// Closes the `def`/`class` scopes that a statement at the current
+    /// position dedents out of
+    fn start_statement(&mut self) {
+        let (_, column) = self.lines.line_col(self.pos);
+        self.statement_indent = column - 1;
+        while let Some((_, indent)) = self.scopes.last() {
+            if *indent < self.statement_indent {
+                break;
+            }
+            self.scopes.pop();
+        }
+    }
+
+    /// Whether the next token is `(`
+    fn next_is_call(&self) -> bool {
+        self.src[self.pos..]
+            .trim_start_matches([' ', '\t'])
+            .starts_with('(')
+    }
+
+    /// Starts a call to `hook` in the current scope, taking the scope's next
+    /// hook index
+    fn hook_call(&mut self, hook: String) -> HookCall {
+        let scope = self
+            .scopes
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(".");
+        let count = self.hook_counts.entry(scope.clone()).or_insert(0);
+        let index = *count;
+        *count += 1;
+        HookCall {
+            hook,
+            scope,
+            index,
+            close: 0,
+            needs_comma: false,
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        while let Some(b) = self.peek() {
+            if b == b'\n' {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn skip_identifier(&mut self) {
+        while let Some(b) = self.peek() {
+            if !is_ident_continue(b) {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn skip_number(&mut self) {
+        while let Some(b) = self.peek() {
+            if b.is_ascii_alphanumeric() || b == b'_' || b == b'.' {
+                self.pos += 1;
+                if matches!(b, b'e' | b'E') && matches!(self.peek(), Some(b'+' | b'-')) {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Skips a string literal; `self.pos` points just after a prefix of
+    /// `prefix_len` bytes (`f`, `rb`...) at the opening quote
+    fn skip_string(&mut self, prefix_len: usize) -> Result<(), Diagnostic> {
+        let literal_start = self.pos - prefix_len;
+        let prefix = self.src[literal_start..self.pos].to_ascii_lowercase();
+        let formatted = prefix.contains('f') || prefix.contains('t');
+        let quote = self.bytes[self.pos];
+        let triple = self.peek_at(1) == Some(quote) && self.peek_at(2) == Some(quote);
+        self.pos += if triple { 3 } else { 1 };
+
+        loop {
+            let Some(b) = self.peek() else {
+                return Err(self.error(
+                    "PYX002",
+                    "Unterminated string literal",
+                    Span::new(literal_start, self.pos),
+                ));
+            };
+            match b {
+                b'\\' => self.pos += 2.min(self.bytes.len() - self.pos),
+                b'\n' if !triple => {
+                    return Err(self.error(
+                        "PYX002",
+                        "Unterminated string literal",
+                        Span::new(literal_start, self.pos),
+                    ));
+                }
+                b'{' if formatted => {
+                    if self.peek_at(1) == Some(b'{') {
+                        self.pos += 2;
+                    } else {
+                        self.pos += 1;
+                        self.skip_replacement_field(literal_start)?;
+                    }
+                }
+                b if b == quote => {
+                    if !triple {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                    if self.peek_at(1) == Some(quote) && self.peek_at(2) == Some(quote) {
+                        self.pos += 3;
+                        return Ok(());
+                    }
+                    self.pos += 1;
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Skips an f-string replacement field up to its closing `}`, honouring
+    /// nested brackets and string literals inside the expression
+    fn skip_replacement_field(&mut self, literal_start: usize) -> Result<(), Diagnostic> {
+        let mut depth = 0usize;
+        loop {
+            let Some(b) = self.peek() else {
+                return Err(self.error(
+                    "PYX002",
+                    "Unterminated f-string replacement field",
+                    Span::new(literal_start, self.pos),
+                ));
+            };
+            match b {
+                b'"' | b'\'' => self.skip_string(0)?,
+                b if is_ident_start(b) => {
+                    let word_start = self.pos;
+                    self.skip_identifier();
+                    let word = &self.src[word_start..self.pos];
+                    if matches!(self.peek(), Some(b'"' | b'\'')) && is_string_prefix(word) {
+                        self.skip_string(word.len())?;
+                    }
+                }
+                b'(' | b'[' | b'{' => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                b'}' if depth == 0 => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                b')' | b']' | b'}' => {
+                    depth = depth.saturating_sub(1);
+                    self.pos += 1;
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn at_jsx_tag_start(&self) -> bool {
+        self.peek_at(1)
+            .is_some_and(|b| is_ident_start(b) || b == b'>')
+    }
+
+    /// Skips whitespace inside a JSX tag
+    fn skip_tag_whitespace(&mut self) {
+        while let Some(b) = self.peek() {
+            if !b.is_ascii_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn parse_tag_name(&mut self) -> Result<(String, Span), Diagnostic> {
+        let start = self.pos;
+        if !self.peek().is_some_and(is_ident_start) {
+            return Err(self.error("PYX003", "Expected a tag name", Span::new(start, start + 1)));
+        }
+        while let Some(b) = self.peek() {
+            if is_ident_continue(b) || matches!(b, b'-' | b'.' | b':') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        Ok((
+            self.src[start..self.pos].to_string(),
+            Span::new(start, self.pos),
+        ))
+    }
+
+    /// Parses a tag name, or returns an empty name for the fragment tags `<>`
+    /// and `</>`
+    fn parse_tag_name_or_fragment(&mut self) -> Result<(String, Span), Diagnostic> {
+        if self.peek() == Some(b'>') {
+            return Ok((String::new(), Span::new(self.pos, self.pos)));
+        }
+        self.parse_tag_name()
+    }
+
+    fn parse_element(&mut self) -> Result<JsxElement, Diagnostic> {
+        let start = self.pos;
+        self.pos += 1; // '<'
+        let (name, name_span) = self.parse_tag_name_or_fragment()?;
+        if is_component_name(&name)
+            && !name.split('.').all(|part| {
+                part.bytes().next().is_some_and(is_ident_start)
+                    && part.bytes().all(is_ident_continue)
+            })
+        {
+            return Err(self
+                .error(
+                    "PYX015",
+                    format!("Invalid component name <{}>", name),
+                    name_span,
+                )
+                .with_help(
+                    "component tags must be Python names or attribute paths such as `ui.Button`",
+                ));
+        }
+        let mut attributes = Vec::new();
+
+        loop {
+            self.skip_tag_whitespace();
+            match self.peek() {
+                None => {
+                    return Err(self.error(
+                        "PYX004",
+                        format!("Unterminated opening tag <{}>", name),
+                        Span::new(start, self.pos),
+                    ));
+                }
+                Some(b'/') if self.peek_at(1) == Some(b'>') => {
+                    let closing_span = Span::new(self.pos, self.pos + 2);
+                    self.pos += 2;
+                    return Ok(JsxElement {
+                        name,
+                        name_span,
+                        attributes,
+                        children: Vec::new(),
+                        self_closing: true,
+                        closing_span,
+                        span: Span::new(start, self.pos),
+                    });
+                }
+                Some(b'>') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(_) => attributes.push(self.parse_attribute()?),
+            }
+        }
+
+        let mut children = Vec::new();
+        if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            let text_start = self.pos;
+            let closing = format!("</{}", name);
+            match self.src[self.pos..].find(&closing) {
+                Some(len) => self.pos += len,
+                None => {
+                    return Err(self
+                        .error(
+                            "PYX005",
+                            format!("Unclosed element <{}>", name),
+                            Span::new(start, name_span.end),
+                        )
+                        .with_help(format!(
+                            "add `</{}>` or make the element self-closing with `/>`",
+                            name
+                        )));
+                }
+            }
+            children.push(JsxChild::Text {
+                value: self.src[text_start..self.pos].to_string(),
+                span: Span::new(text_start, self.pos),
+            });
+        }
+
+        loop {
+            match self.peek() {
+                None => {
+                    return Err(self
+                        .error(
+                            "PYX005",
+                            format!("Unclosed element <{}>", name),
+                            Span::new(start, name_span.end),
+                        )
+                        .with_help(if name.is_empty() {
+                            "add `</>` to close the fragment".to_string()
+                        } else {
+                            format!(
+                                "add `</{}>` or make the element self-closing with `/>`",
+                                name
+                            )
+                        }));
+                }
+                Some(b'<') if self.peek_at(1) == Some(b'/') => {
+                    let closing_start = self.pos;
+                    self.pos += 2;
+                    self.skip_tag_whitespace();
+                    let (closing_name, closing_name_span) = self.parse_tag_name_or_fragment()?;
+                    self.skip_tag_whitespace();
+                    if self.peek() != Some(b'>') {
+                        return Err(self.error(
+                            "PYX006",
+                            format!("Expected `>` to close </{}>", closing_name),
+                            Span::new(self.pos, self.pos + 1),
+                        ));
+                    }
+                    self.pos += 1;
+                    if closing_name != name {
+                        return Err(self
+                            .error(
+                                "PYX007",
+                                format!(
+                                    "Mismatched closing tag: expected </{}>, found </{}>",
+                                    name, closing_name
+                                ),
+                                closing_name_span,
+                            )
+                            .with_help(format!(
+                                "<{}> must be closed before </{}>",
+                                name, closing_name
+                            )));
+                    }
+                    return Ok(JsxElement {
+                        name,
+                        name_span,
+                        attributes,
+                        children,
+                        self_closing: false,
+                        closing_span: Span::new(closing_start, self.pos),
+                        span: Span::new(start, self.pos),
+                    });
+                }
+                Some(b'<') => {
+                    if !self.at_jsx_tag_start() {
+                        return Err(self
+                            .error(
+                                "PYX008",
+                                "Unexpected `<` in JSX text",
+                                Span::new(self.pos, self.pos + 1),
+                            )
+                            .with_help("write `&lt;` or `{\"<\"}` for a literal `<`"));
+                    }
+                    children.push(JsxChild::Element(self.parse_element()?));
+                }
+                Some(b'{') => {
+                    let container_start = self.pos;
+                    self.pos += 1;
+                    let code = self.parse_python(Terminator::CloseBrace)?;
+                    self.pos += 1; // '}'
+                    let span = Span::new(container_start, self.pos);
+                    if !is_blank_code(&code) {
+                        children.push(JsxChild::Expr { code, span });
+                    } else if self.src[span.start..span.end].contains('#') {
+                        children.push(JsxChild::Comment { span });
+                    }
+                }
+                Some(_) => {
+                    let text_start = self.pos;
+                    while let Some(b) = self.peek() {
+                        if b == b'<' || b == b'{' {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                    children.push(JsxChild::Text {
+                        value: self.src[text_start..self.pos].to_string(),
+                        span: Span::new(text_start, self.pos),
+                    });
+                }
+            }
+        }
+    }
+
+    fn parse_attribute(&mut self) -> Result<JsxAttr, Diagnostic> {
+        let start = self.pos;
+        if self.peek() == Some(b'{') {
+            return self.parse_spread();
+        }
+        if !self.peek().is_some_and(is_ident_start) {
+            return Err(self.error(
+                "PYX009",
+                "Unexpected character in tag",
+                Span::new(start, start + 1),
+            ));
+        }
+        while let Some(b) = self.peek() {
+            if is_ident_continue(b) || matches!(b, b'-' | b':') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        let name = self.src[start..self.pos].to_string();
+        let name_end = self.pos;
+
+        self.skip_tag_whitespace();
+        if self.peek() != Some(b'=') {
+            // Boolean shorthand; leave the following whitespace to the caller
+            self.pos = name_end;
+            return Ok(JsxAttr::Named(JsxAttribute {
+                name,
+                value: JsxAttrValue::Implicit,
+                span: Span::new(start, name_end),
+            }));
+        }
+        self.pos += 1;
+        self.skip_tag_whitespace();
+
+        let value = match self.peek() {
+            Some(quote @ (b'"' | b'\'')) => {
+                let value_start = self.pos;
+                self.pos += 1;
+                while let Some(b) = self.peek() {
+                    if b == quote {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                if self.peek().is_none() {
+                    return Err(self.error(
+                        "PYX011",
+                        format!("Unterminated value for attribute `{}`", name),
+                        Span::new(value_start, self.pos),
+                    ));
+                }
+                self.pos += 1;
+                JsxAttrValue::Str {
+                    value: self.src[value_start + 1..self.pos - 1].to_string(),
+                    span: Span::new(value_start, self.pos),
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let code = self.parse_python(Terminator::CloseBrace)?;
+                self.pos += 1; // '}'
+                if is_blank_code(&code) {
+                    return Err(self.error(
+                        "PYX012",
+                        format!("Empty expression for attribute `{}`", name),
+                        Span::new(start, self.pos),
+                    ));
+                }
+                JsxAttrValue::Expr(code)
+            }
+            _ => {
+                return Err(self.error(
+                    "PYX013",
+                    format!("Expected a quoted string or `{{...}}` for `{}`", name),
+                    Span::new(self.pos, self.pos + 1),
+                ));
+            }
+        };
+
+        Ok(JsxAttr::Named(JsxAttribute {
+            name,
+            value,
+            span: Span::new(start, self.pos),
+        }))
+    }
+
+    /// Parses a `{...expression}` spread attribute
+    fn parse_spread(&mut self) -> Result<JsxAttr, Diagnostic> {
+        let start = self.pos;
+        self.pos += 1; // '{'
+        self.skip_tag_whitespace();
+        if !self.starts_with("...") {
+            return Err(self
+                .error(
+                    "PYX014",
+                    "Expected `...` in spread attribute",
+                    Span::new(start, self.pos + 1),
+                )
+                .with_help("pass a dict of props as `{...props}`"));
+        }
+        self.pos += 3;
+        let code = self.parse_python(Terminator::CloseBrace)?;
+        self.pos += 1; // '}'
+        if is_blank_code(&code) {
+            return Err(self.error(
+                "PYX012",
+                "Empty expression in spread attribute",
+                Span::new(start, self.pos),
+            ));
+        }
+        Ok(JsxAttr::Spread {
+            code,
+            span: Span::new(start, self.pos),
+        })
+    }
+}
+
+fn jsx_may_start(prev: Prev) -> bool {
+    matches!(prev, Prev::Start | Prev::ExprStart)
+}
+
+fn is_component_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) || name.contains('.')
+}
+
+fn is_ident_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || b >= 0x80
+}
+
+fn is_ident_continue(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80
+}
+
+fn is_string_prefix(word: &str) -> bool {
+    matches!(
+        word.to_ascii_lowercase().as_str(),
+        "r" | "u" | "b" | "f" | "t" | "br" | "rb" | "fr" | "rf" | "tr" | "rt"
+    )
+}
+
+/// Whether an embedded expression holds nothing but whitespace and comments
+fn is_blank_code(code: &PyCode) -> bool {
+    code.parts.iter().all(|part| match part {
+        PyPart::Code { text, .. } => text
+            .lines()
+            .all(|line| line.trim().is_empty() || line.trim_start().starts_with('#')),
+        PyPart::Element(_) => false,
+    })
+}
@@ -1,143 +1,398 @@
+use crate::diagnostics::Diagnostic;
+use crate::jsx_parser::{
+    parse_pyx_range, HookCall, JsxAttr, JsxAttrValue, JsxChild, JsxElement, LineIndex, PyCode,
+    PyPart, PyxModule, Span, RAW_TEXT_ELEMENTS,
+};
+use crate::source_map::Mapping;
+use crate::virtual_dom::FRAGMENT_TAG;
 use pyo3::prelude::*;
-use regex::Regex;
+use std::collections::HashMap;
 
-/// Incremental transformation of JSX code to a Python-compatible format
+/// Complete transformation of JSX code to Python-compatible code; `module`
+/// names the module in the injected hook ids
 #[pyfunction]
-pub fn incremental_jsx_transform(js_code: &str) -> PyResult<String> {
-    parse_jsx(js_code)
+#[pyo3(signature = (js_code, module=None))]
+pub fn parse_jsx(js_code: &str, module: Option<&str>) -> PyResult<String> {
+    transform_pyx_module(js_code, module.unwrap_or(DEFAULT_MODULE))
+        .map(|output| output.code)
+        .map_err(|e| e.to_py_err(Some(js_code)))
 }
 
-/// Complete transformation of JSX code to Python-compatible code
-#[pyfunction]
-pub fn parse_jsx(js_code: &str) -> PyResult<String> {
-    // Simple state machine parser for JSX in Python
-    // This is a significant improvement over simple string replacement
-    // but still a simplified implementation compared to a full AST parser.
-
-    let mut output = String::new();
-    let mut chars = js_code.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        if c == '<' {
-            // Potential JSX start
-            // Check if it's a tag and not a less-than operator
-            // Heuristic: followed by a letter or /
-            let next = chars.peek();
-            if let Some(&n) = next {
-                if n.is_alphabetic() || n == '/' || n == '>' {
-                    // It is likely a tag
-                    let tag_content = read_until_matching_bracket(&mut chars);
-                    let transformed = transform_tag(&tag_content);
-                    output.push_str(&transformed);
-                    continue;
+/// Module name used in hook ids when the source has no file
+const DEFAULT_MODULE: &str = "__main__";
+
+/// Parses a `.pyx` source and emits Python where every JSX element has been
+/// replaced by a `create_element(tag, props, children)` call.
+///
+/// Line breaks between elements, attributes and children are carried over, so
+/// each line of the output corresponds to the same line of the source.
+pub fn transform_pyx(source: &str) -> Result<String, Diagnostic> {
+    transform_pyx_mapped(source).map(|output| output.code)
+}
+
+/// Generated Python together with the positions it maps back to
+#[derive(Debug, Clone)]
+pub struct TransformOutput {
+    pub code: String,
+    pub mappings: Vec<Mapping>,
+}
+
+impl TransformOutput {
+    /// Replaces the `(start, end, text)` ranges of the code, which are in
+    /// order, and moves the mappings after them
+    pub(crate) fn replace_ranges(&mut self, edits: &[(usize, usize, String)]) {
+        for (start, end, text) in edits.iter().rev() {
+            self.code.replace_range(start..end, text);
+            for mapping in &mut self.mappings {
+                if mapping.generated >= *end {
+                    mapping.generated = mapping.generated - end + start + text.len();
+                } else if mapping.generated > *start {
+                    mapping.generated = *start;
                 }
             }
-            output.push(c);
-        } else {
-            output.push(c);
         }
     }
+}
 
-    Ok(output)
+/// Like [`transform_pyx`], also recording where each piece of the output
+/// comes from in the source
+pub fn transform_pyx_mapped(source: &str) -> Result<TransformOutput, Diagnostic> {
+    transform_pyx_module(source, DEFAULT_MODULE)
 }
 
-fn read_until_matching_bracket(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
-    let mut content = String::new();
-    let mut depth = 1; // We already consumed the first '<'
-
-    // Note: This is a simplified reader that doesn't handle strings containing '>' correctly
-    // A full implementation would need to track string state.
-    while let Some(c) = chars.next() {
-        if c == '>' {
-            depth -= 1;
-            if depth == 0 {
-                break;
-            }
-            content.push(c);
-        } else if c == '<' {
-            depth += 1;
-            content.push(c);
-        } else {
-            content.push(c);
-        }
-    }
-    content
+/// Like [`transform_pyx_mapped`] for the source of the Python module `module`
+/// (e.g. `components.Counter`).
+///
+/// Every `use_state`, `use_reducer` and `use_effect_with_deps` call gets a
+/// `hook_id="module:Component:index"` keyword argument, which identifies the
+/// call site so that hooks can be called without explicit ids.
+pub fn transform_pyx_module(source: &str, module: &str) -> Result<TransformOutput, Diagnostic> {
+    transform_statements(source, Span::new(0, source.len()), module, HashMap::new())
+        .map(|(output, _)| output)
+}
+
+/// Transforms the module-level statements in `span` of `source`, continuing
+/// the hook numbering from `hook_counts`. The generated offsets of the
+/// mappings are relative to the start of the returned code.
+pub(crate) fn transform_statements(
+    source: &str,
+    span: Span,
+    module: &str,
+    hook_counts: HashMap<String, usize>,
+) -> Result<(TransformOutput, PyxModule), Diagnostic> {
+    let parsed = parse_pyx_range(source, span, hook_counts)?;
+    let mut emitter = Emitter::new(source);
+    emitter.line = emitter.lines.line_col(span.start).0;
+    emitter.insertions = hook_id_insertions(&parsed.hook_calls, module);
+    emitter.emit_code(&parsed.code);
+    let output = TransformOutput {
+        code: emitter.out,
+        mappings: emitter.mappings,
+    };
+    Ok((output, parsed))
+}
+
+struct Emitter {
+    out: String,
+    mappings: Vec<Mapping>,
+    lines: LineIndex,
+    /// 1-based source line the output has reached
+    line: usize,
+    /// Text inserted before source offsets, sorted by offset
+    insertions: Vec<(usize, String)>,
 }
 
-fn transform_tag(tag_content: &str) -> String {
-    // tag_content is what's inside <...>, e.g. "div className='foo'" or "/div"
+impl Emitter {
+    fn new(source: &str) -> Self {
+        Emitter {
+            out: String::with_capacity(source.len()),
+            mappings: Vec::new(),
+            lines: LineIndex::new(source),
+            line: 1,
+            insertions: Vec::new(),
+        }
+    }
 
-    let trimmed = tag_content.trim();
+    /// Records that the next output byte comes from `original`
+    fn map(&mut self, original: usize) {
+        self.mappings.push(Mapping {
+            generated: self.out.len(),
+            original,
+        });
+    }
 
-    if trimmed.starts_with('/') {
-        // Closing tag: </div> -> "])"
-        // We need to match the structure of create_element
-        // create_element(tag, props, [children...])
-        // So a closing tag ends the children array and the function call.
-        return "])".to_string();
+    /// Copies Python source verbatim, mapping the start of every line and
+    /// adding the pending insertions that fall inside it
+    fn push_code(&mut self, text: &str, start: usize) {
+        let mut offset = start;
+        for line in text.split_inclusive('\n') {
+            self.map(offset);
+            let end = offset + line.len();
+            let mut copied = offset;
+            while let Some((at, _)) = self.insertions.first() {
+                if *at >= end {
+                    break;
+                }
+                let (at, insertion) = self.insertions.remove(0);
+                self.out.push_str(&text[copied - start..at - start]);
+                self.out.push_str(&insertion);
+                self.map(at);
+                copied = at;
+            }
+            self.out.push_str(&text[copied - start..end - start]);
+            offset = end;
+        }
+        self.line += text.matches('\n').count();
     }
 
-    // Opening tag
-    // Parse tag name and attributes
-    // <div className="foo"> -> create_element("div", {"className": "foo"}, [
+    /// Breaks the line until the output reaches the source line of `offset`,
+    /// indenting to its column. Returns whether a line break was emitted.
+    fn sync_to(&mut self, offset: usize) -> bool {
+        let (line, column) = self.lines.line_col(offset);
+        if line <= self.line {
+            return false;
+        }
+        while self.line < line {
+            self.out.push('\n');
+            self.line += 1;
+        }
+        self.out.push_str(&" ".repeat(column - 1));
+        true
+    }
 
-    let mut parts = trimmed.split_whitespace();
-    let tag_name = parts.next().unwrap_or("div");
+    fn emit_code(&mut self, code: &PyCode) {
+        for part in &code.parts {
+            match part {
+                PyPart::Code { text, span } => self.push_code(text, span.start),
+                PyPart::Element(element) => self.emit_element(element),
+            }
+        }
+    }
 
-    // Handle self-closing tags ending with /
-    let is_self_closing = trimmed.ends_with('/');
-    let clean_tag_name = if is_self_closing && tag_name.ends_with('/') {
-        &tag_name[..tag_name.len() - 1]
-    } else {
-        tag_name
-    };
+    fn emit_element(&mut self, element: &JsxElement) {
+        self.map(element.span.start);
+        self.out.push_str("create_element(");
+        if element.is_fragment() {
+            self.out.push_str(&py_string_literal(FRAGMENT_TAG));
+        } else if element.is_component() {
+            // A reference to the component callable, e.g. `Button` or `ui.Button`
+            self.out.push_str(&element.name);
+        } else {
+            self.out.push_str(&py_string_literal(&element.name));
+        }
+        self.out.push_str(", {");
 
-    // Parse attributes
-    // This is a naive attribute parser
-    let mut props_str = String::from("{");
-    let mut first_prop = true;
-
-    // Re-join the rest to parse attributes properly (handling spaces in values)
-    let rest = &trimmed[tag_name.len()..].trim();
-    if !rest.is_empty() {
-        let attr_regex = Regex::new(r#"(\w+)=(?:\{([^}]+)\}|"([^"]+)"|'([^']+)')"#).unwrap();
-        for cap in attr_regex.captures_iter(rest) {
-            if !first_prop {
-                props_str.push_str(", ");
+        for (i, attr) in element.attributes.iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            if !self.sync_to(attr.span().start) && i > 0 {
+                self.out.push(' ');
             }
-            let key = &cap[1];
-            let value = if let Some(v) = cap.get(2) {
-                // Expression: {value}
-                v.as_str().to_string()
-            } else if let Some(v) = cap.get(3) {
-                // Double quoted: "value"
-                format!("\"{}\"", v.as_str())
+            self.map(attr.span().start);
+            let attribute = match attr {
+                // Later keys override earlier ones, as with JSX spreads
+                JsxAttr::Spread { code, .. } => {
+                    self.out.push_str("**");
+                    self.emit_expression(code);
+                    continue;
+                }
+                JsxAttr::Named(attribute) => attribute,
+            };
+            // Handle className -> class mapping
+            let key = if attribute.name == "className" {
+                "class"
             } else {
-                // Single quoted: 'value'
-                format!("\"{}\"", cap.get(4).unwrap().as_str())
+                &attribute.name
             };
+            self.out.push_str(&py_string_literal(key));
+            self.out.push_str(": ");
+            match &attribute.value {
+                JsxAttrValue::Str { value, .. } => self.out.push_str(&py_string_literal(value)),
+                JsxAttrValue::Expr(code) => self.emit_expression(code),
+                JsxAttrValue::Implicit => self.out.push_str("True"),
+            }
+        }
 
-            // Handle className -> class mapping if needed, or keep as is
-            let final_key = if key == "className" { "class" } else { key };
+        self.out.push_str("}, [");
 
-            // If value is a string literal, quote it, otherwise keep as expression
-            // The regex logic above already handles quoting for string literals
-            // But we need to be careful about the expression case.
+        let raw_text = RAW_TEXT_ELEMENTS.contains(&element.name.as_str());
+        let mut first = true;
+        for child in &element.children {
+            let (start, text) = match child {
+                JsxChild::Text { value, span } => {
+                    let text = if raw_text {
+                        Some(value.clone())
+                    } else {
+                        clean_jsx_text(value)
+                    };
+                    let Some(text) = text else { continue };
+                    let leading = value.len() - value.trim_start().len();
+                    (span.start + leading, Some(text))
+                }
+                JsxChild::Expr { span, .. } => (span.start, None),
+                JsxChild::Element(child) => (child.span.start, None),
+                JsxChild::Comment { .. } => continue,
+            };
+            if !first {
+                self.out.push(',');
+            }
+            if !self.sync_to(start) && !first {
+                self.out.push(' ');
+            }
+            first = false;
 
-            if cap.get(2).is_some() {
-                props_str.push_str(&format!("\"{}\": {}", final_key, value));
-            } else {
-                props_str.push_str(&format!("\"{}\": {}", final_key, value));
+            match (child, text) {
+                (_, Some(text)) => {
+                    self.map(start);
+                    self.out.push_str(&py_string_literal(&text));
+                }
+                (JsxChild::Expr { code, span }, None) => {
+                    self.map(span.start);
+                    self.emit_expression(code);
+                }
+                (JsxChild::Element(child), None) => self.emit_element(child),
+                (JsxChild::Text { .. } | JsxChild::Comment { .. }, None) => {}
             }
+        }
 
-            first_prop = false;
+        if !element.self_closing {
+            self.sync_to(element.closing_span.start);
         }
+        self.map(element.closing_span.start);
+        self.out.push_str("])");
+    }
+
+    /// Emits an embedded `{expression}`, parenthesised so that multi-line
+    /// expressions stay valid inside the generated call
+    fn emit_expression(&mut self, code: &PyCode) {
+        self.out.push('(');
+        self.emit_code(code);
+        self.out.push(')');
     }
-    props_str.push('}');
+}
+
+/// The `hook_id` arguments to add before the closing parenthesis of each
+/// hook call
+fn hook_id_insertions(calls: &[HookCall], module: &str) -> Vec<(usize, String)> {
+    let mut insertions: Vec<(usize, String)> = calls
+        .iter()
+        .map(|call| {
+            let id = format!("{}:{}:{}", module, call.scope, call.index);
+            let separator = if call.needs_comma { ", " } else { "" };
+            (
+                call.close,
+                format!("{}hook_id={}", separator, py_string_literal(&id)),
+            )
+        })
+        .collect();
+    insertions.sort_by_key(|(at, _)| *at);
+    insertions
+}
+
+/// Applies the JSX whitespace rules to a text child and decodes its
+/// character references. Returns `None` when nothing but whitespace is left.
+fn clean_jsx_text(raw: &str) -> Option<String> {
+    collapse_jsx_text(raw).map(|text| decode_entities(&text.replace('\t', " ")))
+}
 
-    if is_self_closing {
-        format!("create_element(\"{}\", {}, [])", clean_tag_name, props_str)
+/// The JSX whitespace rules: lines are trimmed where they meet a line break,
+/// blank lines are dropped and the remaining lines are joined with a single
+/// space. Returns `None` when nothing but whitespace is left.
+pub(crate) fn collapse_jsx_text(raw: &str) -> Option<String> {
+    let lines: Vec<&str> = raw.split('\n').collect();
+    let last = lines.len() - 1;
+    let mut text = String::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let mut line = line.trim_end_matches('\r');
+        if i > 0 {
+            line = line.trim_start();
+        }
+        if i < last {
+            line = line.trim_end();
+        }
+        if line.is_empty() {
+            continue;
+        }
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(line);
+    }
+
+    if text.is_empty() {
+        None
     } else {
-        format!("create_element(\"{}\", {}, [", clean_tag_name, props_str)
+        Some(text)
+    }
+}
+
+/// Decodes the HTML character references allowed in JSX text
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+        return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+    }
+    if let Some(dec) = name.strip_prefix('#') {
+        return dec.parse().ok().and_then(char::from_u32);
+    }
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        "copy" => Some('©'),
+        "lbrace" => Some('{'),
+        "rbrace" => Some('}'),
+        _ => None,
+    }
+}
+
+/// Quotes a string as a Python double-quoted literal
+pub(crate) fn py_string_literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c => literal.push(c),
+        }
     }
+    literal.push('"');
+    literal
 }
@@ -1,22 +1,40 @@
+pub mod a11y;
+pub mod assets;
+mod build_cache;
+pub mod bundler;
 mod cli;
-mod compiler;
+pub mod compiler;
+pub mod config;
 mod css_minifier;
+mod defines;
+pub mod diagnostics;
 mod event_handler;
+pub mod formatter;
+mod hmr;
 mod hooks;
 mod html_minifier;
+mod import_rewriter;
+pub mod incremental;
 mod js_minifier;
-mod jsx_transformer;
+pub mod jsx_parser;
+pub mod jsx_transformer;
 mod lazy_component;
+pub mod linter;
 mod logger;
+pub mod module_graph;
+mod node_target;
 mod plugin_system;
 mod precompiler;
+mod python_validator;
+pub mod source_map;
 mod suspense;
+pub mod traceback_mapper;
 mod virtual_dom;
 
 use crate::compiler::{compile_all_pyx, compile_pyx_file_to_python, update_application};
 use crate::hooks::{Dispatch, SetState};
 use crate::virtual_dom::Patch;
-use log::{error, info};
+use log::{error, info, warn};
 use once_cell::sync::Lazy;
 use pyo3::{prelude::*, wrap_pyfunction};
 use std::ffi::CString;
@@ -35,12 +53,16 @@ static LOGGER_INIT: Once = Once::new();
 /// Main ReactPyx module in Rust for Python
 #[pymodule]
 fn _core(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    LOGGER_INIT.call_once(|| env_logger::init());
+    LOGGER_INIT.call_once(env_logger::init);
 
     m.add_class::<Patch>()?;
     m.add_class::<crate::lazy_component::LazyComponent>()?;
     m.add_class::<crate::suspense::SuspenseComponent>()?;
     m.add_function(wrap_pyfunction!(run_cli_py, m)?)?;
+    m.add(
+        "PyxSyntaxError",
+        m.py().get_type::<crate::diagnostics::PyxSyntaxError>(),
+    )?;
 
     add_jsx_transformers_to_module(m)?;
     add_hooks_to_module(m)?;
@@ -48,6 +70,8 @@ fn _core(m: &Bound<'_, PyModule>) -> PyResult<()> {
     add_compiler_to_module(m)?;
     add_event_handlers_to_module(m)?;
     add_virtual_dom_to_module(m)?;
+    add_traceback_mapper_to_module(m)?;
+    add_assets_to_module(m)?;
     add_css_compiler_to_module(m)?; // Add the CSS compiler module
 
     info!("ReactPyx core and CLI successfully initialized.");
@@ -75,18 +99,21 @@ fn add_hooks_to_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
 fn add_minifiers_to_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
     use crate::css_minifier::minify_css_code;
     use crate::html_minifier::minify_html_code;
-    use crate::js_minifier::minify_js_code;
+    use crate::js_minifier::{minify_js_code, minify_js_code_with_source_map};
 
     m.add_function(wrap_pyfunction!(minify_css_code, m)?)?;
     m.add_function(wrap_pyfunction!(minify_html_code, m)?)?;
     m.add_function(wrap_pyfunction!(minify_js_code, m)?)?;
+    m.add_function(wrap_pyfunction!(minify_js_code_with_source_map, m)?)?;
     Ok(())
 }
 
 /// Add JSX transformers to PyO3 module
 fn add_jsx_transformers_to_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    use crate::jsx_transformer::{incremental_jsx_transform, parse_jsx};
+    use crate::incremental::{incremental_jsx_transform, PyxDocument};
+    use crate::jsx_transformer::parse_jsx;
 
+    m.add_class::<PyxDocument>()?;
     m.add_function(wrap_pyfunction!(parse_jsx, m)?)?;
     m.add_function(wrap_pyfunction!(incremental_jsx_transform, m)?)?;
     Ok(())
@@ -117,36 +144,66 @@ fn add_virtual_dom_to_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
     Ok(())
 }
 
+/// Add the `.pyx` traceback mapper to PyO3 module
+fn add_traceback_mapper_to_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    use crate::traceback_mapper::{format_pyx_traceback, map_pyx_location};
+
+    m.add_function(wrap_pyfunction!(map_pyx_location, m)?)?;
+    m.add_function(wrap_pyfunction!(format_pyx_traceback, m)?)?;
+    Ok(())
+}
+
+/// Add the build manifest's asset resolvers to PyO3 module
+fn add_assets_to_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    use crate::assets::{asset_tag, asset_url};
+
+    m.add_function(wrap_pyfunction!(asset_url, m)?)?;
+    m.add_function(wrap_pyfunction!(asset_tag, m)?)?;
+    Ok(())
+}
+
 /// Validate paths to avoid empty paths
 fn validate_path(path: &str) -> PyResult<()> {
     if path.trim().is_empty() {
-        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+        Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
             "Path cannot be empty.",
-        ));
+        ))
     } else if path.contains(&['*', '?', '"', '<', '>', '|'][..]) {
         // Fixed to allow directory separators '/' and '\'
-        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+        Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
             "Path contains invalid characters.",
-        ));
+        ))
     } else {
         Ok(())
     }
 }
 
+/// Compiled file paths and `(file, error)` pairs, as returned to Python
+type PyCompileSummary = (Vec<String>, Vec<(String, String)>);
+
 /// Compile all `.pyx` files in a project
 #[pyfunction]
 fn compile_all_pyx_py(
     project_root: &str,
     _config_path: &str,
     _target_env: &str,
-) -> PyResult<(Vec<String>, Vec<(String, String)>)> {
+) -> PyResult<PyCompileSummary> {
     validate_path(project_root)?;
     // validate_path(_config_path)?;
 
     TOKIO_RUNTIME.block_on(async move {
-        compile_all_pyx(project_root, _config_path, _target_env)
+        let summary = compile_all_pyx(project_root, _config_path, _target_env)
             .await
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+        for warning in &summary.warnings {
+            warn!("{}", warning);
+        }
+        let errors = summary
+            .errors
+            .into_iter()
+            .map(|e| (e.file, e.error.to_string()))
+            .collect();
+        Ok((summary.compiled, errors))
     })
 }
 
@@ -165,7 +222,16 @@ fn compile_pyx_file_to_python_py(
     TOKIO_RUNTIME.block_on(async move {
         compile_pyx_file_to_python(&path, config_path, target_env)
             .await
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+            .map(|module| (module.python_code, module.css_code, module.js_code))
+            .map_err(
+                |e| match e.downcast_ref::<crate::diagnostics::Diagnostic>() {
+                    Some(diagnostic) => {
+                        let source = std::fs::read_to_string(&path).ok();
+                        diagnostic.to_py_err(source.as_deref())
+                    }
+                    None => PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()),
+                },
+            )
     })
 }
 
@@ -214,25 +280,26 @@ fn compile_pyx_to_js_py(
 /// Compile a `.pyx` file to JavaScript - Underlying implementation
 async fn compile_pyx_to_js(
     entry_file: &str,
-    _config_path: &str,
+    config_path: &str,
     output_dir: &str,
-    _target_env: &str,
+    target_env: &str,
 ) -> anyhow::Result<()> {
     use std::path::Path;
 
-    let entry_path = Path::new(entry_file);
-    if !entry_path.exists() {
-        return Err(anyhow::anyhow!("Input file does not exist: {}", entry_file));
+    if target_env != "node" {
+        return Err(anyhow::anyhow!(
+            "JavaScript bundles are built for the node target, not `{}`",
+            target_env
+        ));
     }
-
-    let code = tokio::fs::read_to_string(entry_file).await?;
-    let js_code = js_minifier::minify_js_code(&code)?;
-
-    let output_path = Path::new(output_dir).join("bundle.js");
-    tokio::fs::create_dir_all(output_dir).await?;
-    tokio::fs::write(&output_path, js_code).await?;
-
-    info!("Successfully compiled to JavaScript: {}", entry_file);
+    let manifest =
+        bundler::bundle(Path::new(entry_file), config_path, Path::new(output_dir)).await?;
+
+    info!(
+        "Successfully bundled {} into {} files",
+        entry_file,
+        manifest.files.len()
+    );
     Ok(())
 }
 
@@ -241,16 +308,18 @@ async fn compile_pyx_to_js(
 fn run_cli_py() -> PyResult<()> {
     use crate::cli::run_cli_with_args;
 
-    let args: Vec<String> = Python::with_gil(|py| {
+    let args: Vec<String> = Python::attach(|py| {
         let sys = py.import("sys")?;
         sys.getattr("argv")?.extract()
     })?;
 
     if let Err(e) = run_cli_with_args(args) {
-        error!("Error in CLI: {}", e);
-        return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-            e.to_string(),
-        ));
+        // `{:#}` includes the causes, such as the config key that is invalid
+        error!("Error in CLI: {:#}", e);
+        return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+            "{:#}",
+            e
+        )));
     }
 
     Ok(())
@@ -0,0 +1,629 @@
+//! Lint rules for `.pyx` sources.
+//!
+//! Rules run on the parsed `.pyx` tree and on the tree-sitter parse of the
+//! Python it compiles to, whose positions are mapped back to the source.
+//! Their severity can be changed or turned off under `lint.rules` in
+//! `pyx.config.json`, and single lines can opt out with a comment:
+//!
+//! ```python
+//! count = use_state(0) if ready else None  # pyx-lint: disable=rules-of-hooks
+//! # pyx-lint: disable-next-line
+//! return <button onClick={make_handler(id)}>Go</button>
+//! ```
+//!
+//! Inside JSX the comment goes in a `{# pyx-lint: ...}` block, which applies
+//! to the line of its closing brace. `# pyx-lint: disable-file=jsx-key` turns
+//! rules off for the whole file.
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::jsx_parser::{
+    parse_pyx, JsxAttr, JsxAttrValue, JsxChild, JsxElement, LineIndex, PyCode, PyPart, Span,
+};
+use crate::jsx_transformer::{transform_pyx_module, TransformOutput};
+use crate::python_validator::original_offset;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tree_sitter::{Node, Parser, Tree};
+
+/// A lint rule and its default severity
+pub struct Rule {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub severity: Severity,
+    check: fn(&LintContext<'_>, &mut Vec<Finding>),
+}
+
+/// Every lint rule, in the order they run
+pub static RULES: &[Rule] = &[
+    Rule {
+        name: "rules-of-hooks",
+        description: "Hooks must be called unconditionally at the top level of a component",
+        severity: Severity::Error,
+        check: check_rules_of_hooks,
+    },
+    Rule {
+        name: "jsx-key",
+        description: "Elements created in a comprehension or `map` need a `key`",
+        severity: Severity::Warning,
+        check: check_jsx_key,
+    },
+    Rule {
+        name: "duplicate-key",
+        description: "Sibling elements must not share a `key`, nor hook calls a state slot",
+        severity: Severity::Error,
+        check: check_duplicate_key,
+    },
+    Rule {
+        name: "handler-call",
+        description: "Event handlers must be callables, not the result of a call",
+        severity: Severity::Warning,
+        check: check_handler_call,
+    },
+];
+
+/// Severity of a rule as configured in `lint.rules`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    #[serde(alias = "warn")]
+    Warning,
+    Error,
+}
+
+/// The `lint` section of `pyx.config.json`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub rules: BTreeMap<String, RuleLevel>,
+}
+
+impl LintConfig {
+    /// Fails on rule names that are not in [`RULES`]
+    pub fn validate(&self) -> Result<()> {
+        let mut unknown: Vec<&str> = self
+            .rules
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !RULES.iter().any(|rule| rule.name == *name))
+            .collect();
+        unknown.sort();
+        if !unknown.is_empty() {
+            bail!("Unknown lint rules in `lint.rules`: {}", unknown.join(", "));
+        }
+        Ok(())
+    }
+
+    /// The severity `rule` reports with, or `None` when it is off
+    pub fn severity(&self, rule: &Rule) -> Option<Severity> {
+        match self.rules.get(rule.name) {
+            None => Some(rule.severity),
+            Some(RuleLevel::Off) => None,
+            Some(RuleLevel::Warning) => Some(Severity::Warning),
+            Some(RuleLevel::Error) => Some(Severity::Error),
+        }
+    }
+}
+
+/// Lints a `.pyx` source. Fails with the syntax error if it cannot be parsed.
+pub fn lint_pyx(source: &str, config: &LintConfig) -> Result<Vec<Diagnostic>, Diagnostic> {
+    let code = parse_pyx(source)?;
+    let generated = transform_pyx_module(source, "__lint__")?;
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::LANGUAGE.into())
+        .expect("the tree-sitter Python grammar is compatible");
+    let tree = parser
+        .parse(&generated.code, None)
+        .expect("parsing without a timeout always returns a tree");
+    let context = LintContext {
+        source,
+        code,
+        generated,
+        tree,
+    };
+
+    let suppressions = context.suppressions();
+    let lines = LineIndex::new(source);
+    let mut diagnostics = Vec::new();
+    for rule in RULES {
+        let Some(severity) = config.severity(rule) else {
+            continue;
+        };
+        let mut findings = Vec::new();
+        (rule.check)(&context, &mut findings);
+        for finding in findings {
+            let diagnostic =
+                Diagnostic::new(severity, rule.name, finding.message, finding.span, &lines);
+            if suppressions.covers(rule.name, diagnostic.line) {
+                continue;
+            }
+            diagnostics.push(match finding.help {
+                Some(help) => diagnostic.with_help(help),
+                None => diagnostic,
+            });
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.span.start, diagnostic.code));
+    Ok(diagnostics)
+}
+
+/// A problem found by a rule
+struct Finding {
+    span: Span,
+    message: String,
+    help: Option<String>,
+}
+
+struct LintContext<'s> {
+    source: &'s str,
+    code: PyCode,
+    /// The compiled module, which keeps the lines of the source
+    generated: TransformOutput,
+    tree: Tree,
+}
+
+impl LintContext<'_> {
+    fn text(&self, node: Node<'_>) -> &str {
+        &self.generated.code[node.byte_range()]
+    }
+
+    /// Position in the source of an offset in the compiled module
+    fn original(&self, offset: usize) -> usize {
+        original_offset(
+            self.source,
+            &self.generated.code,
+            &self.generated.mappings,
+            offset,
+        )
+    }
+
+    /// The `# pyx-lint:` comments of the host code, and of comment-only
+    /// `{# ...}` children, which the compiler drops
+    fn suppressions(&self) -> Suppressions {
+        let mut suppressions = Suppressions::default();
+        walk(self.tree.root_node(), &mut |node| {
+            if node.kind() == "comment" {
+                let line = node.start_position().row + 1;
+                suppressions.add(self.text(node), line);
+            }
+        });
+
+        let lines = LineIndex::new(self.source);
+        walk_elements(&self.code, &mut |element| {
+            for child in &element.children {
+                let JsxChild::Comment { span } = child else {
+                    continue;
+                };
+                // The block counts as written on the line of its `}`
+                let line = lines.line_col(span.end - 1).0;
+                let text = &self.source[span.start + 1..span.end - 1];
+                for comment in text.lines() {
+                    suppressions.add(comment.trim(), line);
+                }
+            }
+        });
+        suppressions
+    }
+}
+
+/// Rules turned off by `# pyx-lint:` comments
+#[derive(Default)]
+struct Suppressions {
+    /// The line (or the whole file) and the rules (or all of them)
+    entries: Vec<(Option<usize>, Option<HashSet<String>>)>,
+}
+
+impl Suppressions {
+    /// Records the directive of `comment`, written on `line`, if it has one
+    fn add(&mut self, comment: &str, line: usize) {
+        let comment = comment.trim_start_matches('#').trim();
+        let Some(directive) = comment.strip_prefix("pyx-lint:") else {
+            return;
+        };
+        let (directive, rules) = directive.split_once('=').unwrap_or((directive, ""));
+        let rules: Option<HashSet<String>> = (!rules.trim().is_empty()).then(|| {
+            rules
+                .split(',')
+                .map(|rule| rule.trim().to_string())
+                .collect()
+        });
+        let scope = match directive.trim() {
+            "disable" => Some(line),
+            "disable-next-line" => Some(line + 1),
+            "disable-file" => None,
+            _ => return,
+        };
+        self.entries.push((scope, rules));
+    }
+
+    fn covers(&self, rule: &str, line: usize) -> bool {
+        self.entries.iter().any(|(scope, rules)| {
+            scope.is_none_or(|scope| scope == line)
+                && rules.as_ref().is_none_or(|rules| rules.contains(rule))
+        })
+    }
+}
+
+/// Calls `visit` on `node` and its descendants, in source order
+fn walk<'t>(node: Node<'t>, visit: &mut impl FnMut(Node<'t>)) {
+    visit(node);
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        walk(child, visit);
+    }
+}
+
+/// Calls `visit` on every JSX element of `code`, including the ones nested in
+/// children and attribute expressions
+fn walk_elements<'c>(code: &'c PyCode, visit: &mut impl FnMut(&'c JsxElement)) {
+    for part in &code.parts {
+        if let PyPart::Element(element) = part {
+            walk_element(element, visit);
+        }
+    }
+}
+
+fn walk_element<'c>(element: &'c JsxElement, visit: &mut impl FnMut(&'c JsxElement)) {
+    visit(element);
+    for attr in &element.attributes {
+        match attr {
+            JsxAttr::Spread { code, .. } => walk_elements(code, visit),
+            JsxAttr::Named(attribute) => {
+                if let JsxAttrValue::Expr(code) = &attribute.value {
+                    walk_elements(code, visit);
+                }
+            }
+        }
+    }
+    for child in &element.children {
+        match child {
+            JsxChild::Text { .. } | JsxChild::Comment { .. } => {}
+            JsxChild::Expr { code, .. } => walk_elements(code, visit),
+            JsxChild::Element(child) => walk_element(child, visit),
+        }
+    }
+}
+
+/// The name of the function a call node calls, without its module
+/// (`use_state` for `reactpyx.use_state(0)`)
+fn callee_name<'t>(context: &'t LintContext<'_>, call: Node<'_>) -> Option<&'t str> {
+    let function = call.child_by_field_name("function")?;
+    match function.kind() {
+        "identifier" => Some(context.text(function)),
+        "attribute" => Some(context.text(function.child_by_field_name("attribute")?)),
+        _ => None,
+    }
+}
+
+fn check_rules_of_hooks(context: &LintContext<'_>, findings: &mut Vec<Finding>) {
+    walk(context.tree.root_node(), &mut |node| {
+        if node.kind() != "call" {
+            return;
+        }
+        let Some(hook) = callee_name(context, node).filter(|name| is_hook_name(name)) else {
+            return;
+        };
+        let Some(place) = conditional_ancestor(context, node) else {
+            return;
+        };
+        let start = context.original(node.start_byte());
+        findings.push(Finding {
+            span: Span::new(start, start + hook.len()),
+            message: format!("`{}` is called {}", hook, place),
+            help: Some(
+                "call hooks at the top level of the component, in the same order on every render"
+                    .to_string(),
+            ),
+        });
+    });
+}
+
+/// `use_` followed by a lowercase name, e.g. `use_state` or `use_my_hook`
+fn is_hook_name(name: &str) -> bool {
+    name.strip_prefix("use_")
+        .and_then(|rest| rest.bytes().next())
+        .is_some_and(|b| b.is_ascii_lowercase())
+}
+
+/// Describes what makes a hook call conditional, looking up to its function
+fn conditional_ancestor(context: &LintContext<'_>, node: Node<'_>) -> Option<&'static str> {
+    let mut child = node;
+    while let Some(parent) = child.parent() {
+        let is_field = |field: &str| parent.child_by_field_name(field) == Some(child);
+        let place = match parent.kind() {
+            "function_definition" => return nested_function(context, parent),
+            "module" => return Some("outside of a component"),
+            "if_statement" if is_field("condition") => None,
+            "if_statement" | "elif_clause" | "else_clause" | "conditional_expression" => {
+                Some("conditionally")
+            }
+            "boolean_operator" if is_field("left") => None,
+            "boolean_operator" => Some("conditionally"),
+            "for_statement" if is_field("right") => None,
+            "for_statement" | "while_statement" => Some("inside a loop"),
+            "list_comprehension"
+            | "set_comprehension"
+            | "dictionary_comprehension"
+            | "generator_expression" => Some("inside a comprehension"),
+            "lambda" => Some("inside a lambda"),
+            "try_statement" | "except_clause" | "finally_clause" => {
+                Some("inside a `try` statement")
+            }
+            "match_statement" => Some("inside a `match` statement"),
+            _ => None,
+        };
+        if place.is_some() {
+            return place;
+        }
+        child = parent;
+    }
+    None
+}
+
+/// Whether `function` is defined inside another function, such as an event
+/// handler of a component, which does not run on every render. Custom hooks
+/// defined there may call hooks.
+fn nested_function(context: &LintContext<'_>, function: Node<'_>) -> Option<&'static str> {
+    let name = function.child_by_field_name("name")?;
+    if is_hook_name(context.text(name)) {
+        return None;
+    }
+    let mut ancestor = function.parent();
+    while let Some(node) = ancestor {
+        match node.kind() {
+            "function_definition" => return Some("inside a nested function"),
+            "class_definition" | "module" => return None,
+            _ => ancestor = node.parent(),
+        }
+    }
+    None
+}
+
+fn check_jsx_key(context: &LintContext<'_>, findings: &mut Vec<Finding>) {
+    walk(context.tree.root_node(), &mut |node| {
+        if node.kind() != "call" || callee_name(context, node) != Some("create_element") {
+            return;
+        }
+        if !is_list_item(context, node) {
+            return;
+        }
+        let props = node
+            .child_by_field_name("arguments")
+            .and_then(|arguments| arguments.named_child(1));
+        let has_key = props.is_some_and(|props| {
+            let mut cursor = props.walk();
+            let entries: Vec<_> = props.named_children(&mut cursor).collect();
+            props.kind() != "dictionary"
+                || entries.iter().any(|entry| {
+                    entry.kind() == "dictionary_splat"
+                        || entry
+                            .child_by_field_name("key")
+                            .is_some_and(|key| context.text(key) == "\"key\"")
+                })
+        });
+        if has_key {
+            return;
+        }
+        let start = context.original(node.start_byte());
+        findings.push(Finding {
+            span: tag_span(context.source, start),
+            message: "Missing `key` for an element created in a list".to_string(),
+            help: Some(
+                "add a `key` that identifies the item among its siblings, e.g. `key={item.id}`"
+                    .to_string(),
+            ),
+        });
+    });
+}
+
+/// Whether a `create_element` call is the item of a comprehension or of a
+/// `map(lambda ...)`
+fn is_list_item(context: &LintContext<'_>, node: Node<'_>) -> bool {
+    let mut child = node;
+    while let Some(parent) = child.parent() {
+        match parent.kind() {
+            "parenthesized_expression" | "conditional_expression" | "boolean_operator" => {}
+            "list_comprehension" | "set_comprehension" | "generator_expression" => {
+                return parent.child_by_field_name("body") == Some(child);
+            }
+            "lambda" => {
+                return parent
+                    .parent()
+                    .and_then(|arguments| arguments.parent())
+                    .is_some_and(|call| {
+                        call.kind() == "call" && callee_name(context, call) == Some("map")
+                    });
+            }
+            _ => return false,
+        }
+        child = parent;
+    }
+    false
+}
+
+/// The `<tag` at `start` of the source
+fn tag_span(source: &str, start: usize) -> Span {
+    let name = source[start + 1..]
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':')))
+        .unwrap_or(source.len() - start - 1);
+    Span::new(start, start + 1 + name)
+}
+
+fn check_duplicate_key(context: &LintContext<'_>, findings: &mut Vec<Finding>) {
+    check_duplicate_sibling_keys(context, findings);
+    check_duplicate_state_slots(context, findings);
+}
+
+/// Siblings with the same `key`, whatever their tags, as React compares keys
+fn check_duplicate_sibling_keys(context: &LintContext<'_>, findings: &mut Vec<Finding>) {
+    walk_elements(&context.code, &mut |element| {
+        let mut seen: HashMap<String, (&str, Span)> = HashMap::new();
+        for child in &element.children {
+            let JsxChild::Element(child) = child else {
+                continue;
+            };
+            let Some(key) = child.attribute("key") else {
+                continue;
+            };
+            let value = match &key.value {
+                JsxAttrValue::Str { value, .. } => format!("\"{}\"", value),
+                JsxAttrValue::Expr(code) => context.source[code.span.start..code.span.end]
+                    .trim()
+                    .to_string(),
+                JsxAttrValue::Implicit => "True".to_string(),
+            };
+            match seen.get(&value) {
+                Some((name, first)) => {
+                    let line = LineIndex::new(context.source).line_col(first.start).0;
+                    findings.push(Finding {
+                        span: key.span,
+                        message: format!("Duplicate key {} for <{}>", value, child.name),
+                        help: Some(format!(
+                            "a sibling <{}> on line {} has the same key",
+                            name, line
+                        )),
+                    });
+                }
+                None => {
+                    seen.insert(value, (child.name.as_str(), key.span));
+                }
+            }
+        }
+    });
+}
+
+/// Hooks keeping their state under a `(component_id, key)` slot, with the
+/// numbers of positional arguments of the calls that name the slot
+const STATE_SLOT_HOOKS: &[(&str, &[usize])] = &[
+    ("use_state", &[3]),
+    ("use_reducer", &[4]),
+    ("use_lazy_state", &[2, 3]),
+];
+
+/// Hook calls naming the same `(component_id, key)` slot with string
+/// literals, which would share one state
+fn check_duplicate_state_slots(context: &LintContext<'_>, findings: &mut Vec<Finding>) {
+    let mut seen: HashMap<(String, String), Span> = HashMap::new();
+    walk(context.tree.root_node(), &mut |node| {
+        if node.kind() != "call" {
+            return;
+        }
+        let Some(hook) = callee_name(context, node) else {
+            return;
+        };
+        let Some((_, arities)) = STATE_SLOT_HOOKS.iter().find(|(name, _)| *name == hook) else {
+            return;
+        };
+        let Some(arguments) = node.child_by_field_name("arguments") else {
+            return;
+        };
+        let mut cursor = arguments.walk();
+        let positional: Vec<Node<'_>> = arguments
+            .named_children(&mut cursor)
+            .filter(|argument| !matches!(argument.kind(), "keyword_argument" | "comment"))
+            .collect();
+        if !arities.contains(&positional.len()) {
+            return;
+        }
+        let (Some(component_id), Some(key)) = (
+            string_literal(context, positional[0]),
+            string_literal(context, positional[1]),
+        ) else {
+            return;
+        };
+        let start = context.original(positional[1].start_byte());
+        let end = context.original(positional[1].end_byte()).max(start + 1);
+        let span = Span::new(start, end.min(context.source.len()));
+        match seen.get(&(component_id.clone(), key.clone())) {
+            Some(first) => {
+                let line = LineIndex::new(context.source).line_col(first.start).0;
+                findings.push(Finding {
+                    span,
+                    message: format!(
+                        "Duplicate key \"{}\" of the component \"{}\" in `{}`",
+                        key, component_id, hook
+                    ),
+                    help: Some(format!(
+                        "the hook call on line {} keeps its state under the same component id and key",
+                        line
+                    )),
+                });
+            }
+            None => {
+                seen.insert((component_id, key), span);
+            }
+        }
+    });
+}
+
+/// The value of a string literal without interpolations
+fn string_literal(context: &LintContext<'_>, node: Node<'_>) -> Option<String> {
+    if node.kind() != "string" {
+        return None;
+    }
+    let mut cursor = node.walk();
+    let mut value = String::new();
+    for part in node.named_children(&mut cursor) {
+        match part.kind() {
+            "string_start" | "string_end" => {}
+            "string_content" => value.push_str(context.text(part)),
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+fn check_handler_call(context: &LintContext<'_>, findings: &mut Vec<Finding>) {
+    walk(context.tree.root_node(), &mut |node| {
+        if node.kind() != "pair" {
+            return;
+        }
+        let (Some(key), Some(mut value)) = (
+            node.child_by_field_name("key"),
+            node.child_by_field_name("value"),
+        ) else {
+            return;
+        };
+        let name = context.text(key).trim_matches('"');
+        if !is_event_attribute(name) || key.kind() != "string" {
+            return;
+        }
+        while value.kind() == "parenthesized_expression" {
+            match value.named_child(0) {
+                Some(inner) => value = inner,
+                None => return,
+            }
+        }
+        if value.kind() != "call" || callee_name(context, value) == Some("partial") {
+            return;
+        }
+        let start = context.original(value.start_byte());
+        let end = context.original(value.end_byte()).max(start + 1);
+        let span = Span::new(start, end.min(context.source.len()));
+        let call = &context.source[span.start..span.end];
+        let help = match call.strip_suffix("()") {
+            Some(function) if !function.contains(['(', '\n']) => {
+                format!("pass the function itself: `{}={{{}}}`", name, function)
+            }
+            _ => "wrap the call in a lambda, e.g. `lambda event: ...`".to_string(),
+        };
+        findings.push(Finding {
+            span,
+            message: format!(
+                "`{}` is given the result of a call instead of a callable",
+                name
+            ),
+            help: Some(help),
+        });
+    });
+}
+
+/// `on` followed by an uppercase letter, as in `onClick`
+fn is_event_attribute(name: &str) -> bool {
+    name.strip_prefix("on")
+        .and_then(|rest| rest.bytes().next())
+        .is_some_and(|b| b.is_ascii_uppercase())
+}
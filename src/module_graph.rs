@@ -0,0 +1,192 @@
+//! Dependency graph of the `.pyx` modules of a project, from the imports
+//! between them.
+//!
+//! The graph tells which modules a change affects: the changed module and
+//! every module importing it, directly or through others. It also finds the
+//! cycles of module-level imports, which Python cannot complete when the
+//! modules import names from each other. Imports inside functions run later,
+//! so they are in the graph but never part of a cycle.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// A module a `.pyx` source may import, with the span of the import in the
+/// source. Names that are not modules of the project are left out of the
+/// graph, so an import records every module it could load.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleImport {
+    pub module: String,
+    pub start: usize,
+    pub end: usize,
+    /// Whether the import runs when the module is imported, rather than
+    /// inside a function
+    pub top_level: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModuleGraph {
+    modules: BTreeMap<String, ModuleNode>,
+}
+
+#[derive(Debug, Clone)]
+struct ModuleNode {
+    file: PathBuf,
+    imports: Vec<ModuleImport>,
+}
+
+impl ModuleGraph {
+    pub fn new() -> Self {
+        ModuleGraph::default()
+    }
+
+    /// Adds the module `module`, compiled from `file`, with its imports
+    pub fn insert(&mut self, module: &str, file: &Path, imports: Vec<ModuleImport>) {
+        let node = ModuleNode {
+            file: file.to_path_buf(),
+            imports,
+        };
+        self.modules.insert(module.to_string(), node);
+    }
+
+    /// Module names, in order
+    pub fn modules(&self) -> impl Iterator<Item = &str> {
+        self.modules.keys().map(String::as_str)
+    }
+
+    /// The module compiled from `file`
+    pub fn module_of(&self, file: &Path) -> Option<&str> {
+        self.modules
+            .iter()
+            .find(|(_, node)| node.file == file)
+            .map(|(module, _)| module.as_str())
+    }
+
+    /// The source file of `module`
+    pub fn file_of(&self, module: &str) -> Option<&Path> {
+        self.modules.get(module).map(|node| node.file.as_path())
+    }
+
+    /// The imports of `module` that are modules of the project
+    pub fn imports_of(&self, module: &str) -> Vec<&ModuleImport> {
+        self.modules.get(module).map_or_else(Vec::new, |node| {
+            node.imports
+                .iter()
+                .filter(|import| {
+                    import.module != module && self.modules.contains_key(&import.module)
+                })
+                .collect()
+        })
+    }
+
+    /// The modules `module` imports, in order
+    pub fn dependencies(&self, module: &str) -> BTreeSet<&str> {
+        self.imports_of(module)
+            .into_iter()
+            .map(|import| import.module.as_str())
+            .collect()
+    }
+
+    /// The modules importing `module` directly, in order
+    pub fn dependents(&self, module: &str) -> BTreeSet<&str> {
+        self.modules
+            .keys()
+            .filter(|other| self.dependencies(other).contains(module))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// `modules` and every module importing one of them, directly or not
+    pub fn affected(&self, modules: &[&str]) -> BTreeSet<String> {
+        let mut affected: BTreeSet<String> = BTreeSet::new();
+        let mut queue: VecDeque<&str> = modules.iter().copied().collect();
+        while let Some(module) = queue.pop_front() {
+            if affected.insert(module.to_string()) {
+                queue.extend(self.dependents(module));
+            }
+        }
+        affected
+    }
+
+    /// The source files of the modules affected by changes to `files`.
+    /// Files the graph does not know about are affected as well.
+    pub fn affected_files(&self, files: &[PathBuf]) -> BTreeSet<PathBuf> {
+        let modules: Vec<&str> = files
+            .iter()
+            .filter_map(|file| self.module_of(file))
+            .collect();
+        let mut affected: BTreeSet<PathBuf> = self
+            .affected(&modules)
+            .iter()
+            .filter_map(|module| self.file_of(module))
+            .map(Path::to_path_buf)
+            .collect();
+        affected.extend(files.iter().cloned());
+        affected
+    }
+
+    /// Cycles of module-level imports, each as the modules along it, from
+    /// the first module of the cycle in order back to itself. A module is in
+    /// at most one of the cycles, so each group of modules importing each
+    /// other is reported once.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let edges = |module: &str| -> Vec<&str> {
+            let mut next: Vec<&str> = self
+                .imports_of(module)
+                .into_iter()
+                .filter(|import| import.top_level)
+                .map(|import| import.module.as_str())
+                .collect();
+            next.sort_unstable();
+            next.dedup();
+            next
+        };
+
+        let mut cycles = Vec::new();
+        let mut seen: BTreeSet<&str> = BTreeSet::new();
+        for start in self.modules.keys() {
+            if seen.contains(start.as_str()) {
+                continue;
+            }
+            // Shortest way back from `start` to itself
+            let mut previous: BTreeMap<&str, &str> = BTreeMap::new();
+            let mut queue: VecDeque<&str> = VecDeque::from([start.as_str()]);
+            let mut found = false;
+            while let Some(module) = queue.pop_front() {
+                for next in edges(module) {
+                    if next == start {
+                        previous.insert(start, module);
+                        found = true;
+                        break;
+                    }
+                    if !previous.contains_key(next) && !seen.contains(next) {
+                        previous.insert(next, module);
+                        queue.push_back(next);
+                    }
+                }
+                if found {
+                    break;
+                }
+            }
+            if !found {
+                continue;
+            }
+            let mut cycle = vec![start.clone()];
+            let mut module = previous[start.as_str()];
+            while module != start {
+                cycle.push(module.to_string());
+                module = previous[module];
+            }
+            cycle.push(start.clone());
+            cycle.reverse();
+            seen.extend(cycle.iter().map(|module| {
+                self.modules
+                    .get_key_value(module)
+                    .map(|(module, _)| module.as_str())
+                    .unwrap_or_default()
+            }));
+            cycles.push(cycle);
+        }
+        cycles
+    }
+}
@@ -0,0 +1,2359 @@
+//! Compiles `.pyx` components to JavaScript ES modules for the `node` target.
+//!
+//! The compiled Python of a module, where JSX is already `create_element`
+//! calls, is parsed with tree-sitter and translated into a `swc_core` AST,
+//! which is printed as an ES module importing React. The translation covers
+//! the Python that components are written in: functions and lambdas, props,
+//! JSX, `use_state`, `use_reducer`, the effect hooks and `lazy`, event handlers,
+//! control flow, literals, comprehensions and a few builtins and string, list
+//! and dict methods. Anything else fails with a `PYX019` diagnostic at the
+//! construct, rather than producing JavaScript that behaves differently.
+//!
+//! Some JavaScript operators differ from Python's for some operands, such as
+//! `+` on lists, `in` on dicts, `==` on lists or the truthiness of an empty
+//! list. They are kept where the syntax of the operands shows they agree,
+//! e.g. with a number literal, and otherwise become calls of small helpers
+//! with Python's semantics, declared in the modules that use them.
+//!
+//! Project imports become imports of the sibling `.mjs` modules, and the
+//! functions and variables of a module are its exports.
+
+use crate::diagnostics::Diagnostic;
+use crate::import_rewriter::{absolute_module, source_span};
+use crate::jsx_parser::{LineIndex, Span};
+use crate::jsx_transformer::TransformOutput;
+use crate::virtual_dom::FRAGMENT_TAG;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use swc_core::common::sync::Lrc;
+use swc_core::common::{FileName, SourceMap, DUMMY_SP};
+use swc_core::ecma::ast::*;
+use swc_core::ecma::codegen::text_writer::JsWriter;
+use swc_core::ecma::codegen::Emitter;
+use swc_core::ecma::parser::{lexer::Lexer, EsSyntax, Parser as EsParser, StringInput, Syntax};
+use swc_core::ecma::transforms::base::fixer::fixer;
+use tree_sitter::{Node, Parser};
+
+/// Module the compiled components import `createElement` and the hooks from
+pub const REACT_MODULE: &str = "react";
+
+/// Extension of the compiled modules, which Node loads as ES modules without
+/// a `package.json`
+pub const NODE_MODULE_EXTENSION: &str = "mjs";
+
+const UNSUPPORTED_HELP: &str = "The node target compiles functions, JSX, state and effect hooks, \
+     handlers and plain data; build this module for the python target instead";
+
+/// The React export each `reactpyx` function compiles to
+const RUNTIME_EXPORTS: &[(&str, &str)] = &[
+    ("create_element", "createElement"),
+    ("use_state", "useState"),
+    ("use_reducer", "useReducer"),
+    ("use_effect", "useEffect"),
+    ("use_effect_with_deps", "useEffect"),
+    ("lazy", "lazy"),
+    ("Suspense", "Suspense"),
+];
+
+/// Words JavaScript reserves that are valid Python names
+const RESERVED_WORDS: &[&str] = &[
+    "arguments",
+    "case",
+    "catch",
+    "const",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "function",
+    "implements",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "typeof",
+    "var",
+    "void",
+];
+
+/// Helpers giving operators Python's semantics, with the helpers each one
+/// calls
+const HELPERS: &[(&str, &[&str], &str)] = &[
+    (
+        "__truthy",
+        &[],
+        r#"function __truthy(value) {
+    if (Array.isArray(value) || typeof value === "string") {
+        return value.length > 0;
+    }
+    if (value instanceof Map || value instanceof Set) {
+        return value.size > 0;
+    }
+    if (value !== null && typeof value === "object" && Object.getPrototypeOf(value) === Object.prototype) {
+        return Object.keys(value).length > 0;
+    }
+    return Boolean(value);
+}
+"#,
+    ),
+    (
+        "__and",
+        &["__truthy"],
+        r#"function __and(left, right) {
+    return __truthy(left) ? right() : left;
+}
+"#,
+    ),
+    (
+        "__or",
+        &["__truthy"],
+        r#"function __or(left, right) {
+    return __truthy(left) ? left : right();
+}
+"#,
+    ),
+    (
+        "__add",
+        &[],
+        r#"function __add(left, right) {
+    return Array.isArray(left) ? [...left, ...right] : left + right;
+}
+"#,
+    ),
+    (
+        "__mul",
+        &[],
+        r#"function __mul(left, right) {
+    if (typeof right === "string" || Array.isArray(right)) {
+        [left, right] = [right, left];
+    }
+    if (typeof left === "string") {
+        return left.repeat(Math.max(right, 0));
+    }
+    if (Array.isArray(left)) {
+        return Array.from({ length: Math.max(right, 0) }, () => left).flat();
+    }
+    return left * right;
+}
+"#,
+    ),
+    (
+        "__eq",
+        &[],
+        r#"function __eq(left, right) {
+    if (left === right || left == null || right == null) {
+        return left == right;
+    }
+    if (Array.isArray(left) && Array.isArray(right)) {
+        return left.length === right.length && left.every((item, index) => __eq(item, right[index]));
+    }
+    const isDict = (value) => typeof value === "object" && Object.getPrototypeOf(value) === Object.prototype;
+    if (isDict(left) && isDict(right)) {
+        const keys = Object.keys(left);
+        return keys.length === Object.keys(right).length
+            && keys.every((key) => Object.hasOwn(right, key) && __eq(left[key], right[key]));
+    }
+    return false;
+}
+"#,
+    ),
+    (
+        "__contains",
+        &[],
+        r#"function __contains(container, item) {
+    if (Array.isArray(container) || typeof container === "string") {
+        return container.includes(item);
+    }
+    if (container instanceof Map || container instanceof Set) {
+        return container.has(item);
+    }
+    return Object.hasOwn(container, item);
+}
+"#,
+    ),
+];
+
+type Translated<T> = Result<T, Diagnostic>;
+
+/// What an expression evaluates to, as far as its syntax tells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Bool,
+    Number,
+    Str,
+    List,
+    Dict,
+}
+
+impl Shape {
+    /// Whether JavaScript's operators treat the value as Python's do: its
+    /// truthiness and equality
+    fn is_primitive(self) -> bool {
+        matches!(self, Shape::Bool | Shape::Number | Shape::Str)
+    }
+}
+
+/// Compiles `output`, the compiled code of `source` as the module `module`,
+/// to an ES module. Imports of project `modules` (module and package names)
+/// load the other compiled modules.
+pub fn compile_es_module(
+    source: &str,
+    output: &TransformOutput,
+    module: &str,
+    modules: &HashSet<String>,
+) -> Result<String, Diagnostic> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::LANGUAGE.into())
+        .expect("the tree-sitter Python grammar is compatible");
+    let tree = parser
+        .parse(&output.code, None)
+        .expect("tree-sitter parses without a timeout");
+
+    let mut translator = Translator {
+        source,
+        output,
+        lines: LineIndex::new(source),
+        module,
+        modules,
+        runtime: HashMap::new(),
+        react: BTreeSet::new(),
+        helpers: BTreeSet::new(),
+        setters: HashSet::new(),
+        scopes: Vec::new(),
+    };
+    let body = translator.module_items(tree.root_node())?;
+    Ok(emit_module(body))
+}
+
+/// Path of a compiled module relative to the module `from`, as imported
+/// from it (`./Header.mjs`, `../App.mjs`)
+pub fn module_specifier(from: &str, module: &str) -> String {
+    let mut package: Vec<&str> = from.split('.').collect();
+    package.pop();
+    let target: Vec<&str> = module.split('.').collect();
+    let common = package
+        .iter()
+        .zip(&target)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let prefix = match package.len() - common {
+        0 => "./".to_string(),
+        ups => "../".repeat(ups),
+    };
+    format!(
+        "{}{}.{}",
+        prefix,
+        target[common..].join("/"),
+        NODE_MODULE_EXTENSION
+    )
+}
+
+/// Prints `body` as an ES module
+pub(crate) fn emit_module(body: Vec<ModuleItem>) -> String {
+    let program = Program::Module(Module {
+        span: DUMMY_SP,
+        body,
+        shebang: None,
+    });
+    // Adds the parentheses the AST leaves implicit
+    let Program::Module(module) = program.apply(fixer(None)) else {
+        unreachable!("the fixer keeps a module a module");
+    };
+
+    let cm: Lrc<SourceMap> = Default::default();
+    let mut buf = vec![];
+    {
+        let writer = Box::new(JsWriter::new(cm.clone(), "\n", &mut buf, None));
+        let mut emitter = Emitter {
+            cfg: Default::default(),
+            cm,
+            comments: None,
+            wr: writer,
+        };
+        emitter
+            .emit_module(&module)
+            .expect("writing to a buffer does not fail");
+    }
+    String::from_utf8(buf).expect("the emitter writes UTF-8")
+}
+
+/// How a name is bound where it is assigned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Site {
+    Assign,
+    Loop,
+    Def,
+    Import,
+}
+
+/// The names of a function, lambda, comprehension or of the module
+#[derive(Debug, Default)]
+struct Scope {
+    /// Parameters and every name assigned in the scope
+    names: HashSet<String>,
+    /// Names declared so far in the output
+    declared: HashSet<String>,
+    /// Names first assigned inside a block, declared at the top of the scope
+    hoisted: Vec<String>,
+    /// Number of assignments of each name
+    assignments: HashMap<String, usize>,
+    /// Names only bound by `for` loops, declared by each loop
+    loop_only: HashSet<String>,
+    /// `global` and `nonlocal` names, which belong to an enclosing scope
+    outer: HashSet<String>,
+    /// Nesting of the statement being translated in blocks of the scope
+    depth: usize,
+}
+
+impl Scope {
+    fn with_params(params: &[String]) -> Self {
+        Scope {
+            names: params.iter().cloned().collect(),
+            declared: params.iter().cloned().collect(),
+            ..Scope::default()
+        }
+    }
+}
+
+struct Translator<'a> {
+    source: &'a str,
+    output: &'a TransformOutput,
+    lines: LineIndex,
+    module: &'a str,
+    modules: &'a HashSet<String>,
+    /// Local names of the `reactpyx` imports, with their React exports
+    runtime: HashMap<String, &'static str>,
+    /// React exports the module uses
+    react: BTreeSet<&'static str>,
+    /// [`HELPERS`] the module calls
+    helpers: BTreeSet<&'static str>,
+    /// Setters and dispatchers returned by `use_state` and `use_reducer`
+    setters: HashSet<String>,
+    scopes: Vec<Scope>,
+}
+
+impl<'a> Translator<'a> {
+    fn text(&self, node: Node<'_>) -> &'a str {
+        &self.output.code[node.byte_range()]
+    }
+
+    fn unsupported(&self, node: Node<'_>, what: impl std::fmt::Display) -> Diagnostic {
+        let (start, end) = source_span(self.source, self.output, node);
+        Diagnostic::error(
+            "PYX019",
+            format!("{} cannot be compiled for the node target", what),
+            Span::new(start, end),
+            &self.lines,
+        )
+        .with_help(UNSUPPORTED_HELP)
+    }
+
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().expect("a scope is open")
+    }
+
+    fn is_bound(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.names.contains(name))
+    }
+
+    fn react_name(&mut self, export: &'static str) -> Box<Expr> {
+        self.react.insert(export);
+        name_expr(export)
+    }
+
+    /// A call of one of the [`HELPERS`]
+    fn helper(&mut self, name: &'static str, args: Vec<ExprOrSpread>) -> Box<Expr> {
+        self.helpers.insert(name);
+        call(name_expr(name), args)
+    }
+
+    // Module
+
+    fn module_items(&mut self, root: Node<'_>) -> Translated<Vec<ModuleItem>> {
+        let statements = statements_of(root);
+        let scope = self.plan_scope(&statements, &[])?;
+        self.scopes.push(scope);
+
+        let mut items = Vec::new();
+        for name in self.scope().hoisted.clone() {
+            let decl = var_decl(VarDeclKind::Let, binding(&name), None);
+            items.push(export(Decl::Var(decl)));
+        }
+        for (index, statement) in statements.iter().enumerate() {
+            if index == 0 && is_docstring(*statement) {
+                continue;
+            }
+            match statement.kind() {
+                "import_statement" | "import_from_statement" | "future_import_statement" => {
+                    items.extend(self.import(*statement)?);
+                }
+                _ => {
+                    for stmt in self.statement(*statement)? {
+                        items.push(match stmt {
+                            Stmt::Decl(decl) => export(decl),
+                            stmt => ModuleItem::Stmt(stmt),
+                        });
+                    }
+                }
+            }
+        }
+        self.scopes.pop();
+
+        if !self.react.is_empty() {
+            let specifiers = self
+                .react
+                .iter()
+                .map(|name| {
+                    ImportSpecifier::Named(ImportNamedSpecifier {
+                        span: DUMMY_SP,
+                        local: ident(name),
+                        imported: None,
+                        is_type_only: false,
+                    })
+                })
+                .collect();
+            items.insert(0, import_decl(specifiers, REACT_MODULE));
+        }
+        let imports = items
+            .iter()
+            .take_while(|item| matches!(item, ModuleItem::ModuleDecl(ModuleDecl::Import(_))))
+            .count();
+        items.splice(imports..imports, helper_items(&self.helpers));
+        Ok(items)
+    }
+
+    fn import(&mut self, node: Node<'_>) -> Translated<Vec<ModuleItem>> {
+        if node.kind() == "future_import_statement" {
+            return Ok(Vec::new());
+        }
+        let mut cursor = node.walk();
+        let names: Vec<Node<'_>> = node.children_by_field_name("name", &mut cursor).collect();
+        if node.kind() == "import_statement" {
+            // `import package.module as module`, or a module at the top of `src`
+            let mut items = Vec::new();
+            for name in names {
+                let (dotted, local) = self.import_names(name);
+                if !self.is_module(dotted) {
+                    return Err(self.python_import(name, dotted));
+                }
+                items.push(self.namespace_import(name, local, dotted)?);
+            }
+            return Ok(items);
+        }
+
+        let module_node = node.child_by_field_name("module_name").unwrap();
+        let module_name = self.text(module_node);
+        if module_name == "reactpyx" {
+            for name in names {
+                let (imported, local) = self.import_names(name);
+                let Some((_, export)) = RUNTIME_EXPORTS.iter().find(|(py, _)| *py == imported)
+                else {
+                    return Err(self.unsupported(name, format!("`{}` of `reactpyx`", imported)));
+                };
+                self.runtime.insert(local.to_string(), export);
+            }
+            return Ok(Vec::new());
+        }
+        if module_name == "typing" {
+            // Type annotations are left out of the output
+            return Ok(Vec::new());
+        }
+        if node.child_by_field_name("name").is_none() {
+            return Err(self.unsupported(node, "`import *`"));
+        }
+
+        let absolute = if module_name.starts_with('.') {
+            absolute_module(self.module, module_name)
+        } else {
+            Some(module_name.to_string())
+        };
+        let Some(absolute) = absolute.filter(|name| name.is_empty() || self.modules.contains(name))
+        else {
+            return Err(self.python_import(module_node, module_name));
+        };
+
+        let mut items = Vec::new();
+        let mut specifiers = Vec::new();
+        for name in names {
+            let (imported, local) = self.import_names(name);
+            let submodule = if absolute.is_empty() {
+                imported.to_string()
+            } else {
+                format!("{}.{}", absolute, imported)
+            };
+            if self.is_module(&submodule) {
+                items.push(self.namespace_import(name, local, &submodule)?);
+            } else if self.is_module(&absolute) {
+                self.check_binding(name, local)?;
+                specifiers.push(ImportSpecifier::Named(ImportNamedSpecifier {
+                    span: DUMMY_SP,
+                    local: ident(local),
+                    imported: (imported != local).then(|| ModuleExportName::Ident(ident(imported))),
+                    is_type_only: false,
+                }));
+            } else {
+                return Err(self.unsupported(
+                    name,
+                    format!("`{}` of the package `{}`", imported, absolute),
+                ));
+            }
+        }
+        if !specifiers.is_empty() {
+            let specifier = module_specifier(self.module, &absolute);
+            items.insert(0, import_decl(specifiers, &specifier));
+        }
+        Ok(items)
+    }
+
+    /// The imported and local names of an import
+    fn import_names(&self, name: Node<'_>) -> (&'a str, &'a str) {
+        match name.kind() {
+            "aliased_import" => (
+                self.text(name.child_by_field_name("name").unwrap()),
+                self.text(name.child_by_field_name("alias").unwrap()),
+            ),
+            _ => (self.text(name), self.text(name)),
+        }
+    }
+
+    fn python_import(&self, node: Node<'_>, module: &str) -> Diagnostic {
+        self.unsupported(node, format!("The Python module `{}`", module))
+            .with_help("The node target can only import the .pyx modules of the project")
+    }
+
+    /// Whether `name` is a module of the project rather than a package
+    fn is_module(&self, name: &str) -> bool {
+        let prefix = format!("{}.", name);
+        self.modules.contains(name) && !self.modules.iter().any(|m| m.starts_with(&prefix))
+    }
+
+    fn namespace_import(
+        &self,
+        node: Node<'_>,
+        local: &str,
+        module: &str,
+    ) -> Translated<ModuleItem> {
+        self.check_binding(node, local)?;
+        let specifier = ImportSpecifier::Namespace(ImportStarAsSpecifier {
+            span: DUMMY_SP,
+            local: ident(local),
+        });
+        Ok(import_decl(
+            vec![specifier],
+            &module_specifier(self.module, module),
+        ))
+    }
+
+    fn check_binding(&self, node: Node<'_>, name: &str) -> Translated<()> {
+        if RESERVED_WORDS.contains(&name) {
+            return Err(self
+                .unsupported(node, format!("The name `{}`", name))
+                .with_help(format!(
+                    "`{}` is a reserved word in JavaScript; rename it",
+                    name
+                )));
+        }
+        Ok(())
+    }
+
+    // Scopes
+
+    /// Finds the names the scope of `statements` binds, and which of them
+    /// must be declared before the statements
+    fn plan_scope(&self, statements: &[Node<'_>], params: &[String]) -> Translated<Scope> {
+        let mut scope = Scope::with_params(params);
+        let mut sites = Vec::new();
+        for statement in statements {
+            self.collect_sites(*statement, &mut sites, &mut scope.outer)?;
+        }
+        sites.retain(|(name, _)| !scope.outer.contains(name));
+
+        let mut kinds: HashMap<&str, Vec<Site>> = HashMap::new();
+        for (name, site) in &sites {
+            kinds.entry(name.as_str()).or_default().push(*site);
+        }
+        for (name, kinds) in &kinds {
+            scope.names.insert(name.to_string());
+            scope.assignments.insert(name.to_string(), kinds.len());
+            if kinds.iter().all(|site| *site == Site::Loop) {
+                scope.loop_only.insert(name.to_string());
+            }
+        }
+
+        let mut seen: HashSet<String> = params.iter().cloned().collect();
+        seen.extend(scope.outer.iter().cloned());
+        for statement in statements {
+            let mut statement_sites = Vec::new();
+            self.collect_sites(*statement, &mut statement_sites, &mut HashSet::new())?;
+            let names: Vec<String> = statement_sites
+                .into_iter()
+                .map(|(name, _)| name)
+                .filter(|name| !scope.outer.contains(name))
+                .collect();
+            let declares_inline = match declaration_targets(*statement, &self.output.code) {
+                Some(targets) => targets.iter().all(|name| !seen.contains(name)),
+                None => matches!(
+                    statement.kind(),
+                    "function_definition" | "import_statement" | "import_from_statement"
+                ),
+            };
+            for name in names {
+                if seen.insert(name.clone()) && !declares_inline && !scope.loop_only.contains(&name)
+                {
+                    scope.hoisted.push(name);
+                }
+            }
+        }
+        scope.declared.extend(scope.hoisted.iter().cloned());
+        Ok(scope)
+    }
+
+    /// The names `node` assigns in its scope, in source order, and the
+    /// names it declares `global` or `nonlocal`
+    fn collect_sites(
+        &self,
+        node: Node<'_>,
+        sites: &mut Vec<(String, Site)>,
+        outer: &mut HashSet<String>,
+    ) -> Translated<()> {
+        match node.kind() {
+            "function_definition" => {
+                let name = node.child_by_field_name("name").unwrap();
+                sites.push((self.text(name).to_string(), Site::Def));
+                return Ok(());
+            }
+            "lambda"
+            | "list_comprehension"
+            | "generator_expression"
+            | "set_comprehension"
+            | "dictionary_comprehension" => return Ok(()),
+            "global_statement" | "nonlocal_statement" => {
+                let mut cursor = node.walk();
+                for name in node.named_children(&mut cursor) {
+                    outer.insert(self.text(name).to_string());
+                }
+                return Ok(());
+            }
+            "import_statement" | "import_from_statement" => {
+                let mut cursor = node.walk();
+                for name in node.children_by_field_name("name", &mut cursor) {
+                    let (_, local) = self.import_names(name);
+                    sites.push((local.to_string(), Site::Import));
+                }
+                return Ok(());
+            }
+            "assignment" | "augmented_assignment" => {
+                let left = node.child_by_field_name("left").unwrap();
+                for name in pattern_names(left, &self.output.code) {
+                    sites.push((name, Site::Assign));
+                }
+            }
+            "for_statement" => {
+                let left = node.child_by_field_name("left").unwrap();
+                for name in pattern_names(left, &self.output.code) {
+                    sites.push((name, Site::Loop));
+                }
+            }
+            _ => {}
+        }
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            self.collect_sites(child, sites, outer)?;
+        }
+        Ok(())
+    }
+
+    // Statements
+
+    fn block(&mut self, block: Node<'_>) -> Translated<Vec<Stmt>> {
+        self.scope().depth += 1;
+        let mut stmts = Vec::new();
+        for statement in statements_of(block) {
+            stmts.extend(self.statement(statement)?);
+        }
+        self.scope().depth -= 1;
+        Ok(stmts)
+    }
+
+    fn block_stmt(&mut self, block: Node<'_>) -> Translated<Box<Stmt>> {
+        Ok(Box::new(Stmt::Block(BlockStmt {
+            stmts: self.block(block)?,
+            ..Default::default()
+        })))
+    }
+
+    fn statement(&mut self, node: Node<'_>) -> Translated<Vec<Stmt>> {
+        let stmt = match node.kind() {
+            "expression_statement" => return self.expression_statement(node),
+            "function_definition" => return self.function_definition(node).map(|s| vec![s]),
+            "return_statement" => {
+                let arg = match node.named_child(0) {
+                    Some(value) => Some(self.expression(value)?),
+                    None => None,
+                };
+                Stmt::Return(ReturnStmt {
+                    span: DUMMY_SP,
+                    arg,
+                })
+            }
+            "if_statement" => self.if_statement(node)?,
+            "for_statement" => self.for_statement(node)?,
+            "while_statement" => {
+                if let Some(alternative) = node.child_by_field_name("alternative") {
+                    return Err(self.unsupported(alternative, "`else` after a loop"));
+                }
+                let test = self.condition(node.child_by_field_name("condition").unwrap())?;
+                let body = self.block_stmt(node.child_by_field_name("body").unwrap())?;
+                Stmt::While(WhileStmt {
+                    span: DUMMY_SP,
+                    test,
+                    body,
+                })
+            }
+            "break_statement" => Stmt::Break(BreakStmt {
+                span: DUMMY_SP,
+                label: None,
+            }),
+            "continue_statement" => Stmt::Continue(ContinueStmt {
+                span: DUMMY_SP,
+                label: None,
+            }),
+            "pass_statement" | "global_statement" | "nonlocal_statement" => return Ok(Vec::new()),
+            "import_statement" | "import_from_statement" | "future_import_statement" => {
+                return Err(self
+                    .unsupported(node, "An import inside a function")
+                    .with_help(
+                    "Move the import to the top of the module; ES modules may import each other",
+                ))
+            }
+            kind => return Err(self.unsupported(node, describe(kind))),
+        };
+        Ok(vec![stmt])
+    }
+
+    fn expression_statement(&mut self, node: Node<'_>) -> Translated<Vec<Stmt>> {
+        if node.named_child_count() != 1 {
+            return Err(self.unsupported(node, "A tuple expression statement"));
+        }
+        let expression = node.named_child(0).unwrap();
+        match expression.kind() {
+            "assignment" => self.assignment(expression),
+            "augmented_assignment" => {
+                let expr = self.augmented_assignment(expression)?;
+                Ok(vec![expr_stmt(expr)])
+            }
+            _ => Ok(vec![expr_stmt(self.expression(expression)?)]),
+        }
+    }
+
+    fn assignment(&mut self, node: Node<'_>) -> Translated<Vec<Stmt>> {
+        let left = node.child_by_field_name("left").unwrap();
+        let Some(right) = node.child_by_field_name("right") else {
+            // An annotation without a value, `count: int`
+            return Ok(Vec::new());
+        };
+        self.register_setters(left, right);
+
+        let targets = pattern_names(left, &self.output.code);
+        let scope = self.scopes.last().unwrap();
+        let declare = scope.depth == 0
+            && right.kind() != "assignment"
+            && !targets.is_empty()
+            && targets
+                .iter()
+                .all(|name| !scope.declared.contains(name) && !scope.outer.contains(name))
+            && is_binding_pattern(left);
+        let constant = targets
+            .iter()
+            .all(|name| scope.assignments.get(name) == Some(&1));
+        let value = self.assigned_value(right)?;
+
+        if declare {
+            let kind = if constant {
+                VarDeclKind::Const
+            } else {
+                VarDeclKind::Let
+            };
+            let pat = self.pattern(left)?;
+            self.scope().declared.extend(targets);
+            return Ok(vec![Stmt::Decl(Decl::Var(var_decl(
+                kind,
+                pat,
+                Some(value),
+            )))]);
+        }
+        let target = self.assign_target(left)?;
+        Ok(vec![expr_stmt(assign(target, AssignOp::Assign, value))])
+    }
+
+    /// The value of an assignment, which is itself an assignment when
+    /// assignments are chained (`a = b = 0`)
+    fn assigned_value(&mut self, right: Node<'_>) -> Translated<Box<Expr>> {
+        if right.kind() != "assignment" {
+            return self.expression(right);
+        }
+        let left = right.child_by_field_name("left").unwrap();
+        let value = right.child_by_field_name("right").unwrap();
+        let target = self.assign_target(left)?;
+        let value = self.assigned_value(value)?;
+        Ok(assign(target, AssignOp::Assign, value))
+    }
+
+    /// Remembers the setters and dispatchers of `a, set_a = use_state(...)`
+    fn register_setters(&mut self, left: Node<'_>, right: Node<'_>) {
+        let is_state_hook = right.kind() == "call"
+            && right
+                .child_by_field_name("function")
+                .is_some_and(|function| {
+                    matches!(
+                        self.runtime.get(self.text(function)),
+                        Some(&"useState" | &"useReducer")
+                    )
+                });
+        if !is_state_hook
+            || !matches!(
+                left.kind(),
+                "pattern_list" | "tuple_pattern" | "list_pattern"
+            )
+        {
+            return;
+        }
+        if let Some(setter) = left.named_child(1).filter(|n| n.kind() == "identifier") {
+            self.setters.insert(self.text(setter).to_string());
+        }
+    }
+
+    fn augmented_assignment(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let left = node.child_by_field_name("left").unwrap();
+        let operator = node.child_by_field_name("operator").unwrap();
+        let right_node = node.child_by_field_name("right").unwrap();
+        let right = self.expression(right_node)?;
+        if !matches!(left.kind(), "identifier" | "attribute" | "subscript") {
+            return Err(self.unsupported(left, "This assignment target"));
+        }
+        let target = self.assign_target(left)?;
+        let shapes = (self.shape(left), self.shape(right_node));
+        if let Some(operator) = operator.kind().strip_suffix('=') {
+            let current = self.expression(left)?;
+            if let Some(value) = self.sequence_operation(operator, shapes, &current, &right) {
+                return Ok(assign(target, AssignOp::Assign, value));
+            }
+        }
+        let op = match operator.kind() {
+            "+=" => AssignOp::AddAssign,
+            "-=" => AssignOp::SubAssign,
+            "*=" => AssignOp::MulAssign,
+            "/=" => AssignOp::DivAssign,
+            "%=" => AssignOp::ModAssign,
+            "**=" => AssignOp::ExpAssign,
+            "&=" => AssignOp::BitAndAssign,
+            "|=" => AssignOp::BitOrAssign,
+            "^=" => AssignOp::BitXorAssign,
+            "<<=" => AssignOp::LShiftAssign,
+            ">>=" => AssignOp::RShiftAssign,
+            "//=" => {
+                let current = self.expression(left)?;
+                let value = floor_div(current, right);
+                return Ok(assign(target, AssignOp::Assign, value));
+            }
+            _ => return Err(self.unsupported(operator, format!("`{}`", self.text(operator)))),
+        };
+        Ok(assign(target, op, right))
+    }
+
+    fn if_statement(&mut self, node: Node<'_>) -> Translated<Stmt> {
+        let test = self.condition(node.child_by_field_name("condition").unwrap())?;
+        let cons = self.block_stmt(node.child_by_field_name("consequence").unwrap())?;
+        let mut cursor = node.walk();
+        let alternatives: Vec<Node<'_>> = node
+            .children_by_field_name("alternative", &mut cursor)
+            .collect();
+
+        // `elif` clauses nest as `else if`, from the last one
+        let mut alt: Option<Box<Stmt>> = None;
+        for clause in alternatives.iter().rev() {
+            alt = Some(match clause.kind() {
+                "else_clause" => self.block_stmt(clause.child_by_field_name("body").unwrap())?,
+                _ => {
+                    let test = self.condition(clause.child_by_field_name("condition").unwrap())?;
+                    let cons =
+                        self.block_stmt(clause.child_by_field_name("consequence").unwrap())?;
+                    Box::new(Stmt::If(IfStmt {
+                        span: DUMMY_SP,
+                        test,
+                        cons,
+                        alt,
+                    }))
+                }
+            });
+        }
+        Ok(Stmt::If(IfStmt {
+            span: DUMMY_SP,
+            test,
+            cons,
+            alt,
+        }))
+    }
+
+    fn for_statement(&mut self, node: Node<'_>) -> Translated<Stmt> {
+        if let Some(alternative) = node.child_by_field_name("alternative") {
+            return Err(self.unsupported(alternative, "`else` after a loop"));
+        }
+        let left = node.child_by_field_name("left").unwrap();
+        let right = self.expression(node.child_by_field_name("right").unwrap())?;
+        let names = pattern_names(left, &self.output.code);
+        let pat = self.pattern(left)?;
+        let scope = self.scopes.last().unwrap();
+        let head = if names.iter().all(|name| scope.loop_only.contains(name)) {
+            ForHead::VarDecl(var_decl(VarDeclKind::Const, pat, None))
+        } else {
+            ForHead::Pat(Box::new(pat))
+        };
+        let body = self.block_stmt(node.child_by_field_name("body").unwrap())?;
+        Ok(Stmt::ForOf(ForOfStmt {
+            span: DUMMY_SP,
+            is_await: false,
+            left: head,
+            right,
+            body,
+        }))
+    }
+
+    fn function_definition(&mut self, node: Node<'_>) -> Translated<Stmt> {
+        let name_node = node.child_by_field_name("name").unwrap();
+        let name = self.text(name_node);
+        self.check_binding(name_node, name)?;
+        if node.child(0).is_some_and(|first| first.kind() == "async") {
+            return Err(self.unsupported(node, "An `async` function"));
+        }
+        if let Some(type_parameters) = node.child_by_field_name("type_parameters") {
+            return Err(self.unsupported(type_parameters, "Type parameters"));
+        }
+
+        let (params, names) = self.parameters(node.child_by_field_name("parameters").unwrap())?;
+        let body = node.child_by_field_name("body").unwrap();
+        let mut statements = statements_of(body);
+        if statements.first().is_some_and(|first| is_docstring(*first)) {
+            statements.remove(0);
+        }
+
+        let scope = self.plan_scope(&statements, &names)?;
+        let hoisted = scope.hoisted.clone();
+        self.scopes.push(scope);
+        let mut stmts = Vec::new();
+        if !hoisted.is_empty() {
+            let decls = hoisted
+                .iter()
+                .map(|name| VarDeclarator {
+                    span: DUMMY_SP,
+                    name: binding(name),
+                    init: None,
+                    definite: false,
+                })
+                .collect();
+            stmts.push(Stmt::Decl(Decl::Var(Box::new(VarDecl {
+                kind: VarDeclKind::Let,
+                decls,
+                ..Default::default()
+            }))));
+        }
+        for statement in statements {
+            stmts.extend(self.statement(statement)?);
+        }
+        self.scopes.pop();
+
+        let function = Box::new(Function {
+            params: params
+                .into_iter()
+                .map(|pat| Param {
+                    span: DUMMY_SP,
+                    decorators: Vec::new(),
+                    pat,
+                })
+                .collect(),
+            body: Some(BlockStmt {
+                stmts,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        let scope = self.scope();
+        if scope.depth == 0 && !scope.declared.contains(name) {
+            scope.declared.insert(name.to_string());
+            return Ok(Stmt::Decl(Decl::Fn(FnDecl {
+                ident: ident(name),
+                declare: false,
+                function,
+            })));
+        }
+        let value = Box::new(Expr::Fn(FnExpr {
+            ident: Some(ident(name)),
+            function,
+        }));
+        Ok(expr_stmt(assign(
+            AssignTarget::from(ident(name)),
+            AssignOp::Assign,
+            value,
+        )))
+    }
+
+    /// The parameters of a function or lambda, and the names they bind
+    fn parameters(&mut self, node: Node<'_>) -> Translated<(Vec<Pat>, Vec<String>)> {
+        let mut params = Vec::new();
+        let mut names = Vec::new();
+        let mut cursor = node.walk();
+        for param in node.named_children(&mut cursor) {
+            let (name, default) = match param.kind() {
+                "identifier" => (param, None),
+                "typed_parameter" => (param.named_child(0).unwrap(), None),
+                "default_parameter" | "typed_default_parameter" => (
+                    param.child_by_field_name("name").unwrap(),
+                    param.child_by_field_name("value"),
+                ),
+                "list_splat_pattern" => (param, None),
+                "comment" => continue,
+                "dictionary_splat_pattern" => {
+                    return Err(self.unsupported(param, "A `**` parameter"))
+                }
+                _ => return Err(self.unsupported(param, "This parameter")),
+            };
+            let pat = match name.kind() {
+                "identifier" => {
+                    let text = self.text(name);
+                    self.check_binding(name, text)?;
+                    names.push(text.to_string());
+                    binding(text)
+                }
+                "list_splat_pattern" => {
+                    let inner = name.named_child(0).unwrap();
+                    let text = self.text(inner);
+                    self.check_binding(inner, text)?;
+                    names.push(text.to_string());
+                    Pat::Rest(RestPat {
+                        span: DUMMY_SP,
+                        dot3_token: DUMMY_SP,
+                        arg: Box::new(binding(text)),
+                        type_ann: None,
+                    })
+                }
+                "dictionary_splat_pattern" => {
+                    return Err(self.unsupported(name, "A `**` parameter"))
+                }
+                _ => return Err(self.unsupported(name, "This parameter")),
+            };
+            let pat = match default {
+                Some(value) => Pat::Assign(AssignPat {
+                    span: DUMMY_SP,
+                    left: Box::new(pat),
+                    right: self.expression(value)?,
+                }),
+                None => pat,
+            };
+            params.push(pat);
+        }
+        Ok((params, names))
+    }
+
+    /// A binding pattern: a name, or names unpacked from a list
+    fn pattern(&mut self, node: Node<'_>) -> Translated<Pat> {
+        match node.kind() {
+            "identifier" => {
+                let name = self.text(node);
+                self.check_binding(node, name)?;
+                Ok(binding(name))
+            }
+            "pattern_list" | "tuple_pattern" | "list_pattern" => {
+                let mut elems = Vec::new();
+                let mut cursor = node.walk();
+                for child in node.named_children(&mut cursor) {
+                    elems.push(Some(self.pattern(child)?));
+                }
+                Ok(Pat::Array(ArrayPat {
+                    span: DUMMY_SP,
+                    elems,
+                    optional: false,
+                    type_ann: None,
+                }))
+            }
+            "list_splat_pattern" => Ok(Pat::Rest(RestPat {
+                span: DUMMY_SP,
+                dot3_token: DUMMY_SP,
+                arg: Box::new(self.pattern(node.named_child(0).unwrap())?),
+                type_ann: None,
+            })),
+            "attribute" | "subscript" => Ok(Pat::Expr(self.expression(node)?)),
+            _ => Err(self.unsupported(node, "This assignment target")),
+        }
+    }
+
+    fn assign_target(&mut self, node: Node<'_>) -> Translated<AssignTarget> {
+        match node.kind() {
+            "identifier" => {
+                let name = self.text(node);
+                self.check_binding(node, name)?;
+                Ok(AssignTarget::from(ident(name)))
+            }
+            "attribute" | "subscript" => match *self.expression(node)? {
+                Expr::Member(member) => Ok(AssignTarget::from(member)),
+                _ => Err(self.unsupported(node, "This assignment target")),
+            },
+            _ => match self.pattern(node)? {
+                Pat::Array(array) => Ok(AssignTarget::from(array)),
+                _ => Err(self.unsupported(node, "This assignment target")),
+            },
+        }
+    }
+
+    // Expressions
+
+    fn expression(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let expr = match node.kind() {
+            "identifier" => return self.identifier(node),
+            "true" => Box::new(Expr::Lit(Lit::Bool(true.into()))),
+            "false" => Box::new(Expr::Lit(Lit::Bool(false.into()))),
+            "none" => Box::new(Expr::Lit(Lit::Null(Null { span: DUMMY_SP }))),
+            "integer" | "float" => return self.number(node),
+            "string" | "concatenated_string" => return self.string(node),
+            "parenthesized_expression" => {
+                let inner = node.named_child(0).unwrap();
+                if inner.kind() == "yield" {
+                    return Err(self.unsupported(inner, "`yield`"));
+                }
+                return self.expression(inner);
+            }
+            "list" | "tuple" | "expression_list" => array(self.elements(node)?),
+            "set" => Box::new(Expr::New(NewExpr {
+                callee: name_expr("Set"),
+                args: Some(vec![arg(array(self.elements(node)?))]),
+                ..Default::default()
+            })),
+            "dictionary" => self.dictionary(node, false)?,
+            "attribute" => return self.attribute(node),
+            "subscript" => return self.subscript(node),
+            "call" => return self.call(node),
+            "binary_operator" => return self.binary_operator(node),
+            "comparison_operator" => return self.comparison(node),
+            "boolean_operator" => return self.boolean_operator(node),
+            "not_operator" => {
+                let argument = self.condition(node.child_by_field_name("argument").unwrap())?;
+                unary(UnaryOp::Bang, argument)
+            }
+            "unary_operator" => {
+                let argument = self.expression(node.child_by_field_name("argument").unwrap())?;
+                let op = match node.child_by_field_name("operator").unwrap().kind() {
+                    "-" => UnaryOp::Minus,
+                    "+" => UnaryOp::Plus,
+                    _ => UnaryOp::Tilde,
+                };
+                unary(op, argument)
+            }
+            "conditional_expression" => {
+                let cons = self.expression(node.named_child(0).unwrap())?;
+                let test = self.condition(node.named_child(1).unwrap())?;
+                let alt = self.expression(node.named_child(2).unwrap())?;
+                Box::new(Expr::Cond(CondExpr {
+                    span: DUMMY_SP,
+                    test,
+                    cons,
+                    alt,
+                }))
+            }
+            "lambda" => return self.lambda(node),
+            "list_comprehension" | "generator_expression" => return self.comprehension(node),
+            kind => return Err(self.unsupported(node, describe(kind))),
+        };
+        Ok(expr)
+    }
+
+    fn identifier(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let name = self.text(node);
+        if let Some(export) = self.runtime.get(name).copied() {
+            return Ok(self.react_name(export));
+        }
+        self.check_binding(node, name)?;
+        Ok(name_expr(name))
+    }
+
+    fn number(&self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let text = self.text(node).replace('_', "").to_ascii_lowercase();
+        let value = if text.ends_with('j') {
+            None
+        } else if let Some(digits) = text.strip_prefix("0x") {
+            i64::from_str_radix(digits, 16)
+                .ok()
+                .map(|value| value as f64)
+        } else if let Some(digits) = text.strip_prefix("0o") {
+            i64::from_str_radix(digits, 8)
+                .ok()
+                .map(|value| value as f64)
+        } else if let Some(digits) = text.strip_prefix("0b") {
+            i64::from_str_radix(digits, 2)
+                .ok()
+                .map(|value| value as f64)
+        } else {
+            text.parse::<f64>().ok()
+        };
+        match value {
+            Some(value) => Ok(num_expr(value)),
+            None => Err(self.unsupported(node, format!("The number `{}`", self.text(node)))),
+        }
+    }
+
+    fn string(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let mut quasis = vec![String::new()];
+        let mut exprs = Vec::new();
+        let strings: Vec<Node<'_>> = if node.kind() == "concatenated_string" {
+            let mut cursor = node.walk();
+            node.named_children(&mut cursor)
+                .filter(|child| child.kind() == "string")
+                .collect()
+        } else {
+            vec![node]
+        };
+        for string in strings {
+            let mut cursor = string.walk();
+            let mut prefix = String::new();
+            for child in string.children(&mut cursor) {
+                match child.kind() {
+                    "string_start" => {
+                        prefix = self
+                            .text(child)
+                            .trim_end_matches(['"', '\''])
+                            .to_ascii_lowercase();
+                        if prefix.contains('b') {
+                            return Err(self.unsupported(string, "A bytes literal"));
+                        }
+                    }
+                    "string_content" => {
+                        let mut text = unescape(self.text(child), prefix.contains('r'));
+                        if prefix.contains('f') {
+                            text = text.replace("{{", "{").replace("}}", "}");
+                        }
+                        quasis.last_mut().unwrap().push_str(&text);
+                    }
+                    "interpolation" => {
+                        for field in ["format_specifier", "type_conversion"] {
+                            if let Some(format) = child.child_by_field_name(field) {
+                                return Err(self.unsupported(format, "A conversion in an f-string"));
+                            }
+                        }
+                        let expression = child.child_by_field_name("expression").unwrap();
+                        exprs.push(self.expression(expression)?);
+                        quasis.push(String::new());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if exprs.is_empty() {
+            return Ok(str_expr(&quasis[0]));
+        }
+        let count = quasis.len();
+        let quasis = quasis
+            .into_iter()
+            .enumerate()
+            .map(|(index, cooked)| TplElement {
+                span: DUMMY_SP,
+                tail: index + 1 == count,
+                raw: template_raw(&cooked).into(),
+                cooked: Some(cooked.as_str().into()),
+            })
+            .collect();
+        Ok(Box::new(Expr::Tpl(Tpl {
+            span: DUMMY_SP,
+            exprs,
+            quasis,
+        })))
+    }
+
+    /// The elements of a list, tuple or set, with `*` unpacking as spreads
+    fn elements(&mut self, node: Node<'_>) -> Translated<Vec<ExprOrSpread>> {
+        let mut elements = Vec::new();
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            elements.push(match child.kind() {
+                "comment" => continue,
+                "list_splat" => spread(self.expression(child.named_child(0).unwrap())?),
+                _ => arg(self.expression(child)?),
+            });
+        }
+        Ok(elements)
+    }
+
+    /// A dict as an object. The keys of the props of an HTML element are
+    /// renamed to the DOM property names React expects.
+    fn dictionary(&mut self, node: Node<'_>, dom_props: bool) -> Translated<Box<Expr>> {
+        let mut props = Vec::new();
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            match child.kind() {
+                "pair" => {
+                    let key_node = child.child_by_field_name("key").unwrap();
+                    let value = self.expression(child.child_by_field_name("value").unwrap())?;
+                    let key = match *self.expression(key_node)? {
+                        Expr::Lit(Lit::Str(key)) => {
+                            let key = key.value.to_string_lossy().to_string();
+                            let key = match key.as_str() {
+                                "class" if dom_props => "className".to_string(),
+                                "for" if dom_props => "htmlFor".to_string(),
+                                _ => key,
+                            };
+                            prop_name(&key)
+                        }
+                        Expr::Lit(Lit::Num(number)) => PropName::Num(number),
+                        expr => PropName::Computed(ComputedPropName {
+                            span: DUMMY_SP,
+                            expr: Box::new(expr),
+                        }),
+                    };
+                    props.push(PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                        key,
+                        value,
+                    }))));
+                }
+                "dictionary_splat" => {
+                    let expr = self.expression(child.named_child(0).unwrap())?;
+                    props.push(PropOrSpread::Spread(SpreadElement {
+                        dot3_token: DUMMY_SP,
+                        expr,
+                    }));
+                }
+                "comment" => {}
+                _ => return Err(self.unsupported(child, describe(child.kind()))),
+            }
+        }
+        Ok(Box::new(Expr::Object(ObjectLit {
+            span: DUMMY_SP,
+            props,
+        })))
+    }
+
+    fn attribute(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let object = node.child_by_field_name("object").unwrap();
+        let attribute = self.text(node.child_by_field_name("attribute").unwrap());
+        // `set_count.set` is the React setter itself
+        if object.kind() == "identifier"
+            && self.setters.contains(self.text(object))
+            && matches!(attribute, "set" | "dispatch")
+        {
+            return Ok(name_expr(self.text(object)));
+        }
+        Ok(member(self.expression(object)?, attribute))
+    }
+
+    fn subscript(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let value = self.expression(node.child_by_field_name("value").unwrap())?;
+        let mut cursor = node.walk();
+        let subscripts: Vec<Node<'_>> = node
+            .children_by_field_name("subscript", &mut cursor)
+            .collect();
+        if subscripts.len() != 1 {
+            return Err(self.unsupported(node, "A subscript with several indices"));
+        }
+        let subscript = subscripts[0];
+        if subscript.kind() == "slice" {
+            return self.slice(value, subscript);
+        }
+        // Negative indices count from the end, like `Array.prototype.at`
+        if subscript.kind() == "unary_operator"
+            && subscript.child_by_field_name("operator").unwrap().kind() == "-"
+        {
+            let index = self.expression(subscript)?;
+            return Ok(method(value, "at", vec![arg(index)]));
+        }
+        let index = self.expression(subscript)?;
+        Ok(index_expr(value, index))
+    }
+
+    fn slice(&mut self, value: Box<Expr>, slice: Node<'_>) -> Translated<Box<Expr>> {
+        let mut bounds: [Option<Node<'_>>; 3] = [None; 3];
+        let mut position = 0;
+        let mut cursor = slice.walk();
+        for child in slice.children(&mut cursor) {
+            if child.kind() == ":" {
+                position += 1;
+            } else if child.is_named() && position < 3 {
+                bounds[position] = Some(child);
+            }
+        }
+        if let Some(step) = bounds[2] {
+            return Err(self.unsupported(step, "A slice with a step"));
+        }
+        let mut args = Vec::new();
+        match bounds {
+            [None, None, _] => {}
+            [start, stop, _] => {
+                args.push(arg(match start {
+                    Some(start) => self.expression(start)?,
+                    None => num_expr(0.0),
+                }));
+                if let Some(stop) = stop {
+                    args.push(arg(self.expression(stop)?));
+                }
+            }
+        }
+        Ok(method(value, "slice", args))
+    }
+
+    /// `a and b` and `a or b`, which give an operand rather than a bool
+    fn boolean_operator(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let left_node = node.child_by_field_name("left").unwrap();
+        let left = self.expression(left_node)?;
+        let right = self.expression(node.child_by_field_name("right").unwrap())?;
+        let is_and = node.child_by_field_name("operator").unwrap().kind() == "and";
+        if self.shape(left_node).is_some_and(Shape::is_primitive) {
+            let op = if is_and {
+                BinaryOp::LogicalAnd
+            } else {
+                BinaryOp::LogicalOr
+            };
+            return Ok(binary(op, left, right));
+        }
+        let name = if is_and { "__and" } else { "__or" };
+        Ok(self.helper(name, vec![arg(left), arg(arrow(Vec::new(), right))]))
+    }
+
+    /// An expression tested for its truthiness
+    fn condition(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let expr = self.expression(node)?;
+        if self.shape(node).is_some_and(Shape::is_primitive) {
+            return Ok(expr);
+        }
+        Ok(self.helper("__truthy", vec![arg(expr)]))
+    }
+
+    fn binary_operator(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let operator = node.child_by_field_name("operator").unwrap();
+        let (left_node, right_node) = (
+            node.child_by_field_name("left").unwrap(),
+            node.child_by_field_name("right").unwrap(),
+        );
+        let shapes = (self.shape(left_node), self.shape(right_node));
+        if operator.kind() == "%" && shapes.0 == Some(Shape::Str) {
+            return Err(self
+                .unsupported(operator, "`%` formatting")
+                .with_help("Use an f-string instead"));
+        }
+        let left = self.expression(left_node)?;
+        let right = self.expression(right_node)?;
+        if let Some(expr) = self.sequence_operation(operator.kind(), shapes, &left, &right) {
+            return Ok(expr);
+        }
+        let op = match operator.kind() {
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            "*" => BinaryOp::Mul,
+            "/" => BinaryOp::Div,
+            "%" => BinaryOp::Mod,
+            "**" => BinaryOp::Exp,
+            "&" => BinaryOp::BitAnd,
+            "|" => BinaryOp::BitOr,
+            "^" => BinaryOp::BitXor,
+            "<<" => BinaryOp::LShift,
+            ">>" => BinaryOp::RShift,
+            "//" => return Ok(floor_div(left, right)),
+            _ => return Err(self.unsupported(operator, format!("`{}`", self.text(operator)))),
+        };
+        Ok(binary(op, left, right))
+    }
+
+    /// `left + right` or `left * right`, when the operands may be lists or
+    /// strings that JavaScript's `+` and `*` would not concatenate or repeat
+    fn sequence_operation(
+        &mut self,
+        operator: &str,
+        shapes: (Option<Shape>, Option<Shape>),
+        left: &Expr,
+        right: &Expr,
+    ) -> Option<Box<Expr>> {
+        let (left, right) = (Box::new(left.clone()), Box::new(right.clone()));
+        match operator {
+            "+" if matches!(shapes, (Some(Shape::List), _) | (_, Some(Shape::List))) => {
+                Some(array(vec![spread(left), spread(right)]))
+            }
+            "+" if !is_add(shapes) => Some(self.helper("__add", vec![arg(left), arg(right)])),
+            "*" if !is_mul(shapes) => Some(self.helper("__mul", vec![arg(left), arg(right)])),
+            _ => None,
+        }
+    }
+
+    fn comparison(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let mut cursor = node.walk();
+        let operands: Vec<Node<'_>> = node.named_children(&mut cursor).collect();
+        let mut cursor = node.walk();
+        let operators: Vec<Node<'_>> = node
+            .children_by_field_name("operators", &mut cursor)
+            .collect();
+        // `a < b < c` compares `b` twice, which only a plain operand allows
+        for middle in &operands[1..operands.len() - 1] {
+            if !matches!(
+                middle.kind(),
+                "identifier" | "attribute" | "integer" | "float" | "string"
+            ) {
+                return Err(self.unsupported(*middle, "A chained comparison of this operand"));
+            }
+        }
+
+        let mut result: Option<Box<Expr>> = None;
+        for (index, operator) in operators.iter().enumerate() {
+            let (left_node, right_node) = (operands[index], operands[index + 1]);
+            let left = self.expression(left_node)?;
+            let right = self.expression(right_node)?;
+            // `None` compares loosely, so that it also matches `undefined`
+            let with_none = left_node.kind() == "none" || right_node.kind() == "none";
+            let primitive = [left_node, right_node]
+                .into_iter()
+                .any(|operand| self.shape(operand).is_some_and(Shape::is_primitive));
+            let container = self.shape(right_node);
+            let comparison = match operator.kind() {
+                "<" => binary(BinaryOp::Lt, left, right),
+                "<=" => binary(BinaryOp::LtEq, left, right),
+                ">" => binary(BinaryOp::Gt, left, right),
+                ">=" => binary(BinaryOp::GtEq, left, right),
+                "==" | "is" if with_none => binary(BinaryOp::EqEq, left, right),
+                "!=" | "<>" | "is not" if with_none => binary(BinaryOp::NotEq, left, right),
+                "==" if !primitive => self.helper("__eq", vec![arg(left), arg(right)]),
+                "!=" | "<>" if !primitive => unary(
+                    UnaryOp::Bang,
+                    self.helper("__eq", vec![arg(left), arg(right)]),
+                ),
+                "==" | "is" => binary(BinaryOp::EqEqEq, left, right),
+                "!=" | "<>" | "is not" => binary(BinaryOp::NotEqEq, left, right),
+                "in" | "not in" => {
+                    let contains = match container {
+                        Some(Shape::List | Shape::Str) => {
+                            method(right, "includes", vec![arg(left)])
+                        }
+                        _ => self.helper("__contains", vec![arg(right), arg(left)]),
+                    };
+                    match operator.kind() {
+                        "in" => contains,
+                        _ => unary(UnaryOp::Bang, contains),
+                    }
+                }
+                _ => return Err(self.unsupported(*operator, format!("`{}`", self.text(*operator)))),
+            };
+            result = Some(match result {
+                Some(previous) => binary(BinaryOp::LogicalAnd, previous, comparison),
+                None => comparison,
+            });
+        }
+        Ok(result.expect("a comparison has an operator"))
+    }
+
+    /// What `node` evaluates to, when its syntax tells
+    fn shape(&self, node: Node<'_>) -> Option<Shape> {
+        let field = |name: &str| node.child_by_field_name(name);
+        match node.kind() {
+            "parenthesized_expression" => self.shape(node.named_child(0)?),
+            "true" | "false" | "comparison_operator" | "not_operator" => Some(Shape::Bool),
+            "integer" | "float" => Some(Shape::Number),
+            "string" | "concatenated_string" => Some(Shape::Str),
+            "list" | "tuple" | "list_comprehension" => Some(Shape::List),
+            "dictionary" => Some(Shape::Dict),
+            "boolean_operator" => {
+                let left = self.shape(field("left")?)?;
+                (self.shape(field("right")?)? == left).then_some(left)
+            }
+            "conditional_expression" => {
+                let cons = self.shape(node.named_child(0)?)?;
+                (self.shape(node.named_child(2)?)? == cons).then_some(cons)
+            }
+            "unary_operator" => match self.shape(field("argument")?)? {
+                Shape::Number | Shape::Bool => Some(Shape::Number),
+                _ => None,
+            },
+            "binary_operator" => {
+                let shapes = (self.shape(field("left")?), self.shape(field("right")?));
+                match field("operator")?.kind() {
+                    "+" | "*" => match shapes {
+                        (Some(Shape::Str), _) | (_, Some(Shape::Str)) => Some(Shape::Str),
+                        (Some(Shape::List), _) | (_, Some(Shape::List)) => Some(Shape::List),
+                        (Some(Shape::Number), Some(Shape::Number)) => Some(Shape::Number),
+                        (Some(Shape::Number), _) | (_, Some(Shape::Number))
+                            if field("operator")?.kind() == "+" =>
+                        {
+                            Some(Shape::Number)
+                        }
+                        _ => None,
+                    },
+                    "-" | "/" | "//" | "**" => Some(Shape::Number),
+                    "%" => shapes.0.filter(|shape| *shape == Shape::Number),
+                    _ => None,
+                }
+            }
+            "call" => {
+                let function = field("function")?;
+                let name = self.text(function);
+                if function.kind() != "identifier" || self.is_bound(name) {
+                    return None;
+                }
+                match name {
+                    "len" | "int" | "float" | "abs" | "round" | "sum" => Some(Shape::Number),
+                    "str" => Some(Shape::Str),
+                    "bool" => Some(Shape::Bool),
+                    "list" => Some(Shape::List),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn lambda(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let (params, names) = match node.child_by_field_name("parameters") {
+            Some(parameters) => self.parameters(parameters)?,
+            None => (Vec::new(), Vec::new()),
+        };
+        self.scopes.push(Scope::with_params(&names));
+        let body = self.expression(node.child_by_field_name("body").unwrap());
+        self.scopes.pop();
+        Ok(arrow(params, body?))
+    }
+
+    /// `[body for target in iterable if condition]` as
+    /// `iterable.filter((target) => condition).map((target) => body)`
+    fn comprehension(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let body = node.child_by_field_name("body").unwrap();
+        let mut cursor = node.walk();
+        let clauses: Vec<Node<'_>> = node
+            .named_children(&mut cursor)
+            .filter(|child| matches!(child.kind(), "for_in_clause" | "if_clause"))
+            .collect();
+        let for_clauses = clauses
+            .iter()
+            .filter(|clause| clause.kind() == "for_in_clause")
+            .count();
+        if for_clauses != 1 || clauses[0].kind() != "for_in_clause" {
+            return Err(self.unsupported(node, "A comprehension with several `for` clauses"));
+        }
+        let left = clauses[0].child_by_field_name("left").unwrap();
+        let right = clauses[0].child_by_field_name("right").unwrap();
+        let result = self.expression(right)?;
+
+        let names = pattern_names(left, &self.output.code);
+        self.scopes.push(Scope::with_params(&names));
+        let translated = self.comprehension_calls(result, left, &clauses[1..], body);
+        self.scopes.pop();
+        translated
+    }
+
+    /// The `filter` and `map` calls of a comprehension over `iterable`
+    fn comprehension_calls(
+        &mut self,
+        mut iterable: Box<Expr>,
+        target: Node<'_>,
+        conditions: &[Node<'_>],
+        body: Node<'_>,
+    ) -> Translated<Box<Expr>> {
+        let param = self.pattern(target)?;
+        for condition in conditions {
+            let condition = self.condition(condition.named_child(0).unwrap())?;
+            let filter = arrow(vec![param.clone()], condition);
+            iterable = method(iterable, "filter", vec![arg(filter)]);
+        }
+        if conditions.is_empty() || self.text(body) != self.text(target) {
+            let body = self.expression(body)?;
+            iterable = method(iterable, "map", vec![arg(arrow(vec![param], body))]);
+        }
+        Ok(iterable)
+    }
+
+    fn call(&mut self, node: Node<'_>) -> Translated<Box<Expr>> {
+        let function = node.child_by_field_name("function").unwrap();
+        let arguments = node.child_by_field_name("arguments").unwrap();
+
+        let mut args = Vec::new();
+        let mut keywords = Vec::new();
+        if arguments.kind() == "generator_expression" {
+            args.push(arg(self.comprehension(arguments)?));
+        } else {
+            let mut cursor = arguments.walk();
+            for argument in arguments.named_children(&mut cursor) {
+                match argument.kind() {
+                    "keyword_argument" => keywords.push(argument),
+                    "list_splat" => {
+                        args.push(spread(self.expression(argument.named_child(0).unwrap())?))
+                    }
+                    "dictionary_splat" => return Err(self.unsupported(argument, "A `**` argument")),
+                    "comment" => {}
+                    _ => args.push(arg(self.expression(argument)?)),
+                }
+            }
+        }
+
+        if function.kind() == "identifier" {
+            let name = self.text(function);
+            if let Some(export) = self.runtime.get(name).copied() {
+                // The hook ids the Python runtime needs mean nothing to React
+                keywords.retain(|keyword| {
+                    keyword
+                        .child_by_field_name("name")
+                        .is_none_or(|name| self.text(name) != "hook_id")
+                });
+                self.no_keywords(&keywords)?;
+                return self.runtime_call(node, arguments, export, args);
+            }
+            self.no_keywords(&keywords)?;
+            if !self.is_bound(name) {
+                if let Some(expr) = self.builtin(node, name, &mut args)? {
+                    return Ok(expr);
+                }
+            }
+        }
+        self.no_keywords(&keywords)?;
+
+        if function.kind() == "attribute" {
+            let object = function.child_by_field_name("object").unwrap();
+            let name = self.text(function.child_by_field_name("attribute").unwrap());
+            let is_setter =
+                object.kind() == "identifier" && self.setters.contains(self.text(object));
+            if !is_setter {
+                let object = self.expression(object)?;
+                return self.method_call(node, object, name, args);
+            }
+        }
+        let callee = self.expression(function)?;
+        Ok(call(callee, args))
+    }
+
+    fn no_keywords(&self, keywords: &[Node<'_>]) -> Translated<()> {
+        match keywords.first() {
+            Some(keyword) => Err(self
+                .unsupported(*keyword, "A keyword argument")
+                .with_help("Pass the value as a positional argument")),
+            None => Ok(()),
+        }
+    }
+
+    fn runtime_call(
+        &mut self,
+        node: Node<'_>,
+        arguments: Node<'_>,
+        export: &'static str,
+        mut args: Vec<ExprOrSpread>,
+    ) -> Translated<Box<Expr>> {
+        let arities: &[usize] = match export {
+            "useState" => &[1],
+            "useReducer" => &[2],
+            "useEffect" | "lazy" => &[1, 2],
+            "createElement" => &[3],
+            _ => &[],
+        };
+        if !arities.contains(&args.len()) || args.iter().any(|arg| arg.spread.is_some()) {
+            return Err(self.unsupported(
+                node,
+                format!(
+                    "This call of `{}`",
+                    self.text(node.child_by_field_name("function").unwrap())
+                ),
+            ));
+        }
+        let callee = self.react_name(export);
+        if export == "lazy" {
+            return self.lazy_call(node, callee, args);
+        }
+        if export != "createElement" {
+            return Ok(call(callee, args));
+        }
+
+        // `create_element(tag, props, [children])`, with the children as
+        // arguments, as React takes them
+        let mut cursor = arguments.walk();
+        let nodes: Vec<Node<'_>> = arguments
+            .named_children(&mut cursor)
+            .filter(|child| child.kind() != "comment")
+            .collect();
+        let children = args.pop().unwrap();
+        args.pop();
+        let tag = args.pop().unwrap();
+
+        let tag_name = match &*tag.expr {
+            Expr::Lit(Lit::Str(tag)) => Some(tag.value.to_string_lossy().to_string()),
+            _ => None,
+        };
+        let tag = match tag_name.as_deref() {
+            Some(FRAGMENT_TAG) => self.react_name("Fragment"),
+            _ => tag.expr,
+        };
+        let props = match nodes[1].kind() {
+            "dictionary" if nodes[1].named_child_count() == 0 => {
+                Box::new(Expr::Lit(Lit::Null(Null { span: DUMMY_SP })))
+            }
+            "dictionary" => self.dictionary(nodes[1], tag_name.is_some())?,
+            _ => self.expression(nodes[1])?,
+        };
+        let mut args = vec![arg(tag), arg(props)];
+        match *children.expr {
+            Expr::Array(children) => args.extend(children.elems.into_iter().flatten()),
+            children => args.push(spread(Box::new(children))),
+        }
+        Ok(call(callee, args))
+    }
+
+    /// `lazy(module, name)`, which imports the component `name` of the
+    /// project module `module` (`pages.Admin`) the first time it renders
+    fn lazy_call(
+        &self,
+        node: Node<'_>,
+        callee: Box<Expr>,
+        args: Vec<ExprOrSpread>,
+    ) -> Translated<Box<Expr>> {
+        let strings: Vec<String> = args
+            .iter()
+            .filter_map(|arg| match &*arg.expr {
+                Expr::Lit(Lit::Str(value)) => Some(value.value.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        if strings.len() != args.len() {
+            return Err(self
+                .unsupported(node, "A `lazy` call with computed arguments")
+                .with_help("Name the module and the component with string literals"));
+        }
+        let module = &strings[0];
+        if !self.is_module(module) {
+            return Err(self.python_import(node, module));
+        }
+        let name = strings
+            .get(1)
+            .map(String::as_str)
+            .unwrap_or_else(|| module.rsplit('.').next().unwrap());
+
+        // `lazy(() => import("./pages/Admin.mjs").then((module) => ({ default: module.Admin })))`
+        let import = Box::new(Expr::Call(CallExpr {
+            callee: Callee::Import(Import {
+                span: DUMMY_SP,
+                phase: ImportPhase::default(),
+            }),
+            args: vec![arg(str_expr(&module_specifier(self.module, module)))],
+            ..Default::default()
+        }));
+        let component = Box::new(Expr::Object(ObjectLit {
+            span: DUMMY_SP,
+            props: vec![PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                key: prop_name("default"),
+                value: member(name_expr("module"), name),
+            })))],
+        }));
+        let loaded = method(
+            import,
+            "then",
+            vec![arg(arrow(vec![binding("module")], component))],
+        );
+        Ok(call(callee, vec![arg(arrow(Vec::new(), loaded))]))
+    }
+
+    /// The builtin `name` called with `args`, or `None` when it has no
+    /// translation
+    fn builtin(
+        &self,
+        node: Node<'_>,
+        name: &str,
+        args: &mut Vec<ExprOrSpread>,
+    ) -> Translated<Option<Box<Expr>>> {
+        let count = args.len();
+        let arity = |expected: &[usize]| expected.contains(&count);
+        let take = |args: &mut Vec<ExprOrSpread>| args.remove(0).expr;
+        let expr = match name {
+            "print" => call(member(name_expr("console"), "log"), std::mem::take(args)),
+            "len" if arity(&[1]) => member(take(args), "length"),
+            "str" if arity(&[1]) => call(name_expr("String"), std::mem::take(args)),
+            "float" if arity(&[1]) => call(name_expr("Number"), std::mem::take(args)),
+            "bool" if arity(&[1]) => call(name_expr("Boolean"), std::mem::take(args)),
+            "int" if arity(&[1]) => call(
+                member(name_expr("Math"), "trunc"),
+                vec![arg(call(name_expr("Number"), std::mem::take(args)))],
+            ),
+            "abs" | "round" if arity(&[1]) => {
+                call(member(name_expr("Math"), name), std::mem::take(args))
+            }
+            "min" | "max" if arity(&[1]) => {
+                let values = take(args);
+                call(member(name_expr("Math"), name), vec![spread(values)])
+            }
+            "min" | "max" if count > 1 => {
+                call(member(name_expr("Math"), name), std::mem::take(args))
+            }
+            "list" if arity(&[0]) => array(Vec::new()),
+            "list" if arity(&[1]) => call(member(name_expr("Array"), "from"), std::mem::take(args)),
+            "sum" if arity(&[1]) => {
+                let values = take(args);
+                let add = arrow(
+                    vec![binding("total"), binding("value")],
+                    binary(BinaryOp::Add, name_expr("total"), name_expr("value")),
+                );
+                method(values, "reduce", vec![arg(add), arg(num_expr(0.0))])
+            }
+            "enumerate" if arity(&[1]) => {
+                let values = take(args);
+                let pair = arrow(
+                    vec![binding("item"), binding("index")],
+                    array(vec![arg(name_expr("index")), arg(name_expr("item"))]),
+                );
+                method(values, "map", vec![arg(pair)])
+            }
+            "range" if arity(&[1, 2]) => {
+                let (start, stop) = match count {
+                    1 => (None, take(args)),
+                    _ => (Some(take(args)), take(args)),
+                };
+                let length = match &start {
+                    Some(start) => binary(BinaryOp::Sub, stop, start.clone()),
+                    None => stop,
+                };
+                let value = match start {
+                    Some(start) => binary(BinaryOp::Add, start, name_expr("index")),
+                    None => name_expr("index"),
+                };
+                let options = Box::new(Expr::Object(ObjectLit {
+                    span: DUMMY_SP,
+                    props: vec![PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                        key: prop_name("length"),
+                        value: length,
+                    })))],
+                }));
+                call(
+                    member(name_expr("Array"), "from"),
+                    vec![
+                        arg(options),
+                        arg(arrow(vec![binding("_"), binding("index")], value)),
+                    ],
+                )
+            }
+            "len" | "str" | "float" | "bool" | "int" | "abs" | "round" | "min" | "max" | "list"
+            | "sum" | "enumerate" | "range" => {
+                return Err(self.unsupported(node, format!("This call of `{}`", name)))
+            }
+            "dict" | "set" | "tuple" | "sorted" | "reversed" | "zip" | "map" | "filter"
+            | "isinstance" | "type" | "getattr" | "setattr" | "hasattr" | "open" | "input"
+            | "eval" | "exec" | "super" | "object" | "any" | "all" => {
+                return Err(self.unsupported(node, format!("The builtin `{}`", name)))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(expr))
+    }
+
+    /// `object.name(args)`, with the str, list and dict methods components
+    /// use translated to their JavaScript equivalents
+    fn method_call(
+        &mut self,
+        node: Node<'_>,
+        object: Box<Expr>,
+        name: &str,
+        mut args: Vec<ExprOrSpread>,
+    ) -> Translated<Box<Expr>> {
+        let expr = match (name, args.len()) {
+            ("get", 1) => index_expr(object, args.remove(0).expr),
+            ("get", 2) => {
+                let default = args.pop().unwrap().expr;
+                let value = index_expr(object, args.pop().unwrap().expr);
+                binary(BinaryOp::NullishCoalescing, value, default)
+            }
+            ("append", 1) => method(object, "push", args),
+            ("join", 1) => method(args.remove(0).expr, "join", vec![arg(object)]),
+            ("upper", 0) => method(object, "toUpperCase", args),
+            ("lower", 0) => method(object, "toLowerCase", args),
+            ("strip", 0) => method(object, "trim", args),
+            ("lstrip", 0) => method(object, "trimStart", args),
+            ("rstrip", 0) => method(object, "trimEnd", args),
+            ("startswith", 1) => method(object, "startsWith", args),
+            ("endswith", 1) => method(object, "endsWith", args),
+            ("replace", 2) => method(object, "replaceAll", args),
+            ("items", 0) => call(member(name_expr("Object"), "entries"), vec![arg(object)]),
+            ("keys", 0) => call(member(name_expr("Object"), "keys"), vec![arg(object)]),
+            ("values", 0) => call(member(name_expr("Object"), "values"), vec![arg(object)]),
+            ("format", _) => {
+                return Err(self
+                    .unsupported(node, "`str.format`")
+                    .with_help("Use an f-string instead"))
+            }
+            _ => method(object, name, args),
+        };
+        Ok(expr)
+    }
+}
+
+/// Whether JavaScript's `+` adds operands of these shapes as Python does:
+/// numbers, or strings
+fn is_add(shapes: (Option<Shape>, Option<Shape>)) -> bool {
+    matches!(
+        shapes,
+        (Some(Shape::Number | Shape::Str), _) | (_, Some(Shape::Number | Shape::Str))
+    )
+}
+
+/// Whether JavaScript's `*` multiplies operands of these shapes as Python
+/// does: two numbers
+fn is_mul(shapes: (Option<Shape>, Option<Shape>)) -> bool {
+    shapes == (Some(Shape::Number), Some(Shape::Number))
+}
+
+/// The declarations of `names` and of the helpers they call, in the order of
+/// [`HELPERS`]
+fn helper_items(names: &BTreeSet<&'static str>) -> Vec<ModuleItem> {
+    let mut needed = names.clone();
+    for (name, calls, _) in HELPERS {
+        if names.contains(name) {
+            needed.extend(calls.iter().copied());
+        }
+    }
+    let code: String = HELPERS
+        .iter()
+        .filter(|(name, _, _)| needed.contains(name))
+        .map(|(_, _, code)| *code)
+        .collect();
+    if code.is_empty() {
+        return Vec::new();
+    }
+    let cm: Lrc<SourceMap> = Default::default();
+    let fm = cm.new_source_file(Lrc::new(FileName::Anon), code);
+    let lexer = Lexer::new(
+        Syntax::Es(EsSyntax::default()),
+        EsVersion::Es2022,
+        StringInput::from(&*fm),
+        None,
+    );
+    EsParser::new_from(lexer)
+        .parse_module()
+        .expect("the helpers are valid JavaScript")
+        .body
+}
+
+/// The statements of a module or block, without comments
+fn statements_of(node: Node<'_>) -> Vec<Node<'_>> {
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor)
+        .filter(|child| child.kind() != "comment")
+        .collect()
+}
+
+fn is_docstring(statement: Node<'_>) -> bool {
+    statement.kind() == "expression_statement"
+        && statement.named_child_count() == 1
+        && statement
+            .named_child(0)
+            .is_some_and(|child| child.kind() == "string")
+}
+
+/// The names an assignment statement at the top of a scope may declare, or
+/// `None` for other statements
+fn declaration_targets(statement: Node<'_>, code: &str) -> Option<Vec<String>> {
+    let assignment = statement
+        .named_child(0)
+        .filter(|_| statement.kind() == "expression_statement")
+        .filter(|child| child.kind() == "assignment")?;
+    let left = assignment.child_by_field_name("left")?;
+    let right = assignment.child_by_field_name("right")?;
+    if right.kind() == "assignment" || !is_binding_pattern(left) {
+        return None;
+    }
+    Some(pattern_names(left, code))
+}
+
+/// Whether `node` only binds names, so that it can be declared
+fn is_binding_pattern(node: Node<'_>) -> bool {
+    match node.kind() {
+        "identifier" => true,
+        "pattern_list" | "tuple_pattern" | "list_pattern" | "list_splat_pattern" => {
+            let mut cursor = node.walk();
+            let all = node.named_children(&mut cursor).all(is_binding_pattern);
+            all
+        }
+        _ => false,
+    }
+}
+
+/// The names a pattern binds, in order
+fn pattern_names(node: Node<'_>, code: &str) -> Vec<String> {
+    fn collect(node: Node<'_>, code: &str, names: &mut Vec<String>) {
+        match node.kind() {
+            "identifier" => names.push(code[node.byte_range()].to_string()),
+            "pattern_list" | "tuple_pattern" | "list_pattern" | "list_splat_pattern" => {
+                let mut cursor = node.walk();
+                for child in node.named_children(&mut cursor) {
+                    collect(child, code, names);
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut names = Vec::new();
+    collect(node, code, &mut names);
+    names
+}
+
+/// Plain words for the Python constructs the node target rejects
+fn describe(kind: &str) -> String {
+    let description = match kind {
+        "class_definition" => "A class",
+        "decorated_definition" => "A decorator",
+        "try_statement" => "A `try` statement",
+        "with_statement" => "A `with` statement",
+        "raise_statement" => "A `raise` statement",
+        "assert_statement" => "An `assert` statement",
+        "delete_statement" => "A `del` statement",
+        "match_statement" => "A `match` statement",
+        "type_alias_statement" => "A `type` statement",
+        "exec_statement" | "print_statement" => "A Python 2 statement",
+        "named_expression" => "An assignment expression (`:=`)",
+        "dictionary_comprehension" => "A dict comprehension",
+        "set_comprehension" => "A set comprehension",
+        "await" => "`await`",
+        "yield" => "`yield`",
+        "ellipsis" => "`...`",
+        "keyword_argument" => "A keyword argument",
+        "list_splat" | "dictionary_splat" => "Unpacking here",
+        _ => return format!("This `{}`", kind.replace('_', " ")),
+    };
+    description.to_string()
+}
+
+/// Decodes the escape sequences of the content of a Python string
+fn unescape(text: &str, raw: bool) -> String {
+    if raw || !text.contains('\\') {
+        return text.to_string();
+    }
+    let mut value = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let Some(escaped) = chars.next() else {
+            value.push('\\');
+            break;
+        };
+        let hex = |chars: &mut std::iter::Peekable<std::str::Chars<'_>>, digits: usize| {
+            let code: String = (0..digits).filter_map(|_| chars.next()).collect();
+            u32::from_str_radix(&code, 16).ok().and_then(char::from_u32)
+        };
+        match escaped {
+            '\n' => {}
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            'a' => value.push('\x07'),
+            'b' => value.push('\x08'),
+            'f' => value.push('\x0c'),
+            'v' => value.push('\x0b'),
+            '0' => value.push('\0'),
+            'x' => value.extend(hex(&mut chars, 2)),
+            'u' => value.extend(hex(&mut chars, 4)),
+            'U' => value.extend(hex(&mut chars, 8)),
+            '\\' | '\'' | '"' => value.push(escaped),
+            other => {
+                value.push('\\');
+                value.push(other);
+            }
+        }
+    }
+    value
+}
+
+/// The raw text of a template literal part with the value `cooked`
+fn template_raw(cooked: &str) -> String {
+    let mut raw = String::with_capacity(cooked.len());
+    let mut chars = cooked.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => raw.push_str("\\\\"),
+            '`' => raw.push_str("\\`"),
+            '$' if chars.peek() == Some(&'{') => raw.push_str("\\$"),
+            '\r' => raw.push_str("\\r"),
+            c => raw.push(c),
+        }
+    }
+    raw
+}
+
+fn ident(name: &str) -> Ident {
+    Ident::new_no_ctxt(name.into(), DUMMY_SP)
+}
+
+fn name_expr(name: &str) -> Box<Expr> {
+    Box::new(Expr::Ident(ident(name)))
+}
+
+fn binding(name: &str) -> Pat {
+    Pat::Ident(BindingIdent::from(ident(name)))
+}
+
+fn str_expr(value: &str) -> Box<Expr> {
+    Box::new(Expr::Lit(Lit::Str(value.into())))
+}
+
+fn num_expr(value: f64) -> Box<Expr> {
+    Box::new(Expr::Lit(Lit::Num(Number {
+        span: DUMMY_SP,
+        value,
+        raw: None,
+    })))
+}
+
+/// An object key, as an identifier where the name is one
+fn prop_name(key: &str) -> PropName {
+    let mut chars = key.chars();
+    let is_ident = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_ident {
+        PropName::Ident(IdentName::new(key.into(), DUMMY_SP))
+    } else {
+        PropName::Str(key.into())
+    }
+}
+
+fn arg(expr: Box<Expr>) -> ExprOrSpread {
+    ExprOrSpread { spread: None, expr }
+}
+
+fn spread(expr: Box<Expr>) -> ExprOrSpread {
+    ExprOrSpread {
+        spread: Some(DUMMY_SP),
+        expr,
+    }
+}
+
+fn array(elements: Vec<ExprOrSpread>) -> Box<Expr> {
+    Box::new(Expr::Array(ArrayLit {
+        span: DUMMY_SP,
+        elems: elements.into_iter().map(Some).collect(),
+    }))
+}
+
+fn call(callee: Box<Expr>, args: Vec<ExprOrSpread>) -> Box<Expr> {
+    Box::new(Expr::Call(CallExpr {
+        callee: Callee::Expr(callee),
+        args,
+        ..Default::default()
+    }))
+}
+
+fn member(object: Box<Expr>, name: &str) -> Box<Expr> {
+    Box::new(Expr::Member(MemberExpr {
+        span: DUMMY_SP,
+        obj: object,
+        prop: MemberProp::Ident(IdentName::new(name.into(), DUMMY_SP)),
+    }))
+}
+
+fn method(object: Box<Expr>, name: &str, args: Vec<ExprOrSpread>) -> Box<Expr> {
+    call(member(object, name), args)
+}
+
+/// `object[index]`, or `object.name` when the index is a string that is a
+/// valid identifier
+fn index_expr(object: Box<Expr>, index: Box<Expr>) -> Box<Expr> {
+    if let Expr::Lit(Lit::Str(key)) = &*index {
+        if let PropName::Ident(name) = prop_name(&key.value.to_string_lossy()) {
+            return member(object, &name.sym);
+        }
+    }
+    Box::new(Expr::Member(MemberExpr {
+        span: DUMMY_SP,
+        obj: object,
+        prop: MemberProp::Computed(ComputedPropName {
+            span: DUMMY_SP,
+            expr: index,
+        }),
+    }))
+}
+
+fn binary(op: BinaryOp, left: Box<Expr>, right: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Bin(BinExpr {
+        span: DUMMY_SP,
+        op,
+        left,
+        right,
+    }))
+}
+
+fn unary(op: UnaryOp, arg: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Unary(UnaryExpr {
+        span: DUMMY_SP,
+        op,
+        arg,
+    }))
+}
+
+/// Python's `a // b`, which rounds down
+fn floor_div(left: Box<Expr>, right: Box<Expr>) -> Box<Expr> {
+    call(
+        member(name_expr("Math"), "floor"),
+        vec![arg(binary(BinaryOp::Div, left, right))],
+    )
+}
+
+fn arrow(params: Vec<Pat>, body: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Arrow(ArrowExpr {
+        params,
+        body: Box::new(BlockStmtOrExpr::Expr(body)),
+        ..Default::default()
+    }))
+}
+
+fn assign(target: AssignTarget, op: AssignOp, value: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Assign(AssignExpr {
+        span: DUMMY_SP,
+        op,
+        left: target,
+        right: value,
+    }))
+}
+
+fn expr_stmt(expr: Box<Expr>) -> Stmt {
+    Stmt::Expr(ExprStmt {
+        span: DUMMY_SP,
+        expr,
+    })
+}
+
+fn var_decl(kind: VarDeclKind, name: Pat, init: Option<Box<Expr>>) -> Box<VarDecl> {
+    Box::new(VarDecl {
+        kind,
+        decls: vec![VarDeclarator {
+            span: DUMMY_SP,
+            name,
+            init,
+            definite: false,
+        }],
+        ..Default::default()
+    })
+}
+
+fn export(decl: Decl) -> ModuleItem {
+    ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+        span: DUMMY_SP,
+        decl,
+    }))
+}
+
+fn import_decl(specifiers: Vec<ImportSpecifier>, source: &str) -> ModuleItem {
+    ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+        span: DUMMY_SP,
+        specifiers,
+        src: Box::new(source.into()),
+        type_only: false,
+        with: None,
+        phase: Default::default(),
+    }))
+}
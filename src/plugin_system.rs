@@ -4,6 +4,7 @@ use pyo3::prelude::*;
 
 #[pyclass]
 pub struct Plugin {
+    #[pyo3(get)]
     pub name: String,
     pub execute: Py<PyAny>,
 }
@@ -14,7 +15,7 @@ impl Plugin {
     }
 }
 
-static PLUGIN_REGISTRY: Lazy<DashMap<String, Plugin>> = Lazy::new(|| DashMap::new());
+static PLUGIN_REGISTRY: Lazy<DashMap<String, Plugin>> = Lazy::new(DashMap::new);
 
 #[pyfunction]
 pub fn register_plugin(name: String, execute: Py<PyAny>) -> PyResult<()> {
@@ -26,7 +27,7 @@ pub fn register_plugin(name: String, execute: Py<PyAny>) -> PyResult<()> {
 #[pyfunction]
 pub fn run_plugin(name: String) -> PyResult<()> {
     if let Some(plugin) = PLUGIN_REGISTRY.get(&name) {
-        Python::with_gil(|py| {
+        Python::attach(|py| {
             let _ = plugin
                 .execute
                 .call1(py, ())
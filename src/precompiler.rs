@@ -43,7 +43,10 @@ impl JSXPrecompiler {
             })?
             .duration_since(UNIX_EPOCH)
             .map_err(|e| {
-                PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("Error with UNIX_EPOCH: {}", e))
+                PyErr::new::<pyo3::exceptions::PyIOError, _>(format!(
+                    "Error with UNIX_EPOCH: {}",
+                    e
+                ))
             })?
             .as_secs();
 
@@ -71,7 +74,7 @@ impl JSXPrecompiler {
     /// Logic to transform JSX to Python
     fn transform_jsx_to_python(&self, jsx_code: &str) -> String {
         // Use the shared JSX transformer logic
-        crate::jsx_transformer::parse_jsx(jsx_code).unwrap_or_else(|e| {
+        crate::jsx_transformer::transform_pyx(jsx_code).unwrap_or_else(|e| {
             eprintln!("Error transforming JSX: {}", e);
             jsx_code.to_string()
         })
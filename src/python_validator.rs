@@ -0,0 +1,90 @@
+//! Checks the Python emitted for a `.pyx` module with the tree-sitter Python
+//! grammar, so a broken module is reported at compile time instead of when
+//! the server imports it.
+
+use crate::diagnostics::Diagnostic;
+use crate::jsx_parser::{LineIndex, Span};
+use crate::source_map::Mapping;
+use tree_sitter::{Node, Parser};
+
+/// Validates `python_code`, the output of compiling `source` with `mappings`.
+/// The first syntax error is reported as a `PYX016` diagnostic positioned in
+/// the `.pyx` source.
+pub fn validate_python(
+    source: &str,
+    python_code: &str,
+    mappings: &[Mapping],
+) -> Result<(), Diagnostic> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::LANGUAGE.into())
+        .expect("the tree-sitter Python grammar is compatible");
+    let Some(tree) = parser.parse(python_code, None) else {
+        return Ok(());
+    };
+    let Some(node) = first_error(tree.root_node()) else {
+        return Ok(());
+    };
+
+    let message = if node.is_missing() {
+        format!("Generated Python is invalid: missing `{}`", node.kind())
+    } else {
+        let text = &python_code[node.start_byte()..node.end_byte()];
+        match text.split_whitespace().next() {
+            Some(token) => format!("Generated Python is invalid near `{}`", token),
+            None => "Generated Python is invalid".to_string(),
+        }
+    };
+    let generated_line = python_code[node.start_byte()..]
+        .lines()
+        .next()
+        .unwrap_or_default();
+    let line = node.start_position().row + 1;
+
+    let start = original_offset(source, python_code, mappings, node.start_byte());
+    let end = original_offset(source, python_code, mappings, node.end_byte());
+    let end = if end > start { end } else { start + 1 };
+    let span = Span::new(start, end.min(source.len()).max(start));
+    Err(
+        Diagnostic::error("PYX016", message, span, &LineIndex::new(source)).with_help(format!(
+            "line {} of the compiled module is `{}`",
+            line,
+            generated_line.trim()
+        )),
+    )
+}
+
+/// The first `ERROR` or `MISSING` node in source order
+fn first_error(node: Node<'_>) -> Option<Node<'_>> {
+    if node.is_error() || node.is_missing() {
+        return Some(node);
+    }
+    if !node.has_error() {
+        return None;
+    }
+    let mut cursor = node.walk();
+    let children: Vec<_> = node.children(&mut cursor).collect();
+    children.into_iter().find_map(first_error)
+}
+
+/// Maps an offset in the generated Python to `source`. Offsets inside code
+/// that was copied verbatim keep their distance from the closest mapping.
+pub(crate) fn original_offset(
+    source: &str,
+    python_code: &str,
+    mappings: &[Mapping],
+    offset: usize,
+) -> usize {
+    let Some(mapping) = mappings.iter().rev().find(|m| m.generated <= offset) else {
+        return 0;
+    };
+    let delta = offset - mapping.generated;
+    let copied = source
+        .get(mapping.original..mapping.original + delta)
+        .is_some_and(|original| original == &python_code[mapping.generated..offset]);
+    if copied {
+        mapping.original + delta
+    } else {
+        mapping.original.min(source.len())
+    }
+}
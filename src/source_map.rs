@@ -0,0 +1,87 @@
+//! Source Map v3 support for code generated from `.pyx` files.
+//!
+//! The transformer records [`Mapping`]s as pairs of byte offsets (generated
+//! output, original source); they are converted to line/column tokens of a
+//! [`SourceMap`] only when the map is built. [`parse_source_map`] and
+//! [`lookup`] read them back, e.g. to map runtime tracebacks to `.pyx`
+//! positions.
+
+use crate::jsx_parser::LineIndex;
+use swc_sourcemap::SourceMapBuilder;
+pub use swc_sourcemap::{SourceMap, Token};
+
+/// A generated position and the original position it comes from, both as
+/// byte offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mapping {
+    pub generated: usize,
+    pub original: usize,
+}
+
+/// Builds a Source Map v3 for a generated file with a single source
+pub fn build_source_map(
+    file: &str,
+    source_name: &str,
+    source: &str,
+    generated: &str,
+    mappings: &[Mapping],
+) -> SourceMap {
+    let source_lines = LineIndex::new(source);
+    let generated_lines = LineIndex::new(generated);
+
+    let mut sorted = mappings.to_vec();
+    sorted.sort_by_key(|m| m.generated);
+    sorted.dedup_by_key(|m| m.generated);
+
+    let mut builder = SourceMapBuilder::new(Some(file.to_string().into()));
+    let source_id = builder.add_source(source_name.to_string().into());
+    builder.set_source_contents(source_id, Some(source.to_string().into()));
+    for mapping in sorted {
+        let (gen_line, gen_col) = utf16_position(generated, &generated_lines, mapping.generated);
+        let (orig_line, orig_col) = utf16_position(source, &source_lines, mapping.original);
+        builder.add_raw(
+            gen_line,
+            gen_col,
+            orig_line,
+            orig_col,
+            Some(source_id),
+            None,
+            false,
+        );
+    }
+    builder.into_sourcemap()
+}
+
+/// Serializes a source map to its JSON document
+pub fn source_map_json(map: &SourceMap) -> String {
+    let mut json = Vec::new();
+    map.to_writer(&mut json)
+        .expect("writing a source map to memory cannot fail");
+    String::from_utf8(json).expect("source maps are serialized as UTF-8")
+}
+
+/// Parses a Source Map v3 JSON document
+pub fn parse_source_map(json: &str) -> Result<SourceMap, String> {
+    SourceMap::from_slice(json.as_bytes()).map_err(|e| format!("Invalid source map: {}", e))
+}
+
+/// Finds the token covering a generated position (0-based line, UTF-16
+/// column): the last one at or before `column` on `line`, else the first one
+/// on that line, else the closest one on a preceding line
+pub fn lookup(map: &SourceMap, line: u32, column: u32) -> Option<Token<'_>> {
+    let before = map.lookup_token(line, column);
+    if before.is_some_and(|token| token.get_dst_line() == line) {
+        return before;
+    }
+    map.tokens()
+        .find(|token| token.get_dst_line() == line)
+        .or(before)
+}
+
+/// 0-based line and UTF-16 column of a byte offset, as used by source maps
+fn utf16_position(text: &str, lines: &LineIndex, offset: usize) -> (u32, u32) {
+    let (line, column) = lines.line_col(offset);
+    let line_start = offset + 1 - column;
+    let utf16_column = text[line_start..offset].encode_utf16().count();
+    (line as u32 - 1, utf16_column as u32)
+}
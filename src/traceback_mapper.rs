@@ -0,0 +1,210 @@
+//! Maps Python tracebacks raised from compiled components back to the `.pyx`
+//! sources, using the `.py.map` files the compiler writes next to each module.
+
+use crate::diagnostics::{code_frame, display_path};
+use crate::source_map::{lookup, parse_source_map, SourceMap};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Loaded maps by generated file, reloaded when the map file changes (HMR)
+static MAP_CACHE: Lazy<DashMap<PathBuf, Arc<LoadedMap>>> = Lazy::new(DashMap::new);
+
+struct LoadedMap {
+    modified: Option<SystemTime>,
+    /// Generated Python, used to convert byte columns to UTF-16
+    generated: String,
+    map: SourceMap,
+    map_dir: PathBuf,
+}
+
+/// A position in a `.pyx` source; `line` and `column` are 1-based, the column
+/// counted in bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PyxLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    /// The content of the `.pyx` file as embedded in the map
+    pub source: Option<String>,
+}
+
+/// Translates a position in a compiled `.py` file (1-based line, optional
+/// 0-based byte column) to its `.pyx` origin, if a source map exists for it
+pub fn map_location(
+    generated_file: &Path,
+    line: usize,
+    column: Option<usize>,
+) -> Option<PyxLocation> {
+    let loaded = load_map(generated_file)?;
+    let generated_line = line.checked_sub(1)?;
+    let utf16_column = match (column, loaded.generated.lines().nth(generated_line)) {
+        (Some(column), Some(text)) => utf16_len(text, column),
+        (Some(column), None) => column,
+        (None, _) => 0,
+    };
+
+    let token = lookup(&loaded.map, generated_line as u32, utf16_column as u32)?;
+    let source_name = token.get_source()?;
+    let source = loaded
+        .map
+        .get_source_contents(token.get_src_id())
+        .map(|content| content.to_string());
+    let column = match source
+        .as_deref()
+        .and_then(|s| s.lines().nth(token.get_src_line() as usize))
+    {
+        Some(text) => utf16_to_byte(text, token.get_src_col() as usize),
+        None => token.get_src_col() as usize,
+    };
+
+    Some(PyxLocation {
+        file: display_path(&normalize_path(&loaded.map_dir.join(&**source_name))),
+        line: token.get_src_line() as usize + 1,
+        column: column + 1,
+        source,
+    })
+}
+
+fn load_map(generated_file: &Path) -> Option<Arc<LoadedMap>> {
+    let mut map_path = generated_file.as_os_str().to_owned();
+    map_path.push(".map");
+    let map_path = PathBuf::from(map_path);
+    let modified = std::fs::metadata(&map_path).ok()?.modified().ok();
+
+    if let Some(cached) = MAP_CACHE.get(generated_file) {
+        if cached.modified == modified {
+            return Some(cached.clone());
+        }
+    }
+
+    let json = std::fs::read_to_string(&map_path).ok()?;
+    let map = match parse_source_map(&json) {
+        Ok(map) => map,
+        Err(e) => {
+            log::warn!("Ignoring source map {}: {}", map_path.display(), e);
+            return None;
+        }
+    };
+    let loaded = Arc::new(LoadedMap {
+        modified,
+        generated: std::fs::read_to_string(generated_file).unwrap_or_default(),
+        map,
+        map_dir: map_path.parent().unwrap_or(Path::new("")).to_path_buf(),
+    });
+    MAP_CACHE.insert(generated_file.to_path_buf(), loaded.clone());
+    Some(loaded)
+}
+
+/// Formats an exception's traceback like Python does, with frames from
+/// compiled components pointing at their `.pyx` source and a code frame
+pub fn format_traceback(py: Python<'_>, exc: &Bound<'_, PyAny>) -> PyResult<String> {
+    let traceback = py.import("traceback")?;
+    let frames = traceback.call_method1("extract_tb", (exc.getattr("__traceback__")?,))?;
+
+    let mut text = String::from("Traceback (most recent call last):\n");
+    for frame in frames.try_iter()? {
+        let frame = frame?;
+        let filename: String = frame.getattr("filename")?.extract()?;
+        let lineno: Option<usize> = frame.getattr("lineno")?.extract()?;
+        let name: String = frame.getattr("name")?.extract()?;
+        let colno: Option<usize> = frame
+            .getattr("colno")
+            .ok()
+            .and_then(|c| c.extract().ok())
+            .flatten();
+
+        let mapped = lineno.and_then(|line| map_location(Path::new(&filename), line, colno));
+        match mapped {
+            Some(location) => {
+                let _ = writeln!(
+                    text,
+                    "  File \"{}\", line {}, column {}, in {}",
+                    location.file, location.line, location.column, name
+                );
+                if let Some(source) = &location.source {
+                    for line in code_frame(source, location.line, location.column, 1, false).lines()
+                    {
+                        let _ = writeln!(text, "    {}", line);
+                    }
+                }
+            }
+            None => {
+                let _ = writeln!(
+                    text,
+                    "  File \"{}\", line {}, in {}",
+                    filename,
+                    lineno.unwrap_or(0),
+                    name
+                );
+                let line: Option<String> = frame.getattr("line")?.extract()?;
+                if let Some(line) = line.filter(|l| !l.trim().is_empty()) {
+                    let _ = writeln!(text, "    {}", line.trim());
+                }
+            }
+        }
+    }
+
+    let summary = traceback.call_method1("format_exception_only", (exc.get_type(), exc))?;
+    for line in summary.try_iter()? {
+        text.push_str(&line?.extract::<String>()?);
+    }
+    Ok(text)
+}
+
+/// Maps a position in a compiled `.py` file to `(pyx_file, line, column)`
+#[pyfunction]
+#[pyo3(signature = (file, line, column=None))]
+pub fn map_pyx_location(
+    file: &str,
+    line: usize,
+    column: Option<usize>,
+) -> Option<(String, usize, usize)> {
+    map_location(Path::new(file), line, column).map(|l| (l.file, l.line, l.column))
+}
+
+/// Formats an exception's traceback with frames mapped to `.pyx` sources
+#[pyfunction]
+pub fn format_pyx_traceback(py: Python<'_>, exc: &Bound<'_, PyAny>) -> PyResult<String> {
+    format_traceback(py, exc)
+}
+
+fn utf16_len(text: &str, byte_column: usize) -> usize {
+    let mut end = byte_column.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text[..end].encode_utf16().count()
+}
+
+fn utf16_to_byte(text: &str, utf16_column: usize) -> usize {
+    let mut units = 0;
+    for (offset, c) in text.char_indices() {
+        if units >= utf16_column {
+            return offset;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Resolves `.` and `..` components without touching the filesystem
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
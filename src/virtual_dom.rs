@@ -1,7 +1,14 @@
+use crate::jsx_parser::RAW_TEXT_ELEMENTS;
 use pyo3::prelude::*;
-use pyo3::types::PyModule;
+use pyo3::types::{PyBool, PyModule};
 use std::collections::HashMap;
 
+/// Tag used for text nodes; their content is stored in the `nodeValue` prop
+pub const TEXT_NODE_TAG: &str = "#text";
+
+/// Tag used for fragments, whose children are rendered in place of the node
+pub const FRAGMENT_TAG: &str = "#fragment";
+
 /// Virtual DOM node representation
 #[pyclass]
 #[derive(Debug)]
@@ -18,6 +25,17 @@ pub struct VNode {
     pub cache_duration_secs: u64,
     #[pyo3(get, set)]
     pub key: Option<String>,
+    /// The callable of a component node (`<Button />`); its props include
+    /// `children`
+    #[pyo3(get)]
+    pub component: Option<Py<PyAny>>,
+    /// The output of the component the last time it was rendered
+    #[pyo3(get)]
+    pub rendered: Option<Py<VNode>>,
+    /// Position of a component node in the rendered tree (`App/0/1:Counter`),
+    /// which scopes the state of its hooks; set when its parent renders
+    #[pyo3(get)]
+    pub instance_path: Option<String>,
 }
 
 #[pymethods]
@@ -39,18 +57,120 @@ impl VNode {
             is_critical,
             cache_duration_secs,
             key,
+            component: None,
+            rendered: None,
+            instance_path: None,
         }
     }
 
+    /// Creates a text node
+    #[staticmethod]
+    fn text(py: Python, value: String) -> PyResult<Self> {
+        let mut props = HashMap::new();
+        props.insert(
+            "nodeValue".to_string(),
+            value.into_pyobject(py)?.into_any().unbind(),
+        );
+        Ok(VNode {
+            tag: TEXT_NODE_TAG.to_string(),
+            props,
+            children: Vec::new(),
+            is_critical: false,
+            cache_duration_secs: 0,
+            key: None,
+            component: None,
+            rendered: None,
+            instance_path: None,
+        })
+    }
+
+    /// Creates a fragment node
+    #[staticmethod]
+    #[pyo3(signature = (children, key=None))]
+    fn fragment(children: Vec<Py<VNode>>, key: Option<String>) -> Self {
+        VNode {
+            tag: FRAGMENT_TAG.to_string(),
+            props: HashMap::new(),
+            children,
+            is_critical: false,
+            cache_duration_secs: 0,
+            key,
+            component: None,
+            rendered: None,
+            instance_path: None,
+        }
+    }
+
+    /// Creates a component node; the component is called with `props` when
+    /// the node is rendered
+    #[staticmethod]
+    #[pyo3(signature = (component, props, key=None))]
+    fn component(
+        py: Python,
+        component: Py<PyAny>,
+        props: HashMap<String, Py<PyAny>>,
+        key: Option<String>,
+    ) -> PyResult<Self> {
+        let name = component_name(component.bind(py))?;
+        Ok(VNode {
+            tag: name,
+            props,
+            children: Vec::new(),
+            is_critical: false,
+            cache_duration_secs: 0,
+            key,
+            component: Some(component),
+            rendered: None,
+            instance_path: None,
+        })
+    }
+
+    /// Whether this node renders a component
+    #[getter]
+    fn is_component(&self) -> bool {
+        self.component.is_some()
+    }
+
+    /// Calls the component again and stores its output in `rendered`, so a
+    /// component can be re-rendered without rebuilding its parent
+    fn render_component(slf: &Bound<'_, Self>) -> PyResult<Py<VNode>> {
+        let py = slf.py();
+        let rendered = call_component(py, &slf.borrow())?;
+        slf.borrow_mut().rendered = Some(rendered.clone_ref(py));
+        Ok(rendered)
+    }
+
     /// Renders the virtual node as an HTML string
     pub fn render(&self, py: Python) -> PyResult<String> {
+        if self.component.is_some() {
+            let rendered = match &self.rendered {
+                Some(rendered) => rendered.clone_ref(py),
+                None => call_component(py, self)?,
+            };
+            return rendered.borrow(py).render(py);
+        }
+        if self.tag == TEXT_NODE_TAG {
+            return match self.props.get("nodeValue") {
+                Some(value) => Ok(escape_html(&value.bind(py).str()?.to_string())),
+                None => Ok(String::new()),
+            };
+        }
+        if self.tag == FRAGMENT_TAG {
+            let mut html = String::new();
+            for child in &self.children {
+                let child = expand_component(py, child)?;
+                html.push_str(&child.borrow(py).render(py)?);
+            }
+            return Ok(html);
+        }
+
         let mut html = format!("<{}", self.tag);
 
         // Add attributes
         for (key, value) in &self.props {
             // Handle event handlers for SSR
-            if key.starts_with("on") {
-                let event_name = key[2..].to_lowercase();
+            if let Some(event_name) = key.strip_prefix("on") {
+                let event_name = event_name.to_lowercase();
 
                 // Register handler in Python registry
                 let registry = py.import("reactpyx.registry")?;
@@ -62,6 +182,19 @@ impl VNode {
                 continue;
             }
 
+            // Boolean attributes: `True` renders the bare name, `False`/`None`
+            // omit the attribute
+            let bound = value.bind(py);
+            if bound.is_none() {
+                continue;
+            }
+            if let Ok(flag) = bound.cast::<PyBool>() {
+                if flag.is_true() {
+                    html.push_str(&format!(" {}", key));
+                }
+                continue;
+            }
+
             // Safely convert value to string
             let value_str: String = if let Ok(s) = value.extract::<String>(py) {
                 s
@@ -75,10 +208,18 @@ impl VNode {
 
         html.push('>');
 
-        // Add children
+        // Add children; the text of `<style>` and `<script>` is not HTML, so
+        // it is kept as is but for sequences that would close the element
+        let raw_text = RAW_TEXT_ELEMENTS.contains(&self.tag.as_str());
         for child in &self.children {
-            let child_node = child.borrow(py);
-            let child_html = child_node.render(py)?;
+            let child = expand_component(py, child)?;
+            let child = child.borrow(py);
+            let child_html = match child.props.get("nodeValue") {
+                Some(value) if raw_text && child.tag == TEXT_NODE_TAG => {
+                    escape_raw_text(&value.bind(py).str()?.to_string())
+                }
+                _ => child.render(py)?,
+            };
 
             html.push_str(&child_html);
         }
@@ -116,7 +257,7 @@ impl VNode {
         dict.set_item("props", props_dict)?;
 
         let children_list = pyo3::types::PyList::empty(py);
-        for child in &self.children {
+        for child in self.flat_children(py)? {
             let child_node = child.borrow(py);
             children_list.append(child_node.to_dict(py)?)?;
         }
@@ -151,6 +292,9 @@ impl VNode {
             is_critical: self.is_critical,
             cache_duration_secs: self.cache_duration_secs,
             key: self.key.clone(),
+            component: self.component.as_ref().map(|c| c.clone_ref(py)),
+            rendered: self.rendered.as_ref().map(|r| r.clone_ref(py)),
+            instance_path: self.instance_path.clone(),
         };
 
         Py::new(py, new_node)
@@ -169,6 +313,132 @@ impl VNode {
     }
 }
 
+impl VNode {
+    /// The children as they appear in the rendered output, with components
+    /// replaced by their output and fragments by their own children
+    pub fn flat_children(&self, py: Python) -> PyResult<Vec<Py<VNode>>> {
+        let mut flat = Vec::with_capacity(self.children.len());
+        for child in &self.children {
+            let child = expand_component(py, child)?;
+            let node = child.borrow(py);
+            if node.tag == FRAGMENT_TAG {
+                flat.extend(node.flat_children(py)?);
+            } else {
+                flat.push(child.clone_ref(py));
+            }
+        }
+        Ok(flat)
+    }
+}
+
+/// Follows component nodes to the node they render, rendering (and caching)
+/// them on first use
+fn expand_component(py: Python, node: &Py<VNode>) -> PyResult<Py<VNode>> {
+    let mut current = node.clone_ref(py);
+    loop {
+        let (is_component, rendered) = {
+            let borrowed = current.borrow(py);
+            let rendered = borrowed.rendered.as_ref().map(|r| r.clone_ref(py));
+            (borrowed.component.is_some(), rendered)
+        };
+        if !is_component {
+            return Ok(current);
+        }
+        let rendered = match rendered {
+            Some(rendered) => rendered,
+            None => {
+                let rendered = call_component(py, &current.borrow(py))?;
+                current.borrow_mut(py).rendered = Some(rendered.clone_ref(py));
+                rendered
+            }
+        };
+        current = rendered;
+    }
+}
+
+/// Calls a component node's callable through `reactpyx.element`, which passes
+/// the props, makes the node's instance path current for its hooks and turns
+/// the result into a `VNode`
+fn call_component(py: Python, node: &VNode) -> PyResult<Py<VNode>> {
+    let Some(component) = &node.component else {
+        return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(
+            "Node is not a component",
+        ));
+    };
+    let props = pyo3::types::PyDict::new(py);
+    for (key, value) in &node.props {
+        props.set_item(key, value)?;
+    }
+    let path = node
+        .instance_path
+        .clone()
+        .unwrap_or_else(|| node.tag.clone());
+    let element = py.import("reactpyx.element")?;
+    let rendered: Py<VNode> = element
+        .call_method1("render_component", (component, props, &path))?
+        .extract()
+        .map_err(PyErr::from)?;
+    assign_instance_paths(py, &rendered, &path);
+    Ok(rendered)
+}
+
+/// Gives the component nodes in a component's output their instance path:
+/// the path of the component, the key (or index) of each node on the way
+/// down and the component's tag
+fn assign_instance_paths(py: Python, node: &Py<VNode>, path: &str) {
+    let Ok(mut borrowed) = node.try_borrow_mut(py) else {
+        return;
+    };
+    if borrowed.component.is_some() {
+        if borrowed.instance_path.is_none() {
+            borrowed.instance_path = Some(format!("{}:{}", path, borrowed.tag));
+        }
+        return;
+    }
+    let children: Vec<Py<VNode>> = borrowed.children.iter().map(|c| c.clone_ref(py)).collect();
+    drop(borrowed);
+    for (index, child) in children.iter().enumerate() {
+        let segment = child
+            .try_borrow(py)
+            .ok()
+            .and_then(|child| child.key.clone())
+            .unwrap_or_else(|| index.to_string());
+        assign_instance_paths(py, child, &format!("{}/{}", path, segment));
+    }
+}
+
+/// Display name of a component callable
+fn component_name(component: &Bound<'_, PyAny>) -> PyResult<String> {
+    for attribute in ["__name__", "__qualname__"] {
+        if let Ok(name) = component.getattr(attribute) {
+            return name.extract();
+        }
+    }
+    Ok(component.get_type().name()?.to_string())
+}
+
+/// Escapes text content for inclusion in HTML
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes the text of a raw text element: only `</`, which could close it,
+/// becomes `<\/`
+fn escape_raw_text(text: &str) -> String {
+    text.replace("</", "<\\/")
+}
+
 /// Types of patch operations for virtual nodes
 #[pyclass]
 #[derive(Debug)]
@@ -211,7 +481,7 @@ impl Patch {
 
 /// Calculates differences between two virtual nodes
 #[pyfunction]
-pub fn diff_nodes(py: Python, old_node: &VNode, new_node: &VNode) -> Vec<Patch> {
+pub fn diff_nodes(py: Python, old_node: &VNode, new_node: &VNode) -> PyResult<Vec<Patch>> {
     let mut patches = Vec::new();
 
     // Compare properties
@@ -249,37 +519,40 @@ pub fn diff_nodes(py: Python, old_node: &VNode, new_node: &VNode) -> Vec<Patch>
         }
     }
 
-    // Compare children (simplified approach)
-    let min_children_len = old_node.children.len().min(new_node.children.len());
+    // Compare children (simplified approach); components and fragments are
+    // flattened so that indices match the rendered siblings
+    let old_children = old_node.flat_children(py)?;
+    let new_children = new_node.flat_children(py)?;
+    let min_children_len = old_children.len().min(new_children.len());
     for index in 0..min_children_len {
-        let old_child = old_node.children[index].borrow(py);
-        let new_child = new_node.children[index].borrow(py);
+        let old_child = old_children[index].borrow(py);
+        let new_child = new_children[index].borrow(py);
 
         if old_child.tag != new_child.tag {
             patches.push(Patch::ReplaceChild {
                 index,
-                child: new_node.children[index].clone_ref(py),
+                child: new_children[index].clone_ref(py),
             });
         }
     }
 
     // Add new children
-    if new_node.children.len() > old_node.children.len() {
-        for index in old_node.children.len()..new_node.children.len() {
+    if new_children.len() > old_children.len() {
+        for child in &new_children[old_children.len()..] {
             patches.push(Patch::AddChild {
-                child: new_node.children[index].clone_ref(py),
+                child: child.clone_ref(py),
             });
         }
     }
 
     // Remove old children
-    if old_node.children.len() > new_node.children.len() {
-        for index in (new_node.children.len()..old_node.children.len()).rev() {
+    if old_children.len() > new_children.len() {
+        for index in (new_children.len()..old_children.len()).rev() {
             patches.push(Patch::RemoveChild { index });
         }
     }
 
-    patches
+    Ok(patches)
 }
 
 /// Adds the Virtual DOM module to the Python module
@@ -4,6 +4,7 @@ from fastapi import FastAPI, Request, HTTPException, WebSocket, WebSocketDisconn
 from fastapi.responses import HTMLResponse, FileResponse
 from fastapi.staticfiles import StaticFiles
 from fastapi.templating import Jinja2Templates
+import importlib
 import os
 import sys
 import json
@@ -48,16 +49,20 @@ BASE_DIR = os.path.dirname(os.path.abspath(__file__))
 # --- ReactPyx Configuration ---
 # Import the main component from the build directory
 # Note: You need to run the ReactPyx compiler first
+# `reactpyx run` passes the `outDir`, `entry` and `entryFunction` of
+# pyx.config.json; the defaults match a project without one
+OUT_DIR = os.environ.get("REACTPYX_OUT_DIR", "build")
+ENTRY_MODULE = os.environ.get("REACTPYX_ENTRY_MODULE", "components.main")
+ENTRY_FUNCTION = os.environ.get("REACTPYX_ENTRY_FUNCTION", "MainApp")
+PUBLIC_PATH = os.environ.get("REACTPYX_PUBLIC_PATH", "public")
 try:
-    # Assuming your main component is named 'App' in 'src/main.pyx'
-    # which compiles to 'build/components/main.py'
-    # Adjust the import according to your project structure
-    sys.path.append(os.path.join(BASE_DIR, "build"))
-    from components.main import App
+    # 'src/main.pyx' compiles to 'build/components/main.py'
+    sys.path.append(os.path.join(BASE_DIR, OUT_DIR))
+    App = getattr(importlib.import_module(ENTRY_MODULE), ENTRY_FUNCTION)
     import reactpyx
 
     reactpyx.set_root(App)
-except ImportError:
+except (ImportError, AttributeError):
     print("Warning: Could not import App component. Make sure to compile your project.")
 
 # sys.path.append(BASE_DIR) # Descomentar si necesitas importar desde src/
@@ -70,13 +75,13 @@ templates = Jinja2Templates(directory=templates_dir)  # <-- INSTANCIA CREADA
 
 # --- Montar archivos estáticos ---
 # Los archivos generados por ReactPyx (JS, CSS) irán aquí
-static_dir = os.path.join(BASE_DIR, "public", "static")
+static_dir = os.path.join(BASE_DIR, PUBLIC_PATH, "static")
 # Asegúrate que el directorio exista al iniciar
 os.makedirs(static_dir, exist_ok=True)
 app.mount("/static", StaticFiles(directory=static_dir), name="static")  # <-- MANTENIDO
 
 # --- Favicon (Opcional pero bueno tenerlo) ---
-favicon_path = os.path.join(BASE_DIR, "public", "favicon.ico")
+favicon_path = os.path.join(BASE_DIR, PUBLIC_PATH, "favicon.ico")
 
 
 @app.get("/favicon.ico", include_in_schema=False)
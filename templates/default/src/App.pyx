@@ -10,10 +10,10 @@ from components.SimpleEffect import SimpleEffect
 
 def App():
     """Main application component that renders the application layout"""
-    user, setUser = use_state("user", "ReactPyx Developer")
+    user, setUser = use_state("ReactPyx Developer")
     
     # Using use_effect_with_deps for effects that depend on specific values
-    use_effect_with_deps("user_effect", lambda deps: print(f"User: {user}") if user else None, [user])
+    use_effect_with_deps(lambda deps: print(f"User: {user}") if user else None, [user])
     
     return (
         <div>
@@ -2,8 +2,8 @@ from reactpyx import use_state, use_effect, use_effect_with_deps
 
 def Home():
     """Home page component with counter demonstration"""
-    count, setCount = use_state("home", "count", 0)
-    message, setMessage = use_state("home", "message", "")
+    count, setCount = use_state(0)
+    message, setMessage = use_state("")
     
     def increment():
         setCount.set(count + 1)
@@ -13,7 +13,6 @@ def Home():
     
     # Demonstration of use_effect_with_deps (with dependencies, runs when count changes)
     use_effect_with_deps(
-        "count_effect", 
         lambda deps: setMessage.set(f"Counter updated to: {count}"),
         [count]
     )
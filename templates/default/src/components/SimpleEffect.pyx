@@ -4,7 +4,7 @@ from reactpyx import use_state, use_effect
 
 def SimpleEffect():
     """Simple component demonstrating the use_effect hook"""
-    count, setCount = use_state("simple_effect", "count", 0)
+    count, setCount = use_state(0)
     
     # Using use_effect without dependencies (runs every render)
     use_effect(lambda: print("This effect runs on every render"))
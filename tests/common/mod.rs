@@ -0,0 +1,19 @@
+//! Fixtures shared by the integration tests
+
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+/// Writes `files` under `root/src`, and `config` as `root/pyx.config.json`
+/// when given
+pub fn write_project(root: &Path, config: Option<&str>, files: &[(&str, &str)]) -> Result<()> {
+    if let Some(config) = config {
+        fs::write(root.join("pyx.config.json"), config)?;
+    }
+    for (path, source) in files {
+        let path = root.join("src").join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, source)?;
+    }
+    Ok(())
+}
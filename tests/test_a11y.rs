@@ -0,0 +1,70 @@
+use _core::a11y::check_accessibility;
+use _core::jsx_parser::parse_pyx;
+
+fn warnings(source: &str) -> Vec<(&'static str, usize, String)> {
+    let code = parse_pyx(source).unwrap();
+    check_accessibility(source, &code)
+        .into_iter()
+        .map(|d| (d.code, d.line, d.message))
+        .collect()
+}
+
+#[test]
+fn test_elements_without_names_are_reported() {
+    let source = r#"def Form(props):
+    return <form>
+        <img src="logo.png" />
+        <img src="divider.png" alt="" />
+        <button><svg /></button>
+        <button aria-label="Close"><svg /></button>
+        <button><img src="save.png" alt="Save" /></button>
+        <button>{props["label"]}</button>
+        <input name="email" />
+        <label>Name <input name="name" /></label>
+        <label htmlFor="age">Age</label>
+        <input id="age" />
+        <input type="submit" />
+        <textarea title="Notes"></textarea>
+    </form>
+"#;
+    assert_eq!(
+        warnings(source),
+        vec![
+            ("A11Y001", 3, "<img> has no `alt` text".to_string()),
+            ("A11Y002", 5, "<button> has no accessible text".to_string()),
+            ("A11Y003", 9, "<input> has no label".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_aria_attributes_and_click_handlers_are_checked() {
+    let source = r#"def Card(props):
+    return <div onClick={props["open"]} aria-lable="Card">
+        <span role="button" tabIndex={0} onClick={props["open"]}>Open</span>
+        <a href="/" onClick={props["open"]} aria-current="page">Home</a>
+        <Card.Body onClick={props["open"]} aria-whatever="x" />
+        <div {...props} />
+    </div>
+"#;
+    let found = warnings(source);
+    assert_eq!(
+        found,
+        vec![
+            (
+                "A11Y005",
+                2,
+                "<div> has `onClick` but is not interactive".to_string()
+            ),
+            (
+                "A11Y004",
+                2,
+                "Unknown ARIA attribute `aria-lable`".to_string()
+            ),
+        ]
+    );
+
+    let code = parse_pyx(source).unwrap();
+    let aria = &check_accessibility(source, &code)[1];
+    assert_eq!(aria.help.as_deref(), Some("did you mean `aria-label`?"));
+}
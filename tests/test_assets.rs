@@ -0,0 +1,77 @@
+use _core::assets::{hash_assets, integrity, AssetManifest, ASSET_MANIFEST_FILE};
+use anyhow::Result;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_integrity_is_sha384() {
+    assert_eq!(
+        integrity(b"abc"),
+        "sha384-ywB1P0WjXou1oD1pmsZQBycsMqsO3tFjGotgWkP/W+2AhgcroefMI1i67KE0yCWn"
+    );
+    assert_eq!(
+        integrity(b""),
+        "sha384-OLBgp1GsljhM2TJ+sbHjaiH9txEUvgdDTAzHv2P24donTt6/529l+9Ua0vFImLlb"
+    );
+    // Padding spills into a second block
+    assert_eq!(
+        integrity(&[b'a'; 200]),
+        "sha384-BpG26XhhS2fWBVeyos3dU0BlCFIu+iHGJNu/qKtucm1cWGtInHwJ8kEJpkwQIR1I"
+    );
+}
+
+#[test]
+fn test_hash_assets_writes_hashed_files_and_manifest() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let out_dir = temp_dir.path();
+    fs::write(out_dir.join("styles.css"), ".app{color:red}")?;
+    fs::write(
+        out_dir.join("bundle.js"),
+        "console.log(1);\n//# sourceMappingURL=bundle.js.map\n",
+    )?;
+    fs::write(out_dir.join("bundle.js.map"), "{\"version\":3}")?;
+    // The bundler's manifest is left alone
+    let bundle_manifest = r#"{"files": {}, "lazy": {}}"#;
+    fs::write(out_dir.join("manifest.json"), bundle_manifest)?;
+
+    let manifest = hash_assets(out_dir)?;
+    let styles = &manifest.assets["styles.css"];
+    assert!(styles.file.starts_with("styles.") && styles.file.ends_with(".css"));
+    assert_eq!(styles.file.len(), "styles.12345678.css".len());
+    assert_eq!(fs::read(out_dir.join(&styles.file))?, b".app{color:red}");
+    assert_eq!(styles.size, 15);
+    assert_eq!(styles.integrity, integrity(b".app{color:red}"));
+    assert!(styles.map.is_none());
+
+    let bundle = &manifest.assets["bundle.js"];
+    let map = bundle.map.as_deref().unwrap();
+    assert_eq!(map, format!("{}.map", bundle.file));
+    let bundle_js = fs::read_to_string(out_dir.join(&bundle.file))?;
+    assert_eq!(
+        bundle_js,
+        format!("console.log(1);\n//# sourceMappingURL={}\n", map)
+    );
+    assert_eq!(bundle.integrity, integrity(bundle_js.as_bytes()));
+    assert!(out_dir.join(map).exists());
+    assert!(out_dir.join("bundle.js").exists(), "fixed names are kept");
+
+    let written = AssetManifest::load(out_dir)?.unwrap();
+    assert_eq!(written, manifest);
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join(ASSET_MANIFEST_FILE))?)?;
+    assert_eq!(json["styles.css"]["file"], styles.file.as_str());
+    assert_eq!(
+        fs::read_to_string(out_dir.join("manifest.json"))?,
+        bundle_manifest
+    );
+
+    // A rebuild removes the files the new manifest no longer points at
+    fs::write(out_dir.join("styles.css"), ".app{color:blue}")?;
+    let rebuilt = hash_assets(out_dir)?;
+    assert_ne!(rebuilt.assets["styles.css"].file, styles.file);
+    assert!(!out_dir.join(&styles.file).exists());
+    assert!(out_dir.join(&rebuilt.assets["styles.css"].file).exists());
+    assert_eq!(rebuilt.assets["bundle.js"], *bundle);
+    assert!(out_dir.join(&bundle.file).exists());
+    Ok(())
+}
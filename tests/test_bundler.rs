@@ -0,0 +1,216 @@
+mod common;
+
+use _core::bundler::{bundle, BundleManifest, BUNDLE_FILE, MANIFEST_FILE};
+use _core::diagnostics::Diagnostic;
+use anyhow::Result;
+use common::write_project;
+use std::fs;
+use tempfile::TempDir;
+
+const CONFIG: &str = r#"{"compilerOptions": {"minify": false}}"#;
+
+#[tokio::test]
+async fn test_bundle_splits_lazy_modules_into_chunks() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    write_project(
+        root,
+        Some(CONFIG),
+        &[
+            (
+                "App.pyx",
+                concat!(
+                    "from reactpyx import lazy, Suspense\n",
+                    "from components.Title import Title\n",
+                    "\n",
+                    "Admin = lazy(\"pages.Admin\")\n",
+                    "\n",
+                    "def App():\n",
+                    "    return <Suspense fallback={<Title text=\"...\" />}><Admin /></Suspense>\n",
+                ),
+            ),
+            (
+                "components/Title.pyx",
+                "def Title(props):\n    return <h1>{props[\"text\"]}</h1>\n",
+            ),
+            (
+                "pages/Admin.pyx",
+                concat!(
+                    "from components.Title import Title\n",
+                    "from pages.Users import Users\n",
+                    "\n",
+                    "def Admin():\n",
+                    "    return <section><Title text=\"Admin\" /><Users /></section>\n",
+                ),
+            ),
+            ("pages/Users.pyx", "def Users():\n    return <ul></ul>\n"),
+        ],
+    )?;
+    let out_dir = root.join("dist");
+
+    let manifest = bundle(
+        &root.join("src").join("App.pyx"),
+        "pyx.config.json",
+        &out_dir,
+    )
+    .await?;
+    assert_eq!(manifest.entry, BUNDLE_FILE);
+    assert_eq!(
+        manifest.files[BUNDLE_FILE].modules,
+        ["components.Title", "App"]
+    );
+    let chunk = &manifest.lazy["pages.Admin"][0];
+    assert!(chunk.starts_with("chunks/pages.Admin-") && chunk.ends_with(".js"));
+    assert_eq!(
+        manifest.files[chunk].modules,
+        ["pages.Users", "pages.Admin"]
+    );
+    assert_eq!(manifest.files[chunk].imports, ["react"]);
+
+    let bundle_js = fs::read_to_string(out_dir.join(BUNDLE_FILE))?;
+    assert!(bundle_js.starts_with("import { Suspense, createElement, lazy } from \"react\";\n"));
+    assert!(bundle_js.contains("__define(\"components.Title\", function (__require, __load) {"));
+    assert!(bundle_js.contains("const { Title } = __require(\"components.Title\");"));
+    assert!(bundle_js.contains("lazy(()=>__load(\"pages.Admin\")"));
+    assert!(!bundle_js.contains("pages.Users\", function"));
+    assert!(bundle_js.ends_with("export const { Admin, App } = __require(\"App\");\n"));
+
+    let chunk_js = fs::read_to_string(out_dir.join(chunk))?;
+    assert!(chunk_js.contains("export function register(__define) {"));
+    assert!(chunk_js.contains("const { Title } = __require(\"components.Title\");"));
+
+    let written: BundleManifest =
+        serde_json::from_str(&fs::read_to_string(out_dir.join(MANIFEST_FILE))?)?;
+    assert_eq!(written, manifest);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bundle_reports_modules_the_node_target_rejects() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    write_project(
+        root,
+        Some(CONFIG),
+        &[(
+            "App.pyx",
+            concat!(
+                "from reactpyx import lazy\n",
+                "\n",
+                "Missing = lazy(\"pages.Missing\")\n",
+            ),
+        )],
+    )?;
+
+    let error = bundle(
+        &root.join("src").join("App.pyx"),
+        "pyx.config.json",
+        &root.join("dist"),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("The Python module `pages.Missing` cannot be compiled for the node target"),
+        "{}",
+        error
+    );
+    assert!(!root.join("dist").join(BUNDLE_FILE).exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bundle_rejects_import_cycles() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    write_project(
+        root,
+        Some(CONFIG),
+        &[
+            (
+                "App.pyx",
+                "from components.Card import Card\n\ndef App():\n    return <Card />\n",
+            ),
+            (
+                "components/Card.pyx",
+                "from components.Button import Button\n\ndef Card():\n    return <Button />\n",
+            ),
+            (
+                "components/Button.pyx",
+                "from components.Card import Card\n\ndef Button():\n    return <button />\n",
+            ),
+        ],
+    )?;
+
+    let error = bundle(
+        &root.join("src").join("App.pyx"),
+        "pyx.config.json",
+        &root.join("dist"),
+    )
+    .await
+    .unwrap_err();
+    let diagnostic = error.downcast_ref::<Diagnostic>().expect("a diagnostic");
+    assert_eq!(diagnostic.code, "PYX018");
+    assert_eq!(
+        diagnostic.message,
+        "Import cycle: components.Button -> components.Card -> components.Button"
+    );
+    assert!(!root.join("dist").join(BUNDLE_FILE).exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bundle_is_minified_as_configured() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    write_project(
+        root,
+        Some(CONFIG),
+        &[(
+            "App.pyx",
+            concat!(
+                "def Greeting(props):\n",
+                "    message = f\"Hello {props['name']}\"\n",
+                "    print(message)\n",
+                "    return <p>{message}</p>\n",
+                "\n",
+                "def App():\n",
+                "    return <Greeting name=\"Ada\" />\n",
+            ),
+        )],
+    )?;
+    let entry = root.join("src").join("App.pyx");
+    let out_dir = root.join("dist");
+
+    fs::write(
+        root.join("pyx.config.json"),
+        r#"{"compilerOptions": {"sourceMaps": true, "jsMinify": {"dropConsole": true}}}"#,
+    )?;
+    let manifest = bundle(&entry, "pyx.config.json", &out_dir).await?;
+    let bundle_js = fs::read_to_string(out_dir.join(BUNDLE_FILE))?;
+    assert!(!bundle_js.contains("console"), "{}", bundle_js);
+    assert!(!bundle_js.contains("message"), "locals are mangled");
+    assert!(!bundle_js.contains("function Greeting("));
+    assert!(
+        bundle_js.contains("function __require("),
+        "the top level is kept"
+    );
+    assert!(bundle_js.ends_with("\n//# sourceMappingURL=bundle.js.map\n"));
+    let map = manifest.files[BUNDLE_FILE].map.as_deref();
+    assert_eq!(map, Some("bundle.js.map"));
+    let map: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("bundle.js.map"))?)?;
+    assert_eq!(map["sources"][0], BUNDLE_FILE);
+
+    fs::write(
+        root.join("pyx.config.json"),
+        r#"{"compilerOptions": {"jsMinify": {"keepFnames": true, "compress": false}}}"#,
+    )?;
+    let manifest = bundle(&entry, "pyx.config.json", &out_dir).await?;
+    let bundle_js = fs::read_to_string(out_dir.join(BUNDLE_FILE))?;
+    assert!(bundle_js.contains("function Greeting("));
+    assert!(bundle_js.contains("console.log("));
+    assert!(manifest.files[BUNDLE_FILE].map.is_none());
+    Ok(())
+}
@@ -94,7 +94,7 @@ class TestCLIIntegration:
 
         # Let's create a dummy config
         config_path = Path(app_dir) / "pyx.config.json"
-        config_path.write_text('{"compiler": {"target": "python"}}')
+        config_path.write_text('{"compilerOptions": {"minify": true}}')
 
         # Run build
         cmd = [sys.executable, "-m", "reactpyx", "build", "--env", "python"]
@@ -1,6 +1,11 @@
+mod common;
+
+use _core::compiler::{BuildOptions, CompileSummary};
+use _core::config::Config;
+use _core::diagnostics::Severity;
 use anyhow::Result;
+use common::write_project;
 use std::fs;
-use std::path::Path;
 use tempfile::TempDir;
 
 // This test verifies that the project compiles correctly
@@ -10,11 +15,7 @@ async fn test_compilation() -> Result<()> {
     let temp_dir = TempDir::new()?;
     let project_root = temp_dir.path();
 
-    // Create src/components directory
-    let components_dir = project_root.join("src").join("components");
-    fs::create_dir_all(&components_dir)?;
-
-    // Create a dummy .pyx file
+    // Create a dummy .pyx file and config file
     let pyx_content = r#"
 def TestComponent(props):
     return (
@@ -26,27 +27,431 @@ def TestComponent(props):
         </div>
     )
 "#;
-    let pyx_path = components_dir.join("TestComponent.pyx");
-    fs::write(&pyx_path, pyx_content)?;
-
-    // Create a dummy config file
-    fs::write(project_root.join("pyx.config.json"), "{}")?;
+    write_project(
+        project_root,
+        Some("{}"),
+        &[("components/TestComponent.pyx", pyx_content)],
+    )?;
 
     // Run compilation
-    // Note: We need to expose compile_all_pyx in lib.rs or make it public in compiler.rs
-    // Assuming it is available via reactpyx::compiler::compile_all_pyx
+    let CompileSummary {
+        compiled, errors, ..
+    } = _core::compiler::compile_all_pyx(
+        project_root.to_str().unwrap(),
+        "pyx.config.json",
+        "python",
+    )
+    .await?;
+    assert_eq!(compiled.len(), 1);
+    assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+
+    // Check if output files exist
+    let build_dir = project_root.join("build").join("components");
+    let output = fs::read_to_string(build_dir.join("components").join("TestComponent.py"))?;
+    assert!(output.starts_with("\nfrom reactpyx import create_element\ndef TestComponent"));
+    assert!(output.contains(r#"create_element("div", {"class": "test"}, ["#));
+    assert!(output.contains(r#"create_element("h1", {}, ["Hello World"])"#));
+
+    println!("✅ Compilation test successful");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_source_maps_are_written_when_enabled() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    write_project(
+        project_root,
+        Some(r#"{"compilerOptions": {"sourceMaps": true}}"#),
+        &[("App.pyx", "def App():\n    return <p>Hi</p>\n")],
+    )?;
+
+    let CompileSummary { errors, .. } = _core::compiler::compile_all_pyx(
+        project_root.to_str().unwrap(),
+        "pyx.config.json",
+        "python",
+    )
+    .await?;
+    assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+
+    let build_dir = project_root.join("build");
+    let output = fs::read_to_string(build_dir.join("components").join("App.py"))?;
+    assert!(output.ends_with("# sourceMappingURL=App.py.map\n"));
+
+    let map: serde_json::Value = serde_json::from_str(&fs::read_to_string(
+        build_dir.join("components").join("App.py.map"),
+    )?)?;
+    assert_eq!(map["version"], 3);
+    assert_eq!(map["file"], "App.py");
+    assert_eq!(map["sources"][0], "../../src/App.pyx");
+    // The runtime import occupies line 1, so `def App():` maps from line 2
+    assert!(map["mappings"].as_str().unwrap().starts_with(";AAAA;"));
+
+    assert!(build_dir.join("bundle.js.map").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_outputs_mirror_the_src_tree() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    write_project(
+        project_root,
+        None,
+        &[
+            (
+                "components/Home.pyx",
+                "def Home():\n    return <a href=\"/\">Home</a>\n",
+            ),
+            (
+                "pages/Home.pyx",
+                "import json\nfrom components.Home import Home as HomeLink\n\n\
+                 def Home():\n    return <main><HomeLink /></main>\n",
+            ),
+            (
+                "App.pyx",
+                "import json, pages.Home as home\nfrom pages import Home\n\n\
+                 def App():\n    return <home.Home />\n",
+            ),
+        ],
+    )?;
+
+    let CompileSummary {
+        compiled, errors, ..
+    } = _core::compiler::compile_all_pyx(
+        project_root.to_str().unwrap(),
+        "pyx.config.json",
+        "python",
+    )
+    .await?;
+    assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+    assert_eq!(compiled.len(), 3);
 
-    // Since we are in integration tests, we might not have direct access to internal modules easily
-    // without proper pub visibility. For now, we'll simulate the check by asserting file creation
-    // if we were calling the actual function.
+    let build_dir = project_root.join("build").join("components");
+    for package in ["", "pages", "components"] {
+        assert!(build_dir.join(package).join("__init__.py").exists());
+    }
+    let page = fs::read_to_string(build_dir.join("pages").join("Home.py"))?;
+    assert!(page.contains("import json\nfrom ..components.Home import Home as HomeLink\n"));
+    let app = fs::read_to_string(build_dir.join("App.py"))?;
+    assert!(app.contains("import json; from .pages import Home as home\nfrom .pages import Home\n"));
+    assert!(build_dir.join("components").join("Home.py").exists());
+    Ok(())
+}
 
-    // Ideally:
-    // reactpyx::compiler::compile_all_pyx(project_root.to_str().unwrap(), "pyx.config.json", "python").await?;
+#[tokio::test]
+async fn test_output_collisions_fail_both_files() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    let component = "def Page():\n    return <p>Hi</p>\n";
+    write_project(
+        project_root,
+        None,
+        &[
+            ("pages/Home.pyx", component),
+            ("pages.Home.pyx", component),
+            ("About.pyx", component),
+        ],
+    )?;
+
+    let CompileSummary {
+        compiled, errors, ..
+    } = _core::compiler::compile_all_pyx(
+        project_root.to_str().unwrap(),
+        "pyx.config.json",
+        "python",
+    )
+    .await?;
+    assert_eq!(compiled.len(), 1);
+    assert!(compiled[0].ends_with("About.pyx"));
+    assert_eq!(errors.len(), 2);
+    for error in &errors {
+        assert!(error.error.to_string().contains("both compile to"));
+    }
+    assert!(!project_root
+        .join("build")
+        .join("components")
+        .join("pages")
+        .join("Home.py")
+        .exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_css_bundle_collects_every_component() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    write_project(
+        project_root,
+        Some(r#"{"compilerOptions": {"cssSplit": true}}"#),
+        &[
+            (
+                "App.pyx",
+                "def App():\n    return <div><style>.box { margin: 0 } .app { color: red }</style></div>\n",
+            ),
+            (
+                "components/Card.pyx",
+                "def Card():\n    return <div><style>.card { color: blue } .box { margin: 0 }</style></div>\n",
+            ),
+        ],
+    )?;
+
+    for _ in 0..3 {
+        let CompileSummary { errors, .. } = _core::compiler::compile_all_pyx(
+            project_root.to_str().unwrap(),
+            "pyx.config.json",
+            "python",
+        )
+        .await?;
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+
+        // App.pyx sorts first, and the shared rule is kept where it comes last
+        let bundle = fs::read_to_string(project_root.join("build").join("styles.css"))?;
+        assert_eq!(bundle, ".app{color:red}.card{color:#00f}.box{margin:0}");
+    }
+
+    let styles_dir = project_root.join("build").join("styles");
+    assert_eq!(
+        fs::read_to_string(styles_dir.join("components").join("Card.css"))?,
+        ".card{color:#00f}.box{margin:0}"
+    );
+    assert!(styles_dir.join("App.css").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unchanged_files_are_not_recompiled() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    let root = project_root.to_str().unwrap();
+    let build = || _core::compiler::compile_all_pyx(root, "pyx.config.json", "python");
+
+    write_project(
+        project_root,
+        None,
+        &[
+            (
+                "App.pyx",
+                "def App():\n    return <p>Hi<style>p { color: red }</style></p>\n",
+            ),
+            ("About.pyx", "def About():\n    return <p>About</p>\n"),
+        ],
+    )?;
+    let summary = build().await?;
+    assert_eq!((summary.compiled.len(), summary.unchanged.len()), (2, 0));
+    assert!(project_root
+        .join("build")
+        .join(".reactpyx-cache.json")
+        .exists());
+
+    let summary = build().await?;
+    assert_eq!((summary.compiled.len(), summary.unchanged.len()), (0, 2));
+    // Cached files still contribute their styles
+    let bundle = fs::read_to_string(project_root.join("build").join("styles.css"))?;
+    assert_eq!(bundle, "p{color:red}");
+
+    write_project(
+        project_root,
+        None,
+        &[("About.pyx", "def About():\n    return <p>About us</p>\n")],
+    )?;
+    fs::remove_file(project_root.join("build").join("components").join("App.py"))?;
+    let summary = build().await?;
+    assert_eq!((summary.compiled.len(), summary.unchanged.len()), (2, 0));
+
+    // The compiler options are part of the key
+    write_project(
+        project_root,
+        Some(r#"{"compilerOptions": {"sourceMaps": true}}"#),
+        &[],
+    )?;
+    let summary = build().await?;
+    assert_eq!((summary.compiled.len(), summary.unchanged.len()), (2, 0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_config_sets_the_output_directory_and_minification() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    write_project(
+        project_root,
+        Some(
+            r#"{
+            "outDir": "dist/app",
+            "compilerOptions": {"minify": {"css": false}, "concurrency": 1}
+        }"#,
+        ),
+        &[(
+            "main.pyx",
+            "def MainApp():\n    return <p>Hi<style>p { color: red } a { color: blue }</style></p>\n",
+        )],
+    )?;
+
+    let root = project_root.to_str().unwrap();
+    let CompileSummary { errors, .. } =
+        _core::compiler::compile_all_pyx(root, "pyx.config.json", "python").await?;
+    assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+
+    let out_dir = project_root.join("dist").join("app");
+    assert!(out_dir.join("components").join("main.py").exists());
+    assert!(out_dir.join(".reactpyx-cache.json").exists());
+    assert!(!project_root.join("build").exists());
+    let bundle = fs::read_to_string(out_dir.join("styles.css"))?;
+    assert_eq!(bundle, "p {\n  color: red;\n}\na {\n  color: #00f;\n}");
+
+    let config = Config::load_project(root, "pyx.config.json")?;
+    _core::compiler::check_entry(root, &config)?;
+    let config = Config {
+        entry_function: "App".to_string(),
+        ..config
+    };
+    let error = _core::compiler::check_entry(root, &config).unwrap_err();
+    assert!(error.to_string().contains("does not define `App`"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_invalid_python_keeps_the_last_good_build() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    let root = project_root.to_str().unwrap();
+
+    write_project(
+        project_root,
+        None,
+        &[(
+            "App.pyx",
+            "def App():\n    return <p><style>.hi { color: red }</style>Hi</p>\n",
+        )],
+    )?;
+    let CompileSummary { errors, .. } =
+        _core::compiler::compile_all_pyx(root, "pyx.config.json", "python").await?;
+    assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+    let output_path = project_root.join("build").join("components").join("App.py");
+    let styles_path = project_root.join("build").join("styles.css");
+    let good = fs::read_to_string(&output_path)?;
+    let good_styles = fs::read_to_string(&styles_path)?;
+    assert_eq!(good_styles, ".hi{color:red}");
+
+    // The JSX is fine, but the Python around it is not
+    write_project(
+        project_root,
+        None,
+        &[(
+            "App.pyx",
+            "def App():\n    return <p><style>.hi { color: blue }</style>Hi</p> +\n",
+        )],
+    )?;
+    let CompileSummary {
+        compiled, errors, ..
+    } = _core::compiler::compile_all_pyx(root, "pyx.config.json", "python").await?;
+    assert!(compiled.is_empty());
+    assert_eq!(errors.len(), 1);
+    let diagnostic = errors[0].diagnostic().expect("a structured diagnostic");
+    assert_eq!(diagnostic.code, "PYX016");
+    assert_eq!(diagnostic.line, 2);
+    assert!(diagnostic.file.as_deref().unwrap().ends_with("App.pyx"));
+
+    assert_eq!(fs::read_to_string(&output_path)?, good);
+    // The styles go with the Python left in place
+    assert_eq!(fs::read_to_string(&styles_path)?, good_styles);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_accessibility_warnings_fail_strict_builds() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    let root = project_root.to_str().unwrap();
+
+    write_project(
+        project_root,
+        None,
+        &[(
+            "Logo.pyx",
+            "def Logo():\n    return <a href=\"/\"><style>.logo { margin: 0 }</style><img src=\"logo.png\" /></a>\n",
+        )],
+    )?;
+    let summary = _core::compiler::compile_all_pyx(root, "pyx.config.json", "python").await?;
+    assert!(
+        summary.errors.is_empty(),
+        "unexpected errors: {:?}",
+        summary.errors
+    );
+    assert_eq!(summary.compiled.len(), 1);
+    assert_eq!(summary.warnings.len(), 1);
+    assert_eq!(summary.warnings[0].code, "A11Y001");
+    assert_eq!(summary.warnings[0].severity, Severity::Warning);
+
+    let output_path = project_root
+        .join("build")
+        .join("components")
+        .join("Logo.py");
+    fs::remove_file(&output_path)?;
+    let options = BuildOptions {
+        strict: true,
+        ..BuildOptions::default()
+    };
+    let summary =
+        _core::compiler::compile_all_pyx_with(root, &Config::default(), "python", options).await?;
+    assert!(summary.compiled.is_empty());
+    assert!(summary.warnings.is_empty());
+    let diagnostic = summary.errors[0].diagnostic().unwrap();
+    assert_eq!(
+        (diagnostic.code, diagnostic.severity),
+        ("A11Y001", Severity::Error)
+    );
+    assert!(!output_path.exists());
+    // The component's styles are still bundled
+    let styles = fs::read_to_string(project_root.join("build").join("styles.css"))?;
+    assert_eq!(styles, ".logo{margin:0}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_defines_are_substituted_and_dead_branches_removed() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    let root = project_root.to_str().unwrap();
+    write_project(
+        project_root,
+        Some(r#"{"define": {"API_URL": "https://api.example.com"}}"#),
+        &[(
+            "App.pyx",
+            concat!(
+                "def App():\n",
+                "    if __DEV__:\n",
+                "        print(\"debug\", API_URL)\n",
+                "    else:\n",
+                "        track(f\"{API_URL}/hit\")\n",
+                "    options.API_URL = API_URL\n",
+                "    return <p>{API_URL}</p>\n",
+            ),
+        )],
+    )?;
+    let output_path = project_root.join("build").join("components").join("App.py");
 
-    // Check if output files exist (this part would fail until we actually link the library code)
-    // let build_dir = project_root.join("build").join("components");
-    // assert!(build_dir.join("TestComponent.py").exists());
+    let config = Config::load_project(root, "pyx.config.json")?;
+    let summary =
+        _core::compiler::compile_all_pyx_with(root, &config, "python", BuildOptions::default())
+            .await?;
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    let development = fs::read_to_string(&output_path)?;
+    assert!(development
+        .contains("    if True:\n        print(\"debug\", \"https://api.example.com\")\n\n\n"));
+    assert!(!development.contains("track("));
 
-    println!("✅ Compilation test setup successful");
+    let config = Config::load_project_env(root, "pyx.config.json", "production")?;
+    let summary =
+        _core::compiler::compile_all_pyx_with(root, &config, "python", BuildOptions::default())
+            .await?;
+    assert_eq!(summary.compiled.len(), 1, "the defines changed");
+    let production = fs::read_to_string(&output_path)?;
+    assert!(!production.contains("debug"), "{}", production);
+    assert!(production.contains("    if False:\n        pass\n    else:\n        track(f\"{'https://api.example.com'}/hit\")\n"));
+    assert!(production.contains("options.API_URL = \"https://api.example.com\""));
+    // Lines keep their numbers
+    assert_eq!(production.lines().count(), development.lines().count());
     Ok(())
 }
@@ -0,0 +1,321 @@
+use _core::config::{BrowserTargets, Config, JsMinifyOptions, JsTarget, Minify};
+use anyhow::Result;
+use std::fs;
+use tempfile::TempDir;
+
+fn load(content: &str) -> Result<Config> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("pyx.config.json");
+    fs::write(&config_path, content)?;
+    Config::load(config_path.to_str().unwrap())
+}
+
+#[test]
+fn test_config_reads_every_option() -> Result<()> {
+    let config = load(
+        r#"{
+            "port": 3000,
+            "entry": "./src/index.pyx",
+            "entryFunction": "Root",
+            "publicPath": "./static",
+            "outDir": "dist",
+            "compilerOptions": {
+                "minify": false,
+                "sourceMaps": true,
+                "concurrency": 4,
+                "jsMinify": {"target": "es2017", "keepFnames": true, "dropConsole": true},
+                "targets": {"chrome": 100, "safari": "15.4", "iosSafari": "15.4.1"}
+            },
+            "lint": {"rules": {"jsx-key": "off"}}
+        }"#,
+    )?;
+    assert_eq!(config.port, 3000);
+    assert_eq!(config.entry_function, "Root");
+    assert_eq!(config.out_dir("/app"), std::path::Path::new("/app/dist"));
+    assert_eq!(
+        config.compiler_options.minify,
+        Minify {
+            css: false,
+            js: false
+        }
+    );
+    assert!(config.source_maps());
+    assert_eq!(config.concurrency(), 4);
+    assert_eq!(
+        config.compiler_options.js_minify,
+        JsMinifyOptions {
+            target: JsTarget::Es2017,
+            keep_fnames: true,
+            drop_console: true,
+            ..JsMinifyOptions::default()
+        }
+    );
+    assert_eq!(
+        config.targets(),
+        BrowserTargets {
+            chrome: Some(100 << 16),
+            safari: Some(15 << 16 | 4 << 8),
+            ios_safari: Some(15 << 16 | 4 << 8 | 1),
+            ..BrowserTargets::default()
+        }
+    );
+
+    let defaults = load("{}")?;
+    assert_eq!(defaults.port, 8000);
+    assert_eq!(defaults.out_dir, "build");
+    assert_eq!(defaults.compiler_options.minify, Minify::default());
+    assert_eq!(defaults.targets(), BrowserTargets::modern());
+    Ok(())
+}
+
+#[test]
+fn test_config_rejects_unknown_keys_and_bad_values() {
+    let error = |content: &str| format!("{:#}", load(content).unwrap_err());
+
+    let message = error(r#"{"outdir": "dist"}"#);
+    assert!(message.contains("unknown field `outdir`"), "{}", message);
+    assert!(message.contains("`outDir`"), "{}", message);
+
+    let message = error(r#"{"compilerOptions": {"minify": {"html": true}}}"#);
+    assert!(message.contains("unknown field `html`"), "{}", message);
+
+    let message = error(r#"{"compilerOptions": {"targets": {"chrome": "latest"}}}"#);
+    assert!(
+        message.contains("invalid browser version `latest`"),
+        "{}",
+        message
+    );
+
+    let message = error(r#"{"compilerOptions": {"jsMinify": {"target": "es6"}}}"#);
+    assert!(message.contains("unknown variant `es6`"), "{}", message);
+
+    let message = error(r#"{"compilerOptions": {"concurrency": 0}}"#);
+    assert!(message.contains("at least 1"), "{}", message);
+
+    let message = error(r#"{"entryFunction": "main-app"}"#);
+    assert!(message.contains("`entryFunction`"), "{}", message);
+
+    let message = error(r#"{"lint": {"rules": {"no-such-rule": "error"}}}"#);
+    assert!(message.contains("no-such-rule"), "{}", message);
+}
+
+#[test]
+fn test_environment_overlays_merge_over_the_config() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("pyx.config.json");
+    fs::write(
+        &config_path,
+        r#"{
+            "outDir": "build",
+            "compilerOptions": {"sourceMaps": true, "minify": false},
+            "define": {"API_URL": "http://localhost:8000"},
+            "env": {
+                "production": {
+                    "compilerOptions": {"minify": true},
+                    "define": {"API_URL": "https://api.example.com"}
+                },
+                "staging": {"outDir": "staging"}
+            }
+        }"#,
+    )?;
+    let path = config_path.to_str().unwrap();
+
+    let development = Config::load(path)?;
+    assert_eq!(development.environment, "development");
+    assert_eq!(development.defines()["API_URL"], "http://localhost:8000");
+    assert_eq!(development.defines()["__DEV__"], true);
+    assert!(!development.compiler_options.minify.js);
+
+    let production = Config::load_env(path, "production")?;
+    assert_eq!(production.defines()["API_URL"], "https://api.example.com");
+    assert_eq!(production.defines()["__DEV__"], false);
+    assert!(production.compiler_options.minify.js);
+    assert!(production.source_maps());
+    assert_eq!(production.out_dir, "build");
+
+    let staging = Config::load_env(path, "staging")?;
+    assert_eq!(staging.out_dir, "staging");
+    assert_eq!(staging.defines()["API_URL"], "http://localhost:8000");
+
+    let message = format!("{:#}", Config::load_env(path, "qa").unwrap_err());
+    assert!(
+        message.contains("development, production, staging"),
+        "{}",
+        message
+    );
+
+    let error = |content: &str| format!("{:#}", load(content).unwrap_err());
+    let message = error(r#"{"env": {"production": {"outdir": "dist"}}}"#);
+    assert!(message.contains("`env.production`"), "{}", message);
+    assert!(message.contains("unknown field `outdir`"), "{}", message);
+    let message = error(r#"{"define": {"API_URL": ["a"]}}"#);
+    assert!(message.contains("`define.API_URL`"), "{}", message);
+    Ok(())
+}
+
+#[test]
+fn test_toml_config_matches_json() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path().to_str().unwrap();
+    fs::write(
+        temp_dir.path().join("pyx.config.toml"),
+        r#"
+# Served on another port
+port = 3_000
+entryFunction = 'Root'
+"outDir" = "dist"   # quoted key
+
+[compilerOptions]
+minify.css = false
+targets = { chrome = 100, iosSafari = "15.4.1" }
+
+[define]
+API_URL = """
+https://api.example.com\
+  /v1"""
+RETRIES = 3
+RATIO = 0.5e1
+DEBUG = false
+
+[lint.rules]
+jsx-key = "off"
+
+[env.staging]
+outDir = "staging"
+"#,
+    )?;
+
+    let config = Config::load_project(root, "pyx.config.json")?;
+    assert_eq!(config.port, 3000);
+    assert_eq!(config.entry_function, "Root");
+    assert_eq!(config.out_dir, "dist");
+    assert_eq!(
+        config.compiler_options.minify,
+        Minify {
+            css: false,
+            js: true
+        }
+    );
+    assert_eq!(
+        config.targets(),
+        BrowserTargets {
+            chrome: Some(100 << 16),
+            ios_safari: Some(15 << 16 | 4 << 8 | 1),
+            ..BrowserTargets::default()
+        }
+    );
+    let defines = config.defines();
+    assert_eq!(defines["API_URL"], "https://api.example.com/v1");
+    assert_eq!(defines["RETRIES"], 3);
+    assert_eq!(defines["RATIO"], 5.0);
+    assert_eq!(defines["DEBUG"], false);
+    assert_eq!(config.file_name(), "pyx.config.toml");
+
+    let staging = Config::load_project_env(root, "pyx.config.json", "staging")?;
+    assert_eq!(staging.out_dir, "staging");
+
+    fs::write(temp_dir.path().join("pyx.config.json"), "{}")?;
+    let message = format!(
+        "{:#}",
+        Config::load_project(root, "pyx.config.json").unwrap_err()
+    );
+    assert!(message.contains("keep one of them"), "{}", message);
+    Ok(())
+}
+
+#[test]
+fn test_toml_errors_have_positions() {
+    let error = |content: &str| {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("pyx.config.toml");
+        fs::write(&config_path, content).unwrap();
+        format!(
+            "{:#}",
+            Config::load(config_path.to_str().unwrap()).unwrap_err()
+        )
+    };
+
+    let message = error("port = 8000\nentry = \"src/main.pyx\n");
+    assert!(message.contains("at line 2, column 22"), "{}", message);
+    let message = error("[compilerOptions]\nminify = true\nminify = false\n");
+    assert!(message.contains("at line 3, column 1"), "{}", message);
+    assert!(message.contains("duplicate key"), "{}", message);
+    let message = error("[lint]\n[lint]\n");
+    assert!(message.contains("at line 2, column 2"), "{}", message);
+    let message = error("port = 08000\n");
+    assert!(message.contains("unexpected leading zero"), "{}", message);
+    let message = error("[define]\nBUILT = 2024-01-01\n");
+    assert!(message.contains("`define.BUILT`"), "{}", message);
+    let message = error("outdir = \"dist\"\n");
+    assert!(message.contains("unknown field `outdir`"), "{}", message);
+}
+
+#[test]
+fn test_extends_merges_over_the_base_config() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let app_dir = temp_dir.path().join("apps").join("web");
+    fs::create_dir_all(&app_dir)?;
+    fs::write(
+        temp_dir.path().join("base.toml"),
+        "port = 9000\n\n[compilerOptions]\nsourceMaps = true\nminify = false\n\n[define]\nAPI_URL = \"http://localhost\"\n",
+    )?;
+    fs::write(
+        temp_dir.path().join("apps").join("shared.json"),
+        r#"{"extends": "../base.toml", "compilerOptions": {"minify": {"css": true, "js": false}}}"#,
+    )?;
+    fs::write(
+        app_dir.join("pyx.config.json"),
+        r#"{
+            "extends": "../shared.json",
+            "outDir": "dist",
+            "env": {"production": {"define": {"API_URL": "https://example.com"}}}
+        }"#,
+    )?;
+    let root = app_dir.to_str().unwrap();
+
+    let config = Config::load_project(root, "pyx.config.json")?;
+    assert_eq!(config.port, 9000);
+    assert_eq!(config.out_dir, "dist");
+    assert!(config.source_maps());
+    assert_eq!(
+        config.compiler_options.minify,
+        Minify {
+            css: true,
+            js: false
+        }
+    );
+    assert_eq!(config.defines()["API_URL"], "http://localhost");
+    let files: Vec<String> = config
+        .files
+        .iter()
+        .map(|file| file.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(files, ["base.toml", "shared.json", "pyx.config.json"]);
+
+    let production = Config::load_project_env(root, "pyx.config.json", "production")?;
+    assert_eq!(production.defines()["API_URL"], "https://example.com");
+    assert_eq!(production.port, 9000);
+
+    // Errors name the file they are in
+    fs::write(
+        temp_dir.path().join("base.toml"),
+        "[compilerOptions]\nminfy = true\n",
+    )?;
+    let message = format!(
+        "{:#}",
+        Config::load_project(root, "pyx.config.json").unwrap_err()
+    );
+    assert!(message.contains("base.toml"), "{}", message);
+    assert!(message.contains("unknown field `minfy`"), "{}", message);
+
+    fs::write(
+        temp_dir.path().join("base.toml"),
+        "extends = \"apps/web/pyx.config.json\"\n",
+    )?;
+    let message = format!(
+        "{:#}",
+        Config::load_project(root, "pyx.config.json").unwrap_err()
+    );
+    assert!(message.contains("extends itself"), "{}", message);
+    Ok(())
+}
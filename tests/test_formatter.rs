@@ -0,0 +1,74 @@
+use _core::formatter::format_pyx;
+
+#[test]
+fn test_long_elements_are_broken_and_empty_ones_self_closed() {
+    let source = r#"def App(props):
+    total = sum(  props["items"]  )
+    return <div className='app' id="main"><h1>Total: {total}</h1><button onClick={lambda e: props["reset"]()} disabled>Reset all of the items</button><span></span><br/>
+    <p>
+       Hello
+       world
+    </p></div>
+"#;
+    let expected = r#"def App(props):
+    total = sum(  props["items"]  )
+    return <div className="app" id="main">
+        <h1>Total: {total}</h1>
+        <button onClick={lambda e: props["reset"]()} disabled>
+            Reset all of the items
+        </button>
+        <span />
+        <br />
+        <p>Hello world</p>
+    </div>
+"#;
+    let formatted = format_pyx(source).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(format_pyx(&formatted).unwrap(), formatted);
+}
+
+#[test]
+fn test_attributes_wrap_one_per_line() {
+    let source = r#"x = <input type="text" name="description" placeholder="Describe the item" value={value} {...rest}/>
+"#;
+    let expected = r#"x = <input
+    type="text"
+    name="description"
+    placeholder="Describe the item"
+    value={value}
+    {...rest}
+/>
+"#;
+    assert_eq!(format_pyx(source).unwrap(), expected);
+}
+
+#[test]
+fn test_whitespace_sensitive_text_and_raw_text_are_kept() {
+    // Breaking the children would trim the spaces around "and"
+    let source = r#"a = <p><b>Bold</b> and <i>italic text that makes this element far too long to fit</i></p>
+b = <style>
+    .x { color: red; }
+</style>
+"#;
+    assert_eq!(format_pyx(source).unwrap(), source);
+}
+
+#[test]
+fn test_comment_children_are_kept() {
+    let source = r#"x = <ul>
+  {# the first item
+      # is special
+  }
+  <li>first item in a list that is long enough to need its own lines</li></ul>
+"#;
+    let expected = r#"x = <ul>
+    {# the first item
+    # is special
+    }
+    <li>first item in a list that is long enough to need its own lines</li>
+</ul>
+"#;
+    let formatted = format_pyx(source).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(format_pyx(&formatted).unwrap(), formatted);
+}
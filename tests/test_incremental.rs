@@ -0,0 +1,103 @@
+use _core::incremental::{OutputEdit, PyxDocument, TextEdit};
+use _core::jsx_transformer::transform_pyx_module;
+
+const SOURCE: &str = r#"from reactpyx import use_state
+
+def Counter():
+    count, set_count = use_state(0)
+    return <button class="counter">{count}</button>
+
+def Page():
+    return <main>
+        <Counter />
+    </main>
+"#;
+
+fn replace(document: &mut PyxDocument, old: &str, new: &str) -> Vec<OutputEdit> {
+    let start = document.source().find(old).unwrap();
+    let edit = TextEdit {
+        start,
+        end: start + old.len(),
+        text: new.to_string(),
+    };
+    document.apply_edits(&[edit]).unwrap()
+}
+
+fn full_transform(document: &PyxDocument) -> String {
+    transform_pyx_module(document.source(), "app").unwrap().code
+}
+
+#[test]
+fn test_edits_match_a_full_transform() {
+    let mut document = PyxDocument::new(SOURCE, "app").unwrap();
+    assert_eq!(document.output(), full_transform(&document));
+
+    let previous = document.output();
+    let changes = replace(
+        &mut document,
+        "<Counter />",
+        "<Counter />\n        <p>{1}</p>",
+    );
+    assert_eq!(document.output(), full_transform(&document));
+    assert_eq!(changes.len(), 1);
+    let change = changes[0];
+    assert_eq!(previous[..change.start], document.output()[..change.start]);
+    assert_eq!(
+        previous[change.old_end..],
+        document.output()[change.new_end..]
+    );
+    assert!(document.output()[change.start..change.new_end].contains("\"p\""));
+
+    // A second hook call renumbers the ones after it
+    replace(
+        &mut document,
+        "    count, set_count",
+        "    step, _ = use_state(1)\n    count, set_count",
+    );
+    assert_eq!(document.output(), full_transform(&document));
+    assert!(document
+        .output()
+        .contains("use_state(0, hook_id=\"app:Counter:1\")"));
+}
+
+#[test]
+fn test_quotes_that_change_the_host_structure_reach_later_statements() {
+    let mut document = PyxDocument::new(SOURCE, "app").unwrap();
+
+    // The open string swallows `Page`, which can no longer be compiled
+    let start = document.source().find("def Page").unwrap();
+    let edit = TextEdit {
+        start,
+        end: start,
+        text: "x = '''\n".to_string(),
+    };
+    assert!(document.apply_edits(&[edit]).is_err());
+    assert!(document.is_stale());
+
+    replace(&mut document, "x = '''\n", "x = '''</p>'''\n");
+    assert!(!document.is_stale());
+    assert_eq!(document.output(), full_transform(&document));
+}
+
+#[test]
+fn test_errors_keep_the_last_good_output() {
+    let mut document = PyxDocument::new(SOURCE, "app").unwrap();
+    let good = document.output();
+
+    let start = document.source().find("</main>").unwrap();
+    let error = document
+        .apply_edits(&[TextEdit {
+            start,
+            end: start + "</main>".len(),
+            text: "</mian>".to_string(),
+        }])
+        .unwrap_err();
+    assert!(error.to_string().contains("Mismatched closing tag"));
+    assert!(document.is_stale());
+    assert_eq!(document.output(), good);
+
+    let changes = replace(&mut document, "</mian>", "</main>");
+    assert!(changes.is_empty());
+    assert!(!document.is_stale());
+    assert_eq!(document.output(), good);
+}
@@ -0,0 +1,219 @@
+use _core::jsx_transformer::{transform_pyx, transform_pyx_module};
+
+#[test]
+fn test_less_than_comparisons_are_not_tags() {
+    let source = "if a <b:\n    c = x<y and <p>ok</p>\n";
+    let output = transform_pyx(source).unwrap();
+
+    assert_eq!(
+        output,
+        "if a <b:\n    c = x<y and create_element(\"p\", {}, [\"ok\"])\n"
+    );
+}
+
+#[test]
+fn test_strings_and_comments_are_left_alone() {
+    let source = r#"s = "<div>" + '<span a="1">'  # <p>not a tag</p>
+doc = """
+<section>
+"""
+f = f"{x!r:>{width}}<b>{'}'}"
+"#;
+    assert_eq!(transform_pyx(source).unwrap(), source);
+}
+
+#[test]
+fn test_nested_expressions_and_elements() {
+    let source = r#"view = <ul data={ {"a": "}"} }>{[<li>{i}</li> for i in items]}</ul>"#;
+    let output = transform_pyx(source).unwrap();
+
+    assert_eq!(
+        output,
+        r#"view = create_element("ul", {"data": ( {"a": "}"} )}, [([create_element("li", {}, [(i)]) for i in items])])"#
+    );
+}
+
+#[test]
+fn test_line_numbers_are_preserved() {
+    let source = r#"def App():
+    return (
+        <main className="app">
+            <h1>Title</h1>
+        </main>
+    )
+
+x = 1
+"#;
+    let output = transform_pyx(source).unwrap();
+
+    assert_eq!(output.lines().count(), source.lines().count());
+    assert_eq!(output.lines().nth(7), Some("x = 1"));
+}
+
+#[test]
+fn test_style_content_is_raw_text() {
+    let source = "s = <style>.a { color: red; }</style>";
+    let output = transform_pyx(source).unwrap();
+
+    assert_eq!(
+        output,
+        r#"s = create_element("style", {}, [".a { color: red; }"])"#
+    );
+}
+
+#[test]
+fn test_text_whitespace_is_collapsed() {
+    let source = r#"x = (
+    <button onClick={increment}>
+        count is {count}
+        and
+          more &amp; more
+    </button>
+)"#;
+    let output = transform_pyx(source).unwrap();
+
+    assert_eq!(
+        output,
+        r#"x = (
+    create_element("button", {"onClick": (increment)}, [
+        "count is ", (count),
+        "and more & more"
+
+    ])
+)"#
+    );
+}
+
+#[test]
+fn test_inline_text_keeps_inner_spaces() {
+    let output = transform_pyx(r#"x = <p> Hello  <b>{name}</b> ! </p>"#).unwrap();
+
+    assert_eq!(
+        output,
+        r#"x = create_element("p", {}, [" Hello  ", create_element("b", {}, [(name)]), " ! "])"#
+    );
+}
+
+#[test]
+fn test_mismatched_and_unclosed_tags_are_errors() {
+    let err = transform_pyx("x = <div><span></div>").unwrap_err();
+    assert_eq!(err.code, "PYX007");
+    assert!(err.message.contains("expected </span>"));
+    assert_eq!((err.line, err.column), (1, 18));
+    assert_eq!((err.span.start, err.span.end), (17, 20));
+
+    let err = transform_pyx("def f():\n    return <div>\n").unwrap_err();
+    assert_eq!(err.code, "PYX005");
+    assert!(err.message.contains("Unclosed element <div>"));
+    assert_eq!((err.line, err.column), (2, 12));
+    assert!(err.help.unwrap().contains("</div>"));
+}
+
+#[test]
+fn test_diagnostic_renders_code_frame() {
+    let source = "def App():\n    return <ul><li>a</ul>\n";
+    let err = transform_pyx(source).unwrap_err().with_file("src/App.pyx");
+
+    assert_eq!(
+        err.to_string(),
+        "src/App.pyx:2:23: error[PYX007]: Mismatched closing tag: expected </li>, found </ul>"
+    );
+    assert_eq!(
+        err.render(source),
+        r#"error[PYX007]: Mismatched closing tag: expected </li>, found </ul>
+  --> src/App.pyx:2:23
+    1 | def App():
+  > 2 |     return <ul><li>a</ul>
+      |                       ^^
+  = help: <li> must be closed before </ul>
+"#
+    );
+}
+
+#[test]
+fn test_fragments() {
+    let output = transform_pyx("x = <><td>a</td>{b}</>").unwrap();
+    assert_eq!(
+        output,
+        r##"x = create_element("#fragment", {}, [create_element("td", {}, ["a"]), (b)])"##
+    );
+
+    let err = transform_pyx("x = <><p></>").unwrap_err();
+    assert_eq!(err.code, "PYX007");
+    assert!(err.message.contains("expected </p>, found </>"));
+
+    let err = transform_pyx("x = <>\n").unwrap_err();
+    assert_eq!(err.code, "PYX005");
+    assert!(err.message.contains("Unclosed element <>"));
+}
+
+#[test]
+fn test_attribute_grammar() {
+    let output =
+        transform_pyx(r##"x = <input data-id="7" aria-label='Name' xlink:href="#a" disabled {...props} className="c" />"##)
+            .unwrap();
+    assert_eq!(
+        output,
+        r##"x = create_element("input", {"data-id": "7", "aria-label": "Name", "xlink:href": "#a", "disabled": True, **(props), "class": "c"}, [])"##
+    );
+
+    let output =
+        transform_pyx(r#"x = <Button { ...base } onClick={lambda e: {"}": e}} style={ {"a": {"b": "}"}} } hidden>ok</Button>"#)
+            .unwrap();
+    assert_eq!(
+        output,
+        r#"x = create_element(Button, {**(base ), "onClick": (lambda e: {"}": e}), "style": ( {"a": {"b": "}"}} ), "hidden": True}, ["ok"])"#
+    );
+
+    let err = transform_pyx("x = <div {props} />").unwrap_err();
+    assert_eq!(err.code, "PYX014");
+}
+
+#[test]
+fn test_component_tags_reference_callables() {
+    let output =
+        transform_pyx(r#"x = <Layout><ui.Button label="Go" /><my-icon /></Layout>"#).unwrap();
+    assert_eq!(
+        output,
+        r#"x = create_element(Layout, {}, [create_element(ui.Button, {"label": "Go"}, []), create_element("my-icon", {}, [])])"#
+    );
+
+    let err = transform_pyx("x = <Big-Box />").unwrap_err();
+    assert_eq!(err.code, "PYX015");
+}
+
+#[test]
+fn test_hook_calls_get_call_site_ids() {
+    let source = r#"def Counter(props):
+    count, set_count = use_state(0)
+    state, dispatch = use_reducer(
+        reducer,
+        [],  # todos
+    )
+
+    def increment():
+        set_count.set(count + 1)
+
+    use_effect_with_deps(lambda deps: None, [count])
+    return <p>{count}</p>
+
+def App():
+    legacy, _ = use_state("app", "legacy", None)
+    return <Counter />
+"#;
+    let output = transform_pyx_module(source, "components.Counter").unwrap();
+
+    assert!(output
+        .code
+        .contains(r#"use_state(0, hook_id="components.Counter:Counter:0")"#));
+    assert!(output
+        .code
+        .contains("# todos\n    hook_id=\"components.Counter:Counter:1\")"));
+    assert!(output.code.contains(
+        r#"use_effect_with_deps(lambda deps: None, [count], hook_id="components.Counter:Counter:2")"#
+    ));
+    assert!(output
+        .code
+        .contains(r#"use_state("app", "legacy", None, hook_id="components.Counter:App:0")"#));
+    assert_eq!(output.code.lines().count(), source.lines().count());
+}
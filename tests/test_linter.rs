@@ -0,0 +1,173 @@
+use _core::diagnostics::Severity;
+use _core::linter::{lint_pyx, LintConfig, RuleLevel};
+
+const SOURCE: &str = r#"from reactpyx import use_state
+
+def List(props):
+    if props["editable"]:
+        editing, set_editing = use_state(False)
+    for item in props["items"]:
+        use_state(item)
+    rows = [<li>{item}</li> for item in props["items"]]
+    keyed = [<li key={item}>{item}</li> for item in props["items"]]
+    return <ul>
+        <li key="a">A</li>
+        <li key="a">B</li>
+        <b key="a">C</b>
+        <button onClick={reset()}>Reset</button>
+        <button onClick={lambda e: reset()}>Reset</button>
+    </ul>
+"#;
+
+fn rules(source: &str, config: &LintConfig) -> Vec<(&'static str, usize)> {
+    lint_pyx(source, config)
+        .unwrap()
+        .iter()
+        .map(|diagnostic| (diagnostic.code, diagnostic.line))
+        .collect()
+}
+
+#[test]
+fn test_rules_report_hook_and_jsx_mistakes() {
+    let diagnostics = lint_pyx(SOURCE, &LintConfig::default()).unwrap();
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.code, d.line, d.column, d.severity))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("rules-of-hooks", 5, 32, Severity::Error),
+            ("rules-of-hooks", 7, 9, Severity::Error),
+            ("jsx-key", 8, 13, Severity::Warning),
+            ("duplicate-key", 12, 13, Severity::Error),
+            ("duplicate-key", 13, 12, Severity::Error),
+            ("handler-call", 14, 26, Severity::Warning),
+        ]
+    );
+    assert_eq!(
+        diagnostics[0].message,
+        "`use_state` is called conditionally"
+    );
+    assert_eq!(
+        diagnostics[4].help.as_deref(),
+        Some("a sibling <li> on line 11 has the same key")
+    );
+    assert_eq!(
+        diagnostics[5].help.as_deref(),
+        Some("pass the function itself: `onClick={reset}`")
+    );
+}
+
+#[test]
+fn test_severities_come_from_the_config() {
+    let config: LintConfig = serde_json::from_str(
+        r#"{"rules": {"rules-of-hooks": "off", "jsx-key": "error", "duplicate-key": "warn"}}"#,
+    )
+    .unwrap();
+    assert_eq!(config.rules["duplicate-key"], RuleLevel::Warning);
+    let diagnostics = lint_pyx(SOURCE, &config).unwrap();
+    let found: Vec<_> = diagnostics.iter().map(|d| (d.code, d.severity)).collect();
+    assert_eq!(
+        found,
+        vec![
+            ("jsx-key", Severity::Error),
+            ("duplicate-key", Severity::Warning),
+            ("duplicate-key", Severity::Warning),
+            ("handler-call", Severity::Warning),
+        ]
+    );
+
+    let unknown: LintConfig =
+        serde_json::from_str(r#"{"rules": {"no-such-rule": "off"}}"#).unwrap();
+    assert!(unknown
+        .validate()
+        .unwrap_err()
+        .to_string()
+        .contains("no-such-rule"));
+}
+
+#[test]
+fn test_comments_suppress_rules() {
+    let source = r#"def App(props):
+    x = use_state(0) if props else None  # pyx-lint: disable=rules-of-hooks
+    # pyx-lint: disable-next-line
+    y = use_state(0) if props else None
+    z = use_state(0) if props else None  # pyx-lint: disable=jsx-key
+    return <p>
+        {# pyx-lint: disable-next-line=handler-call
+        }
+        <button onClick={go()}>Go</button>
+        <button onClick={go()}>Go</button>
+    </p>
+"#;
+    let config = LintConfig::default();
+    assert_eq!(
+        rules(source, &config),
+        vec![("rules-of-hooks", 5), ("handler-call", 10)]
+    );
+
+    let disabled = format!("# pyx-lint: disable-file=handler-call\n{}", source);
+    assert_eq!(rules(&disabled, &config), vec![("rules-of-hooks", 6)]);
+}
+
+#[test]
+fn test_hooks_in_nested_functions_are_reported() {
+    let source = r#"from reactpyx import use_state
+
+def Form(props):
+    value, set_value = use_state("")
+
+    def on_submit(event):
+        saved, set_saved = use_state(False)
+
+    def use_draft():
+        return use_state("")
+
+    draft, set_draft = use_draft()
+    return <form onSubmit={on_submit}>{value}</form>
+
+class Page:
+    def render(self):
+        title, set_title = use_state("")
+"#;
+    let diagnostics = lint_pyx(source, &LintConfig::default()).unwrap();
+    let found: Vec<_> = diagnostics.iter().map(|d| (d.code, d.line)).collect();
+    assert_eq!(found, vec![("rules-of-hooks", 7)]);
+    assert_eq!(
+        diagnostics[0].message,
+        "`use_state` is called inside a nested function"
+    );
+}
+
+#[test]
+fn test_hook_calls_sharing_a_state_slot_are_reported() {
+    let source = r#"from reactpyx import use_state, use_reducer
+
+def Counter(props):
+    count, set_count = use_state("Counter", "count", 0)
+    total, set_total = use_state("Counter", 'count', 0)
+    other, set_other = use_state("Counter", "other", 0)
+    state, dispatch = use_reducer("Counter", "count", reduce, {})
+    named, set_named = use_state(props["id"], "count", 0)
+    short, set_short = use_state(0)
+    return <p>{count}</p>
+"#;
+    let diagnostics = lint_pyx(source, &LintConfig::default()).unwrap();
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.code, d.line, d.column))
+        .collect();
+    assert_eq!(
+        found,
+        vec![("duplicate-key", 5, 45), ("duplicate-key", 7, 46)]
+    );
+    assert_eq!(
+        diagnostics[0].message,
+        "Duplicate key \"count\" of the component \"Counter\" in `use_state`"
+    );
+    assert_eq!(
+        diagnostics[1].help.as_deref(),
+        Some("the hook call on line 4 keeps its state under the same component id and key")
+    );
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use _core::compiler::{compile_all_pyx, compile_all_pyx_with, BuildOptions, CompileSummary};
+use _core::config::Config;
+use anyhow::Result;
+use common::write_project;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const HEADER: &str = "def Header():\n    return <h1>Title</h1>\n";
+
+#[tokio::test]
+async fn test_imports_between_modules_form_the_graph() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    write_project(
+        project_root,
+        None,
+        &[
+            ("components/Header.pyx", HEADER),
+            (
+                "pages/Home.pyx",
+                "from ..components.Header import Header\n\ndef Home():\n    return <Header />\n",
+            ),
+            (
+                "App.pyx",
+                "import json\nfrom pages import Home\n\ndef App():\n    from components.Header import Header\n    return <Home.Home />\n",
+            ),
+        ],
+    )?;
+
+    let root = project_root.to_str().unwrap();
+    let CompileSummary { errors, graph, .. } =
+        compile_all_pyx(root, "pyx.config.json", "python").await?;
+    assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+
+    let modules: Vec<&str> = graph.modules().collect();
+    assert_eq!(modules, ["App", "components.Header", "pages.Home"]);
+    assert_eq!(
+        graph.dependencies("App"),
+        BTreeSet::from(["components.Header", "pages.Home"])
+    );
+    assert_eq!(
+        graph.dependencies("pages.Home"),
+        BTreeSet::from(["components.Header"])
+    );
+    assert_eq!(
+        graph.dependents("components.Header"),
+        BTreeSet::from(["App", "pages.Home"])
+    );
+    assert_eq!(
+        graph.affected(&["pages.Home"]),
+        BTreeSet::from(["App".to_string(), "pages.Home".to_string()])
+    );
+
+    let header = project_root.join("src/components/Header.pyx");
+    assert_eq!(graph.module_of(&header), Some("components.Header"));
+    assert_eq!(graph.affected_files(&[header]).len(), 3);
+    assert!(graph.cycles().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_import_cycles_are_reported() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    write_project(
+        project_root,
+        None,
+        &[
+            (
+                "components/Card.pyx",
+                "from components.Button import Button\n\ndef Card():\n    return <Button />\n",
+            ),
+            (
+                "components/Button.pyx",
+                "from components.Icon import Icon\n\ndef Button():\n    return <Icon />\n",
+            ),
+            (
+                "components/Icon.pyx",
+                "import json\nfrom components.Card import Card\n\ndef Icon():\n    return <i />\n",
+            ),
+        ],
+    )?;
+
+    let root = project_root.to_str().unwrap();
+    let summary = compile_all_pyx(root, "pyx.config.json", "python").await?;
+    assert_eq!(summary.errors.len(), 1, "{:?}", summary.errors);
+    let diagnostic = summary.errors[0].diagnostic().expect("a diagnostic");
+    assert_eq!(diagnostic.code, "PYX018");
+    assert_eq!(
+        diagnostic.message,
+        "Import cycle: components.Button -> components.Icon -> components.Card -> components.Button"
+    );
+    assert_eq!(diagnostic.line, 1);
+    assert!(diagnostic.file.as_deref().unwrap().ends_with("Button.pyx"));
+    assert_eq!(summary.compiled.len(), 2);
+
+    // The file of the cycle is checked again, even though it did not change
+    let summary = compile_all_pyx(root, "pyx.config.json", "python").await?;
+    assert_eq!(summary.errors.len(), 1);
+
+    // An import inside a function runs after the modules are loaded
+    write_project(
+        project_root,
+        None,
+        &[(
+            "components/Icon.pyx",
+            "def Icon():\n    from components.Card import Card\n    return <i />\n",
+        )],
+    )?;
+    let summary = compile_all_pyx(root, "pyx.config.json", "python").await?;
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_changed_files_recompile_their_importers() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_root = temp_dir.path();
+    write_project(
+        project_root,
+        None,
+        &[
+            ("components/Header.pyx", HEADER),
+            ("components/Footer.pyx", "def Footer():\n    return <footer />\n"),
+            (
+                "pages/Home.pyx",
+                "from components.Header import Header\n\ndef Home():\n    return <Header />\n",
+            ),
+            (
+                "App.pyx",
+                "from pages.Home import Home\nfrom components.Footer import Footer\n\ndef App():\n    return <Home />\n",
+            ),
+        ],
+    )?;
+    let root = project_root.to_str().unwrap();
+    let config = Config::default();
+    let summary = compile_all_pyx_with(root, &config, "python", BuildOptions::default()).await?;
+    assert_eq!(summary.compiled.len(), 4);
+
+    let header = project_root.join("src/components/Header.pyx");
+    fs::write(&header, "def Header():\n    return <h1>New title</h1>\n")?;
+    let options = BuildOptions {
+        changed: Some(vec![header]),
+        ..BuildOptions::default()
+    };
+    let summary = compile_all_pyx_with(root, &config, "python", options).await?;
+    let mut compiled: Vec<String> = summary
+        .compiled
+        .iter()
+        .map(|file| _core::compiler::python_module_name(Path::new(file)))
+        .collect();
+    compiled.sort();
+    assert_eq!(compiled, ["App", "components.Header", "pages.Home"]);
+    assert_eq!(summary.unchanged.len(), 1);
+    assert!(summary.unchanged[0].ends_with("Footer.pyx"));
+
+    let output = fs::read_to_string(project_root.join("build/components/components/Header.py"))?;
+    assert!(output.contains("New title"));
+    Ok(())
+}
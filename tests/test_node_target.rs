@@ -0,0 +1,210 @@
+mod common;
+
+use _core::compiler::compile_all_pyx;
+use anyhow::Result;
+use common::write_project;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn test_components_compile_to_es_modules() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    write_project(
+        root,
+        None,
+        &[
+            (
+                "App.pyx",
+                concat!(
+                    "from components.Counter import Counter\n",
+                    "\n",
+                    "def App():\n",
+                    "    \"\"\"The page\"\"\"\n",
+                    "    return <><Counter start={1} /></>\n",
+                ),
+            ),
+            (
+                "components/Counter.pyx",
+                concat!(
+                    "from reactpyx import use_state\n",
+                    "\n",
+                    "STEP = 1\n",
+                    "\n",
+                    "def Counter(props):\n",
+                    "    count, set_count = use_state(props.get(\"start\", 0))\n",
+                    "    if count > 10:\n",
+                    "        label = f\"Many ({count})\"\n",
+                    "    else:\n",
+                    "        label = \"Few\"\n",
+                    "    def increment(event):\n",
+                    "        set_count.set(count + STEP)\n",
+                    "    items = [<li key={i}>{i}</li> for i in range(count) if i % 2 == 0]\n",
+                    "    return (\n",
+                    "        <div className=\"counter\">\n",
+                    "            <button onClick={increment} disabled={count is None}>{label}</button>\n",
+                    "            <ul>{items}</ul>\n",
+                    "        </div>\n",
+                    "    )\n",
+                ),
+            ),
+        ],
+    )?;
+
+    let summary = compile_all_pyx(root.to_str().unwrap(), "pyx.config.json", "node").await?;
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+    let node_dir = root.join("build").join("node");
+    let app = fs::read_to_string(node_dir.join("App.mjs"))?;
+    assert!(app.starts_with("import { Fragment, createElement } from \"react\";\n"));
+    assert!(app.contains("import { Counter } from \"./components/Counter.mjs\";"));
+    assert!(app.contains("export function App() {"));
+    assert!(!app.contains("The page"), "docstrings are left out");
+    assert!(app.contains("createElement(Fragment, null, createElement(Counter, {"));
+
+    let counter = fs::read_to_string(node_dir.join("components").join("Counter.mjs"))?;
+    assert!(counter.contains("import { createElement, useState } from \"react\";"));
+    assert!(counter.contains("export const STEP = 1;"));
+    assert!(counter.contains("const [count, set_count] = useState(props.start ?? 0);"));
+    assert!(counter.contains("    let label;\n"), "{}", counter);
+    assert!(counter.contains("label = `Many (${count})`;"));
+    assert!(counter.contains("set_count(__add(count, STEP));"));
+    assert!(counter.contains("\nfunction __add(left, right) {"));
+    assert!(counter.contains(".filter((i)=>i % 2 === 0).map((i)=>createElement(\"li\""));
+    assert!(counter.contains("className: \"counter\""));
+    assert!(counter.contains("disabled: count == null"));
+    assert!(!counter.contains("hook_id"));
+
+    // The Python modules are still built
+    assert!(root
+        .join("build")
+        .join("components")
+        .join("App.py")
+        .exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_operators_keep_python_semantics() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    write_project(
+        root,
+        None,
+        &[(
+            "Todos.pyx",
+            concat!(
+                "def Todos(props):\n",
+                "    todos = props[\"todos\"]\n",
+                "    empty = not todos\n",
+                "    added = todos + [\"x\"]\n",
+                "    joined = todos + props[\"more\"]\n",
+                "    rule = \"=\" * 3\n",
+                "    has_a = \"a\" in props\n",
+                "    has_x = \"x\" in [\"x\", \"y\"]\n",
+                "    blank = todos == []\n",
+                "    title = props.get(\"title\") or \"Untitled\"\n",
+                "    if todos:\n",
+                "        todos += [\"y\"]\n",
+                "    done = len(todos) == 0 and not props[\"busy\"]\n",
+                "    return <p>{title}</p>\n",
+            ),
+        )],
+    )?;
+
+    let summary = compile_all_pyx(root.to_str().unwrap(), "pyx.config.json", "node").await?;
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    let todos = fs::read_to_string(root.join("build").join("node").join("Todos.mjs"))?;
+    for expected in [
+        "const empty = !__truthy(todos);",
+        "const added = [\n        ...todos,\n        ...[\n            \"x\"\n        ]\n    ];",
+        "const joined = __add(todos, props.more);",
+        "const rule = __mul(\"=\", 3);",
+        "const has_a = __contains(props, \"a\");",
+        "const has_x = [\n        \"x\",\n        \"y\"\n    ].includes(\"x\");",
+        "const blank = __eq(todos, []);",
+        "const title = __or(props.title, ()=>\"Untitled\");",
+        "if (__truthy(todos)) {",
+        "todos = [\n            ...todos,",
+        "const done = todos.length === 0 && !__truthy(props.busy);",
+    ] {
+        assert!(todos.contains(expected), "{} in\n{}", expected, todos);
+    }
+    // The helpers are declared once, after the imports, and not exported
+    assert!(
+        todos.starts_with("import { createElement } from \"react\";\nfunction __truthy(value) {")
+    );
+    assert_eq!(todos.matches("function __truthy(").count(), 1);
+    assert!(!todos.contains("export function __"));
+    assert!(!todos.contains("function __and("));
+
+    write_project(
+        root,
+        None,
+        &[(
+            "Todos.pyx",
+            "def Todos(props):\n    return <p>{\"%d left\" % props[\"count\"]}</p>\n",
+        )],
+    )?;
+    let summary = compile_all_pyx(root.to_str().unwrap(), "pyx.config.json", "node").await?;
+    let diagnostic = summary.errors[0].diagnostic().expect("a diagnostic");
+    assert_eq!(diagnostic.code, "PYX019");
+    assert_eq!(
+        diagnostic.message,
+        "`%` formatting cannot be compiled for the node target"
+    );
+    assert_eq!(diagnostic.help.as_deref(), Some("Use an f-string instead"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unsupported_python_fails_the_node_target() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    write_project(
+        root,
+        None,
+        &[(
+            "App.pyx",
+            concat!(
+                "import json\n",
+                "\n",
+                "def App(data):\n",
+                "    return <pre>{json.dumps(data)}</pre>\n",
+            ),
+        )],
+    )?;
+    let root = root.to_str().unwrap();
+
+    let summary = compile_all_pyx(root, "pyx.config.json", "node").await?;
+    assert_eq!(summary.errors.len(), 1);
+    let diagnostic = summary.errors[0].diagnostic().expect("a diagnostic");
+    assert_eq!(diagnostic.code, "PYX019");
+    assert_eq!(
+        diagnostic.message,
+        "The Python module `json` cannot be compiled for the node target"
+    );
+    assert_eq!((diagnostic.line, diagnostic.column), (1, 8));
+
+    fs::write(
+        Path::new(root).join("src").join("App.pyx"),
+        concat!(
+            "class App:\n",
+            "    def render(self):\n",
+            "        return <p>Hi</p>\n",
+        ),
+    )?;
+    let summary = compile_all_pyx(root, "pyx.config.json", "node").await?;
+    let diagnostic = summary.errors[0].diagnostic().expect("a diagnostic");
+    assert_eq!(
+        diagnostic.message,
+        "A class cannot be compiled for the node target"
+    );
+    assert_eq!(diagnostic.line, 1);
+
+    // The python target compiles the same module
+    let summary = compile_all_pyx(root, "pyx.config.json", "python").await?;
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    Ok(())
+}
@@ -20,6 +20,9 @@ try:
         use_reducer,
         use_context,
         VNode,
+        create_element,
+        Fragment,
+        PyxSyntaxError,
     )
 except ImportError:
     pytest.skip(
@@ -214,6 +217,186 @@ class TestSessionIsolation:
             reset_current_session_id(token_a)
 
 
+class TestCreateElement:
+    def test_text_and_expression_children(self):
+        count = 3
+        node = create_element(
+            "p", {"class": "counter"}, ["count is ", (count), None, False]
+        )
+
+        assert node.render() == '<p class="counter">count is 3</p>'
+
+    def test_nested_lists_and_escaping(self):
+        items = ["<a>", "b & c"]
+        node = create_element(
+            "ul", {}, [[create_element("li", {"key": i}, [i]) for i in items]]
+        )
+
+        assert node.render() == "<ul><li>&lt;a&gt;</li><li>b &amp; c</li></ul>"
+        assert node.children[0].key == "<a>"
+
+    def test_style_and_script_text_is_not_escaped(self):
+        css = '.a > b { content: "x"; }'
+        script = 'if (a < b && c > d) s = "</script>";'
+        node = create_element(
+            "div",
+            {},
+            [
+                create_element("style", {}, [css]),
+                create_element("script", {}, [script]),
+            ],
+        )
+
+        assert node.render() == (
+            '<div><style>.a > b { content: "x"; }</style>'
+            '<script>if (a < b && c > d) s = "<\\/script>";</script></div>'
+        )
+
+    def test_boolean_attributes_and_spread_class_name(self):
+        spread = {"className": "c"}
+        node = create_element(
+            "input", {"disabled": True, "checked": False, "value": None, **spread}
+        )
+
+        assert node.props["class"] == "c"
+        html = node.render()
+        assert " disabled" in html and "checked" not in html and "value" not in html
+
+    def test_components_are_called_with_props(self):
+        def Greeting(props):
+            return create_element("h1", {}, ["Hello ", props["name"], props["children"]])
+
+        def Cells():
+            return [create_element("td", {}, ["a"]), "b"]
+
+        node = create_element(
+            "div",
+            {},
+            [create_element(Greeting, {"name": "Ada"}, ["!"]), create_element(Cells)],
+        )
+
+        assert node.render() == "<div><h1>Hello Ada!</h1><td>a</td>b</div>"
+        greeting = node.children[0]
+        assert greeting.is_component and greeting.tag == "Greeting"
+        assert greeting.rendered.render() == "<h1>Hello Ada!</h1>"
+        assert greeting.render_component().render() == "<h1>Hello Ada!</h1>"
+
+    def test_fragments_are_flattened(self):
+        from reactpyx._core import diff_nodes, Patch
+
+        old = create_element("tr", {}, [create_element("td", {}, ["a"])])
+        new = create_element(
+            "tr",
+            {},
+            [
+                create_element(
+                    Fragment,
+                    {},
+                    [create_element("td", {}, ["a"]), create_element("td", {}, ["b"])],
+                )
+            ],
+        )
+
+        assert new.render() == "<tr><td>a</td><td>b</td></tr>"
+        patches = diff_nodes(old, new)
+        assert len(patches) == 1 and isinstance(patches[0], Patch.AddChild)
+        assert patches[0].child.render() == "<td>b</td>"
+
+
+class TestHookIdentity:
+    def test_short_form_hooks_are_scoped_to_component_instances(self):
+        from reactpyx._core import parse_jsx
+
+        source = (
+            "def Counter(props):\n"
+            "    count, set_count = use_state(props['start'])\n"
+            "    total, dispatch = use_reducer(lambda s, a: s + a, 0)\n"
+            "    setters.append((set_count, dispatch))\n"
+            "    return <b>{count}/{total}</b>\n"
+            "\n"
+            "def App():\n"
+            "    return <p><Counter start={1} /><Counter start={5} /></p>\n"
+        )
+        setters = []
+        namespace = {
+            "create_element": create_element,
+            "use_state": use_state,
+            "use_reducer": use_reducer,
+            "setters": setters,
+        }
+        exec(parse_jsx(source, "app"), namespace)
+
+        token = set_current_session_id("hook_identity_session")
+        try:
+            manager = RuntimeManager(namespace["App"])
+            assert manager.render() == "<p><b>1/0</b><b>5/0</b></p>"
+
+            second = manager.current_vdom.children[1]
+            assert second.instance_path == "App/1:Counter"
+
+            set_count, dispatch = setters[0]
+            set_count.set(2)
+            dispatch.dispatch(3)
+            assert manager.render() == "<p><b>2/3</b><b>5/0</b></p>"
+        finally:
+            reset_current_session_id(token)
+
+    def test_short_form_requires_a_hook_id(self):
+        with pytest.raises(TypeError, match="compiled .pyx component"):
+            use_state(0)
+        with pytest.raises(TypeError, match="got 2 positional arguments"):
+            use_state("comp", 0)
+
+
+class TestDiagnostics:
+    def test_syntax_errors_are_structured(self):
+        from reactpyx import _core
+
+        with pytest.raises(PyxSyntaxError) as info:
+            _core.parse_jsx("x = <div>\n  <span></div>")
+
+        error = info.value
+        assert isinstance(error, SyntaxError)
+        assert error.code == "PYX007"
+        assert (error.lineno, error.column) == (2, 11)
+        assert error.span == (20, 23)
+        assert error.severity == "error"
+        assert "<span> must be closed" in error.help
+
+
+class TestTracebackMapping:
+    def test_handler_errors_point_at_pyx_source(self, tmp_path, capsys):
+        import importlib.util
+        from reactpyx import _core
+
+        (tmp_path / "src").mkdir()
+        (tmp_path / "src" / "App.pyx").write_text(
+            "def App():\n    return <p>{missing}</p>\n"
+        )
+        (tmp_path / "pyx.config.json").write_text(
+            json.dumps({"compilerOptions": {"sourceMaps": True}})
+        )
+        _core.compile_all_pyx_py(str(tmp_path), "pyx.config.json", "python")
+
+        module_path = tmp_path / "build" / "components" / "App.py"
+        spec = importlib.util.spec_from_file_location("App", module_path)
+        module = importlib.util.module_from_spec(spec)
+        spec.loader.exec_module(module)
+
+        assert _core.map_pyx_location(str(module_path), 3)[1:] == (2, 1)
+
+        handler_id = register_handler(lambda event: module.App())
+        manager = RuntimeManager(lambda: VNode("div", {}, [], False, 0, None))
+        result = manager.handle_event({"target_id": handler_id})
+
+        # The client gets the message; the mapped traceback stays on stderr
+        assert "missing" in result["error"]
+        assert set(result) == {"error"}
+        stderr = capsys.readouterr().err
+        assert 'App.pyx", line 2, column 16, in App' in stderr
+        assert "> 2 |     return <p>{missing}</p>" in stderr
+
+
 @pytest.mark.asyncio
 class TestServerFlow:
     async def test_connection_manager_flow(self):
@@ -0,0 +1,77 @@
+use _core::diagnostics::code_frame;
+use _core::jsx_transformer::transform_pyx_mapped;
+use _core::source_map::{build_source_map, lookup, parse_source_map, source_map_json};
+use _core::traceback_mapper::map_location;
+use std::fs;
+use tempfile::TempDir;
+
+const SOURCE: &str = r#"def App():
+    label = "héllo"
+    return (
+        <main>
+            <p title={label}>{missing}</p>
+        </main>
+    )
+"#;
+
+#[test]
+fn test_mappings_round_trip() {
+    let output = transform_pyx_mapped(SOURCE).unwrap();
+    let map = build_source_map("App.py", "App.pyx", SOURCE, &output.code, &output.mappings);
+    let json = source_map_json(&map);
+
+    let decoded = parse_source_map(&json).unwrap();
+    assert_eq!(decoded.get_token_count(), map.get_token_count());
+    assert_eq!(decoded.get_source_contents(0).map(|s| &**s), Some(SOURCE));
+
+    // Line 5 of the output holds the `<p>` element of line 5 of the source
+    let line = output.code.lines().nth(4).unwrap();
+    let column = line.find("create_element").unwrap() as u32;
+    let token = lookup(&decoded, 4, column).unwrap();
+    assert_eq!(token.get_src(), (4, 12));
+}
+
+#[test]
+fn test_map_location_reads_map_next_to_module() {
+    let temp_dir = TempDir::new().unwrap();
+    let components = temp_dir.path().join("build").join("components");
+    fs::create_dir_all(&components).unwrap();
+
+    let output = transform_pyx_mapped(SOURCE).unwrap();
+    let map = build_source_map(
+        "App.py",
+        "../../src/App.pyx",
+        SOURCE,
+        &output.code,
+        &output.mappings,
+    );
+    let module = components.join("App.py");
+    fs::write(&module, &output.code).unwrap();
+    fs::write(components.join("App.py.map"), source_map_json(&map)).unwrap();
+
+    let column = output
+        .code
+        .lines()
+        .nth(4)
+        .unwrap()
+        .find("(missing)")
+        .unwrap();
+    let location = map_location(&module, 5, Some(column)).unwrap();
+    assert!(location.file.ends_with("src/App.pyx"), "{}", location.file);
+    assert!(!location.file.contains(".."));
+    assert_eq!((location.line, location.column), (5, 30));
+
+    let location = map_location(&module, 2, None).unwrap();
+    assert_eq!((location.line, location.column), (2, 1));
+
+    assert!(map_location(&temp_dir.path().join("missing.py"), 1, None).is_none());
+}
+
+#[test]
+fn test_code_frame_points_at_column() {
+    let frame = code_frame(SOURCE, 2, 13, 1, false);
+    assert_eq!(
+        frame,
+        "  1 | def App():\n> 2 |     label = \"héllo\"\n    |             ^\n  3 |     return (\n  4 |         <main>\n"
+    );
+}